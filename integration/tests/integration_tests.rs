@@ -1,4 +1,7 @@
-use std::process::Command;
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
 
 #[derive(Debug)]
 enum Lang {
@@ -58,6 +61,94 @@ fn test_cases(lang: Lang) {
     run_tests(input_expected, &lang);
 }
 
+#[test]
+fn test_rust_stdin() {
+    let (output, success) = run_piped(&[], "10 + 2\n\nx0 / 4\n");
+    assert_eq!(output, "x0 = 12.0\nx1 = 3.0\n");
+    assert!(success);
+
+    let (output, success) = run_piped(&["--raw"], "10 + 2\n1 / 4\n");
+    assert_eq!(output, "12.0\n0.25\n");
+    assert!(success);
+
+    let (output, success) = run_piped(&[], "10 + 2\nhi\n1 / 0\nx0 * 2\n");
+    assert_eq!(output, "x0 = 12.0\nx1 = 24.0\n");
+    assert!(!success);
+
+    let (output, success) = run_piped(&["--json"], "1 / 0\n");
+    assert!(output.contains("\"kind\": \"DivideByZero\""));
+    assert!(!success);
+
+    let (output, success) = run_piped(&["--json"], ":bogus\n");
+    assert!(output.contains("\"error\""));
+    assert!(!success);
+}
+
+#[test]
+fn test_rust_json() {
+    let output = Command::new("../rust/target/debug/cli_calculator")
+        .args(["--json", "-e", "10 + 2;x1 * 2;1 / 4;5.2= *2"])
+        .output()
+        .unwrap();
+    let expected = concat!(
+        "{\"input\": \"10 + 2\", \"name\": \"x0\", \"value\": 12, \"type\": \"integer\"}\n",
+        "{\"input\": \"x1 * 2\", \"error\": {\"kind\": \"UnknownVariable\", ",
+        "\"message\": \"Unknown Variable: x1\", \"span\": [0, 2]}}\n",
+        "{\"input\": \"1 / 4\", \"name\": \"x1\", \"value\": 0.25, \"type\": \"float\"}\n",
+        "{\"input\": \"5.2= *2\", \"error\": {\"kind\": \"InvalidCharacter\", ",
+        "\"message\": \"Unexpected character \\\"=\\\" at position 4\", \"span\": [3, 4]}}\n",
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_rust_arguments() {
+    let output = Command::new("../rust/target/debug/cli_calculator")
+        .args(["--raw", "-2^2 + 5"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1.0\n");
+    assert!(output.status.success());
+
+    let sum = vec!["1"; 5000].join("+");
+    let output = Command::new("../rust/target/debug/cli_calculator")
+        .args(["--raw", &sum])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5000.0\n");
+    assert!(output.status.success());
+
+    let output = Command::new("../rust/target/debug/cli_calculator")
+        .args(["--define", "pi=3", "-e", "1"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Cannot assign to constant: pi"));
+    assert!(!output.status.success());
+}
+
+fn run_piped(args: &[&str], input: &str) -> (String, bool) {
+    let mut child = Command::new("../rust/target/debug/cli_calculator")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from(String::from_utf8_lossy(&output.stdout)),
+        output.status.success(),
+    )
+}
+
 fn run_tests(input_expected: Vec<(&str, &str)>, lang: &Lang) {
     let mut results = Vec::new();
     let line = "-".repeat(50);
@@ -0,0 +1,65 @@
+//! Compares the ways an expression can be evaluated many times.
+//!
+//! Run with `cargo bench`. Each case evaluates the same formula over the same
+//! rows and reports the time per evaluation.
+
+use std::{hint::black_box, time::Instant};
+
+use cli_calculator::{ast::AST, calculator::Calculator, tokenize};
+
+const ROWS: usize = 200_000;
+const FORMULA: &str = "price * qty * (1 + vat) - min(discount, price * qty) / 2";
+
+fn rows() -> Vec<[f64; 3]> {
+    (0..ROWS)
+        .map(|i| {
+            let i = i as f64;
+            [1.0 + i % 97.0, 1.0 + i % 13.0, i % 7.0]
+        })
+        .collect()
+}
+
+fn bench(name: &str, rows: &[[f64; 3]], mut evaluate: impl FnMut(&[f64; 3]) -> f64) {
+    let start = Instant::now();
+    let mut total = 0.0;
+    for row in rows {
+        total += evaluate(black_box(row));
+    }
+    let elapsed = start.elapsed();
+    black_box(total);
+    println!(
+        "{:<12} {:>8.1} ns/eval  ({:.2?} total)",
+        name,
+        elapsed.as_nanos() as f64 / rows.len() as f64,
+        elapsed
+    );
+}
+
+fn main() {
+    let rows = rows();
+    let mut calculator = Calculator::new();
+    calculator.set_var("vat", 0.2).unwrap();
+
+    bench("parse+walk", &rows, |[price, qty, discount]| {
+        calculator.set_var("price", *price).unwrap();
+        calculator.set_var("qty", *qty).unwrap();
+        calculator.set_var("discount", *discount).unwrap();
+        let tree = AST::build_tree_spanned(&tokenize(FORMULA).unwrap()).unwrap();
+        tree.eval(&calculator).unwrap().as_number().unwrap()
+    });
+
+    let tree = AST::build_tree_spanned(&tokenize(FORMULA).unwrap()).unwrap();
+    bench("tree walker", &rows, |[price, qty, discount]| {
+        calculator.set_var("price", *price).unwrap();
+        calculator.set_var("qty", *qty).unwrap();
+        calculator.set_var("discount", *discount).unwrap();
+        tree.eval(&calculator).unwrap().as_number().unwrap()
+    });
+
+    let compiled = calculator
+        .compile_with(FORMULA, &["price", "qty", "discount"])
+        .unwrap();
+    bench("vm", &rows, |row| {
+        compiled.eval_numbers(&calculator, row).unwrap()
+    });
+}
@@ -1,31 +1,44 @@
 #[cfg(test)]
 mod tests;
 
-use crate::tokens::{Num, Op, ParenType, Token};
+use crate::datetime::{self, DateTime};
+use crate::decimal;
+use crate::environment::Environment;
+use crate::error::{CalcError, ErrorKind, Span};
+use crate::forms;
+use crate::list;
+use crate::money;
+use crate::number_theory;
+use crate::printer::is_negation;
+use crate::tokens::{Cmp, Num, Op, ParenType, Token};
+use crate::units::{self, Quantity, Unit};
+use crate::value::Value;
 use std::{boxed::Box, cmp::Ordering, collections::HashMap, fmt::Display};
 
-#[derive(Debug)]
+/// How deep a tree may be. Evaluating and printing recurse through the tree,
+/// so deeper input would overflow the stack.
+const MAX_DEPTH: usize = 256;
+
+/// How deeply parens, minus signs and exponents may nest. Each level takes
+/// several parser frames, so this is lower than `MAX_DEPTH`.
+const MAX_NESTING: usize = 128;
+
+#[derive(Debug, Clone)]
 pub struct AST {
     value: Token,
     left: Option<Box<AST>>,
     right: Option<Box<AST>>,
-}
-
-#[derive(Debug, Clone)]
-struct WeightedToken {
-    token: Token,
-    weight: i32,
-}
-
-impl Display for WeightedToken {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "('{}',{})", self.token, self.weight)
-    }
+    span: Option<Span>,
 }
 
 impl AST {
     fn new(value: Token, left: Option<Box<AST>>, right: Option<Box<AST>>) -> Box<Self> {
-        let ast = AST { left, right, value };
+        let ast = AST {
+            left,
+            right,
+            value,
+            span: None,
+        };
         Box::new(ast)
     }
 
@@ -33,373 +46,1307 @@ impl AST {
         Self::new(value, None, None)
     }
 
-    pub fn evaluate(ast: Box<Self>, env: &HashMap<String, f64>) -> Result<f64, String> {
-        let mut stack = vec![ast];
-        let mut resolve_stack: Vec<f64> = vec![];
-        while !stack.is_empty() {
-            let mut item = stack.pop().unwrap();
-            match &item.value {
-                Token::Operator(oper) => {
-                    let next: Box<Self>;
-                    if let Some(left) = item.left {
-                        next = left;
-                        item.left = None;
-                        stack.push(item);
-                        stack.push(next);
-                    } else if let Some(right) = item.right {
-                        next = right;
-                        item.right = None;
-                        stack.push(item);
-                        stack.push(next);
-                    } else {
-                        let right = resolve_stack.pop();
-                        let left = resolve_stack.pop();
-                        if left.is_some() && right.is_some() {
-                            resolve_stack.push(Self::evaluate_operator(
-                                oper,
-                                left.unwrap(),
-                                right.unwrap(),
-                            )?);
-                        } else {
-                            return Err("Invalid Expression".to_string());
-                        }
-                    }
+    pub fn new_operator(oper: Op, left: Box<AST>, right: Box<AST>) -> Box<Self> {
+        let span = Self::join_spans(left.span, right.span);
+        Self::new(Token::Operator(oper), Some(left), Some(right)).with_span(span)
+    }
+
+    /// Builds an equation `left = right`, as written in `solve(x^2 = 2, x)`.
+    pub fn new_comparison(cmp: Cmp, left: Box<AST>, right: Box<AST>) -> Box<Self> {
+        let span = Self::join_spans(left.span, right.span);
+        AST::new(Token::Comparison(cmp), Some(left), Some(right)).with_span(span)
+    }
+
+    pub fn new_equation(left: Box<AST>, right: Box<AST>) -> Box<Self> {
+        let span = Self::join_spans(left.span, right.span);
+        Self::new(Token::Equals, Some(left), Some(right)).with_span(span)
+    }
+
+    /// Builds `operand°`, an angle in degrees converted to the angle mode.
+    pub fn new_degrees(operand: Box<AST>) -> Box<Self> {
+        let span = operand.span;
+        Self::new(Token::Degree, Some(operand), None).with_span(span)
+    }
+
+    /// Builds `operand unit`, a number with a unit as in `5 km`.
+    pub fn new_quantity(operand: Box<AST>, unit: Unit) -> Box<Self> {
+        let span = operand.span;
+        Self::new(Token::Unit(unit), Some(operand), None).with_span(span)
+    }
+
+    /// Builds `operand to unit`, as in `60 mph to m/s`.
+    pub fn new_conversion(operand: Box<AST>, unit: Unit) -> Box<Self> {
+        let span = operand.span;
+        Self::new(Token::Conversion(unit), Some(operand), None).with_span(span)
+    }
+
+    /// Builds `operand currency`, an amount of money as in `120 USD`.
+    pub fn new_money(operand: Box<AST>, currency: &str) -> Box<Self> {
+        let span = operand.span;
+        Self::new(Token::Currency(currency.to_owned()), Some(operand), None).with_span(span)
+    }
+
+    /// Builds `operand to currency`, as in `120 USD to EUR`.
+    pub fn new_exchange(operand: Box<AST>, currency: &str) -> Box<Self> {
+        let span = operand.span;
+        Self::new(Token::Exchange(currency.to_owned()), Some(operand), None).with_span(span)
+    }
+
+    /// Builds a duration from quantities of time, as in `3d 4h`, over their
+    /// sum.
+    pub fn new_duration(parts: Box<AST>) -> Box<Self> {
+        let span = parts.span;
+        Self::new(Token::Duration, Some(parts), None).with_span(span)
+    }
+
+    /// Builds a call node. Arguments hang off the left child as a chain of
+    /// `,` nodes: `f(a, b, c)` is `f` over `',' (a, ',' (b, c))`.
+    pub fn new_call(name: &str, args: Vec<Box<AST>>) -> Box<Self> {
+        Self::new_sequence(Token::new_function(name), args.into_iter())
+    }
+
+    /// Builds a list literal, with its elements chained like call arguments.
+    pub fn new_list(items: Vec<Box<AST>>) -> Box<Self> {
+        Self::new_sequence(Token::List, items.into_iter())
+    }
+
+    fn new_sequence(value: Token, items: impl DoubleEndedIterator<Item = Box<AST>>) -> Box<Self> {
+        let chain = items.rev().reduce(|rest, item| {
+            let span = Self::join_spans(item.span, rest.span);
+            Self::new(Token::Comma, Some(item), Some(rest)).with_span(span)
+        });
+        let span = chain.as_ref().and_then(|items| items.span);
+        Self::new(value, chain, None).with_span(span)
+    }
+
+    /// Builds `list[index]`.
+    pub fn new_index(list: Box<AST>, index: Box<AST>) -> Box<Self> {
+        let span = Self::join_spans(list.span, index.span);
+        Self::new(Token::Index, Some(list), Some(index)).with_span(span)
+    }
+
+    /// Builds `from..to`, the whole numbers from one to the other.
+    pub fn new_range(from: Box<AST>, to: Box<AST>) -> Box<Self> {
+        let span = Self::join_spans(from.span, to.span);
+        Self::new(Token::Range, Some(from), Some(to)).with_span(span)
+    }
+
+    /// Builds `params -> body`, as in `(acc, x) -> acc + x`.
+    pub fn new_lambda(params: Vec<String>, body: Box<AST>) -> Box<Self> {
+        let span = body.span;
+        Self::new(Token::Lambda(params), Some(body), None).with_span(span)
+    }
+
+    fn with_span(mut self: Box<Self>, span: Option<Span>) -> Box<Self> {
+        self.span = span;
+        self
+    }
+
+    fn join_spans(start: Option<Span>, end: Option<Span>) -> Option<Span> {
+        match (start, end) {
+            (Some((start, _)), Some((_, end))) => Some((start, end)),
+            (span, None) | (None, span) => span,
+        }
+    }
+
+    pub fn value(&self) -> &Token {
+        &self.value
+    }
+
+    pub fn left(&self) -> Option<&AST> {
+        self.left.as_deref()
+    }
+
+    pub fn right(&self) -> Option<&AST> {
+        self.right.as_deref()
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Whether `other` is the same tree, ignoring where the nodes came from.
+    pub fn equivalent(&self, other: &AST) -> bool {
+        let same_child = |a: Option<&AST>, b: Option<&AST>| match (a, b) {
+            (Some(a), Some(b)) => a.equivalent(b),
+            (None, None) => true,
+            _ => false,
+        };
+        self.value == other.value
+            && same_child(self.left(), other.left())
+            && same_child(self.right(), other.right())
+    }
+
+    /// The arguments of a call node or the elements of a list, in order.
+    pub fn arguments(&self) -> Vec<&AST> {
+        let mut args = vec![];
+        let mut next = self.left.as_deref();
+        while let Some(node) = next {
+            match (&node.value, node.left.as_deref()) {
+                (Token::Comma, Some(arg)) => {
+                    args.push(arg);
+                    next = node.right.as_deref();
                 }
-                Token::Variable(var) => {
-                    resolve_stack.push(Self::evaluate_variable(var, env)?);
+                _ => {
+                    args.push(node);
+                    next = None;
                 }
-                Token::Number(num) => {
-                    resolve_stack.push(Self::evaluate_number(num)?);
+            }
+        }
+        args
+    }
+
+    /// How many operators, calls and other nodes lie on the longest path from
+    /// the root to a leaf. The items of a call or list count once, as they are
+    /// evaluated one after another rather than nested, and so does a chain
+    /// such as `1 + 2 - 3 + 4`, whose left operands `eval` walks in a loop.
+    /// Walks the tree with an explicit stack so that any tree can be measured.
+    fn depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut nodes = vec![(self, 1)];
+        while let Some((node, depth)) = nodes.pop() {
+            deepest = deepest.max(depth);
+            if let Some(left) = &node.left {
+                let next = if node.chained() { depth } else { depth + 1 };
+                nodes.push((left, next));
+            }
+            if let Some(right) = &node.right {
+                let next = if node.value == Token::Comma {
+                    depth
+                } else {
+                    depth + 1
+                };
+                nodes.push((right, next));
+            }
+        }
+        deepest
+    }
+
+    /// Whether the node is `+`, `-`, `*` or `/`, whose left operand is the
+    /// rest of a chain like `1 + 2 + 3` rather than a nested expression.
+    pub(crate) fn chained(&self) -> bool {
+        matches!(
+            self.value,
+            Token::Operator(Op::Add | Op::Sub | Op::Mult | Op::Div)
+        ) && self.left.is_some()
+            && self.right.is_some()
+            && !is_negation(self)
+    }
+
+    /// Splits a chain such as `1 + 2 * 3 - 4` along its left operands: the
+    /// first operand, then each operator node with its operator and right
+    /// operand in the order they apply. Evaluating, printing and the other
+    /// walks fold over the links in a loop, so a long sum does not recurse
+    /// once per term. A negation `-1 * x` is an operand rather than a link.
+    pub(crate) fn chain(&self) -> (&AST, Vec<(&AST, &Op, &AST)>) {
+        let mut links = vec![];
+        let mut first = self;
+        while let (Token::Operator(oper), Some(left), Some(right)) =
+            (&first.value, first.left.as_deref(), first.right.as_deref())
+        {
+            if !first.chained() {
+                break;
+            }
+            links.push((first, oper, right));
+            first = left;
+        }
+        links.reverse();
+        (first, links)
+    }
+
+    // Takes the tree by value as it always has, so existing callers keep working.
+    #[allow(clippy::boxed_local)]
+    pub fn evaluate(ast: Box<Self>, env: &HashMap<String, f64>) -> Result<f64, CalcError> {
+        let value = ast.eval(env)?;
+        value.as_number().ok_or_else(|| {
+            CalcError::new(
+                ErrorKind::TypeMismatch,
+                &format!("Expected a number but got {}", value.described()),
+            )
+        })
+    }
+
+    /// Evaluates the tree without consuming it, resolving names through `env`.
+    pub fn eval(&self, env: &dyn Environment) -> Result<Value, CalcError> {
+        match &self.value {
+            Token::Operator(_) if self.chained() => self.evaluate_chain(env),
+            Token::Operator(oper) => match (&self.left, &self.right) {
+                (Some(left), Some(right)) => {
+                    let left = left.eval(env)?;
+                    let right = right.eval(env)?;
+                    Self::evaluate_operator(oper, left, right, env).map_err(|e| self.locate(e))
                 }
-                x => Err(format!("Cannot evaluate {}", x))?,
+                _ => Err(CalcError::invalid_expression()),
+            },
+            Token::Comparison(cmp) => match (&self.left, &self.right) {
+                (Some(left), Some(right)) => {
+                    let left = left.eval(env)?;
+                    let right = right.eval(env)?;
+                    Self::evaluate_comparison(cmp, left, right, env).map_err(|e| self.locate(e))
+                }
+                _ => Err(CalcError::invalid_expression()),
+            },
+            Token::Variable(var) => Self::evaluate_variable(var, env).map_err(|e| self.locate(e)),
+            Token::Number(num) => Self::evaluate_number(num, env.decimal().is_some()),
+            Token::Function(name) => self.evaluate_call(name, env),
+            Token::Degree | Token::Unit(_) | Token::Conversion(_) => self.evaluate_units(env),
+            Token::Currency(currency) | Token::Exchange(currency) => {
+                self.evaluate_money(currency, env)
             }
+            Token::Date(_) | Token::Duration => self.evaluate_time(env),
+            Token::List | Token::Index | Token::Range | Token::Lambda(_) => self.evaluate_list(env),
+            Token::Equals => Err(self.locate(CalcError::new(
+                ErrorKind::InvalidExpression,
+                "An equation has no value; use solve to find its roots",
+            ))),
+            x => Err(CalcError::new(
+                ErrorKind::InvalidExpression,
+                &format!("Cannot evaluate {}", x),
+            )),
         }
+    }
 
-        if resolve_stack.len() != 1 {
-            Err("Invalid Expression".to_string())
-        } else {
-            resolve_stack.pop().ok_or("Invalid Expression".to_string())
+    /// A chain such as `1 + 2 * 3 - 4`, left to right. The left operands are
+    /// followed in a loop so that a long sum does not recurse once per term.
+    fn evaluate_chain(&self, env: &dyn Environment) -> Result<Value, CalcError> {
+        let (first, links) = self.chain();
+        let mut value = first.eval(env)?;
+        for (link, oper, right) in links {
+            let right = right.eval(env)?;
+            value = Self::evaluate_operator(oper, value, right, env).map_err(|e| link.locate(e))?;
+        }
+        Ok(value)
+    }
+
+    /// An angle in degrees, a quantity or a conversion to another unit. Kept
+    /// out of `eval` along with the other rarer nodes so its frame stays small.
+    fn evaluate_units(&self, env: &dyn Environment) -> Result<Value, CalcError> {
+        let operand = match &self.left {
+            Some(operand) => operand.eval(env)?,
+            None => return Err(CalcError::invalid_expression()),
+        };
+        match &self.value {
+            Token::Degree => match operand.inexact() {
+                Value::Number(x) => Ok(Value::Number(env.angle_mode().from_degrees(x))),
+                value => Err(self.locate(CalcError::new(
+                    ErrorKind::TypeMismatch,
+                    &format!("An angle must be a number, not {}", value.described()),
+                ))),
+            },
+            Token::Unit(unit) => {
+                let quantity = Quantity::try_from(operand).map_err(|e| self.locate(e))?;
+                let (unit, factor) = quantity.unit.combine(unit, 1);
+                Ok(Quantity::new(quantity.value * factor, unit).simplified())
+            }
+            Token::Conversion(unit) => {
+                let quantity = Quantity::try_from(operand).map_err(|e| self.locate(e))?;
+                let converted = quantity.convert(unit).map_err(|e| self.locate(e))?;
+                Ok(Value::Quantity(converted))
+            }
+            _ => Err(CalcError::invalid_expression()),
+        }
+    }
+
+    /// The operand as an amount of `currency`, converted if it is money in
+    /// another. Kept out of `eval` so its frame stays small for deep recursion.
+    fn evaluate_money(&self, currency: &str, env: &dyn Environment) -> Result<Value, CalcError> {
+        match &self.left {
+            Some(operand) => {
+                money::in_currency(operand.eval(env)?, currency, env).map_err(|e| self.locate(e))
+            }
+            None => Err(CalcError::invalid_expression()),
+        }
+    }
+
+    /// A date literal, or the sum of the parts of `3d 4h` as a duration.
+    fn evaluate_time(&self, env: &dyn Environment) -> Result<Value, CalcError> {
+        match (&self.value, &self.left) {
+            (Token::Date(text), _) => DateTime::parse(text)
+                .map(Value::Date)
+                .map_err(|e| self.locate(e)),
+            (Token::Duration, Some(parts)) => {
+                datetime::duration_of(parts.eval(env)?).map_err(|e| self.locate(e))
+            }
+            _ => Err(CalcError::invalid_expression()),
+        }
+    }
+
+    /// A list literal, an element of a list or a range.
+    fn evaluate_list(&self, env: &dyn Environment) -> Result<Value, CalcError> {
+        let result = match (&self.value, &self.left, &self.right) {
+            (Token::List, _, _) => {
+                let items = self.arguments().into_iter().map(|item| item.eval(env));
+                return items.collect::<Result<_, _>>().map(Value::List);
+            }
+            (Token::Index, Some(list), Some(index)) => {
+                list::index(list.eval(env)?, index.eval(env)?)
+            }
+            (Token::Range, Some(from), Some(to)) => list::range(from.eval(env)?, to.eval(env)?),
+            (Token::Lambda(_), _, _) => Err(CalcError::new(
+                ErrorKind::InvalidExpression,
+                "A lambda is only a value as an argument of map, filter or reduce",
+            )),
+            _ => Err(CalcError::invalid_expression()),
+        };
+        result.map_err(|e| self.locate(e))
+    }
+
+    fn locate(&self, error: CalcError) -> CalcError {
+        match (error.span, self.span) {
+            (None, Some(span)) => error.with_span(span),
+            _ => error,
         }
     }
 
-    fn evaluate_operator(oper: &Op, left: f64, right: f64) -> Result<f64, String> {
+    /// `left oper right`. Exact decimals stay exact, rounded as `env`'s
+    /// decimal mode says, until they meet a float or need a fractional power.
+    /// Exact integers stay integers while the result is one, and otherwise
+    /// become decimals in decimal mode and floats outside it.
+    pub(crate) fn evaluate_operator(
+        oper: &Op,
+        left: Value,
+        right: Value,
+        env: &dyn Environment,
+    ) -> Result<Value, CalcError> {
+        let (left, right) = match (left, right) {
+            (Value::Number(left), Value::Number(right)) => (left, right),
+            (left @ Value::List(_), right) | (left, right @ Value::List(_)) => {
+                return list::broadcast(oper, left, right, env)
+            }
+            (left @ Value::Factors(_), right) | (left, right @ Value::Factors(_)) => {
+                return Self::evaluate_operator(oper, left.inexact(), right.inexact(), env)
+            }
+            (Value::Integer(left), Value::Integer(right)) => {
+                match number_theory::operate(oper, left, right) {
+                    Some(result) => return Ok(Value::Integer(result)),
+                    None if env.decimal().is_some() => {
+                        let (left, right) = (Value::Integer(left), Value::Integer(right));
+                        return Self::evaluate_operator(oper, left.exact(), right.exact(), env);
+                    }
+                    None => (left as f64, right as f64),
+                }
+            }
+            (left @ Value::Integer(_), right) | (left, right @ Value::Integer(_)) => {
+                return Self::evaluate_operator(oper, left.exact(), right.exact(), env)
+            }
+            (Value::Decimal(left), Value::Decimal(right)) => {
+                let mode = env.decimal().unwrap_or_default();
+                match decimal::operate(oper, &left, &right, mode)? {
+                    Some(result) => return Ok(Value::Decimal(Box::new(result))),
+                    None => (left.to_f64(), right.to_f64()),
+                }
+            }
+            (left @ Value::Money(_), right) | (left, right @ Value::Money(_)) => {
+                return money::operate(oper, left, right)
+            }
+            (left @ (Value::Date(_) | Value::Duration(_)), right)
+            | (left, right @ (Value::Date(_) | Value::Duration(_))) => {
+                return datetime::operate(oper, left.inexact(), right.inexact())
+            }
+            (Value::Decimal(left), Value::Number(right)) => (left.to_f64(), right),
+            (Value::Number(left), Value::Decimal(right)) => (left, right.to_f64()),
+            (left, right) => return units::operate(oper, left.inexact(), right.inexact()),
+        };
         let result = match oper {
             Op::Add => left + right,
             Op::Sub => left - right,
             Op::Mult => left * right,
             Op::Div => {
                 if right == 0.0 {
-                    Err("Divide by Zero".to_owned())?
+                    Err(CalcError::new(ErrorKind::DivideByZero, "Divide by Zero"))?
                 }
                 left / right
             }
+            Op::Pow => left.powf(right),
         };
-        Ok(result)
+        Ok(Value::Number(result))
+    }
+
+    /// 1 if `left cmp right` holds and 0 if not. The sides are compared by
+    /// the sign of their difference, so quantities compare across units,
+    /// exact numbers compare exactly and lists compare element by element.
+    pub(crate) fn evaluate_comparison(
+        cmp: &Cmp,
+        left: Value,
+        right: Value,
+        env: &dyn Environment,
+    ) -> Result<Value, CalcError> {
+        let names = (left.type_name(), right.type_name());
+        let difference = Self::evaluate_operator(&Op::Sub, left, right, env)?;
+        Self::compare(cmp, difference).ok_or_else(|| {
+            CalcError::new(
+                ErrorKind::TypeMismatch,
+                &format!("Cannot compare {} with {}", names.0, names.1),
+            )
+        })
     }
 
-    fn evaluate_number(num: &Num) -> Result<f64, String> {
-        let result = match num {
-            Num::Float(x) => *x,
-            Num::Integer(x) => *x as f64,
+    /// 1 or 0 for whether `cmp` holds between two values that differ by
+    /// `difference`, or `None` if the difference has no sign.
+    fn compare(cmp: &Cmp, difference: Value) -> Option<Value> {
+        let sign = match difference {
+            Value::List(items) => {
+                let items = items.into_iter().map(|item| Self::compare(cmp, item));
+                return items.collect::<Option<_>>().map(Value::List);
+            }
+            Value::Integer(n) => Some(n.cmp(&0)),
+            Value::Decimal(x) => Some(Self::sign(x.is_zero(), x.is_negative())),
+            Value::Money(money) => Some(Self::sign(
+                money.amount.is_zero(),
+                money.amount.is_negative(),
+            )),
+            Value::Number(x) => x.partial_cmp(&0.0),
+            Value::Quantity(quantity) => quantity.value.partial_cmp(&0.0),
+            Value::Duration(duration) => duration.seconds.partial_cmp(&0.0),
+            _ => return None,
+        };
+        // NaN is neither less than, equal to nor greater than anything.
+        let holds = match (cmp, sign) {
+            (Cmp::NotEqual, sign) => sign != Some(Ordering::Equal),
+            (_, None) => false,
+            (Cmp::Less, Some(sign)) => sign.is_lt(),
+            (Cmp::LessEqual, Some(sign)) => sign.is_le(),
+            (Cmp::Greater, Some(sign)) => sign.is_gt(),
+            (Cmp::GreaterEqual, Some(sign)) => sign.is_ge(),
+            (Cmp::Equal, Some(sign)) => sign.is_eq(),
         };
-        Ok(result)
+        Some(Value::Integer(holds as i128))
     }
 
-    fn evaluate_variable(var: &String, env: &HashMap<String, f64>) -> Result<f64, String> {
-        if let Some(&value) = env.get(var) {
+    fn sign(zero: bool, negative: bool) -> Ordering {
+        match (zero, negative) {
+            (true, _) => Ordering::Equal,
+            (false, true) => Ordering::Less,
+            (false, false) => Ordering::Greater,
+        }
+    }
+
+    /// The value of a literal: an exact integer, a float, or with `exact` set
+    /// an exact decimal.
+    pub(crate) fn evaluate_number(num: &Num, exact: bool) -> Result<Value, CalcError> {
+        let result = match (num, exact) {
+            (Num::Float(x), _) => *x,
+            (Num::Integer(x), _) => return Ok(Value::Integer(*x as i128)),
+            (Num::Decimal(x), true) => return Ok(Value::Decimal(Box::new(*x))),
+            (Num::Decimal(x), false) => x.to_f64(),
+        };
+        Ok(Value::Number(result))
+    }
+
+    pub(crate) fn evaluate_variable(var: &str, env: &dyn Environment) -> Result<Value, CalcError> {
+        if let Some(value) = env.variable(var) {
             Ok(value)
         } else {
-            Err(format!("Unknown Variable: {}", var))
+            Err(CalcError::new(
+                ErrorKind::UnknownVariable,
+                &format!("Unknown Variable: {}", var),
+            ))
         }
     }
 
-    fn get_token_weight(token: &WeightedToken) -> Option<i32> {
-        match &token.token {
-            Token::Paren(_) => Some(token.weight - 2),
-            Token::Number(_) => Some(token.weight - 2),
-            Token::Variable(_) => Some(token.weight - 2),
-            Token::Operator(oper) => match oper {
-                Op::Add => Some(token.weight),
-                Op::Sub => Some(token.weight),
-                Op::Mult => Some(token.weight - 1),
-                Op::Div => Some(token.weight - 1),
-            },
-            _ => None,
+    /// Runs a special form, calls a function, or multiplies when a variable is followed by a
+    /// parenthesized expression, e.g. `x0(2 + 3)`.
+    fn evaluate_call(&self, name: &str, env: &dyn Environment) -> Result<Value, CalcError> {
+        let args = self.arguments();
+        if let Some((arity, form)) = forms::lookup(name) {
+            arity.check(args.len()).map_err(|e| self.locate(e))?;
+            return form(&args, env).map_err(|e| self.locate(e));
+        }
+        if let Some(function) = env.function(name) {
+            function
+                .arity()
+                .check(args.len())
+                .map_err(|e| self.locate(e))?;
+            let values = args
+                .iter()
+                .map(|arg| arg.eval(env))
+                .collect::<Result<Vec<Value>, CalcError>>()?;
+            return function.call(&values, env).map_err(|e| self.locate(e));
         }
-    }
 
-    fn find_root_index(tokens: &[WeightedToken]) -> usize {
-        let mut root_index = 0;
-        let mut weight = i32::MIN;
-        for (i, t) in tokens.iter().enumerate() {
-            if let Some(x) = Self::get_token_weight(t) {
-                if x >= weight {
-                    weight = x;
-                    root_index = i;
-                }
+        // Neither a form nor a function, so the name must be a variable to
+        // multiply, as in `x0(2)`.
+        let value = Self::evaluate_variable(name, env).map_err(|e| self.locate(e))?;
+        match args.as_slice() {
+            [arg] => {
+                let right = arg.eval(env)?;
+                Self::evaluate_operator(&Op::Mult, value, right, env).map_err(|e| self.locate(e))
             }
+            _ => Err(CalcError::invalid_expression()),
         }
-        root_index
     }
 
-    pub fn build_tree(tokens: &[Token]) -> Result<Box<Self>, String> {
-        let tokens = Self::handle_unary_minus(tokens)?;
-        Self::build_tree_weighted(&Self::process_parens(&tokens)?)
+    pub fn build_tree(tokens: &[Token]) -> Result<Box<Self>, CalcError> {
+        let tokens: Vec<(Token, Option<Span>)> =
+            tokens.iter().map(|token| (token.clone(), None)).collect();
+        Parser::new(tokens).parse()
     }
 
-    fn build_tree_weighted(tokens: &[WeightedToken]) -> Result<Box<Self>, String> {
-        let mut end = tokens.len();
-        if end == 0 {
-            Err("No tokens to parse")?
+    /// Like `build_tree`, but records where each node came from so errors can
+    /// point into the input.
+    pub fn build_tree_spanned(tokens: &[(Token, Span)]) -> Result<Box<Self>, CalcError> {
+        let tokens: Vec<(Token, Option<Span>)> = tokens
+            .iter()
+            .map(|(token, span)| (token.clone(), Some(*span)))
+            .collect();
+        Parser::new(tokens).parse()
+    }
+
+    /// Like `build_tree_spanned`, but also accepts an equation such as
+    /// `x^2 = 2` at the top level.
+    pub fn build_equation_spanned(tokens: &[(Token, Span)]) -> Result<Box<Self>, CalcError> {
+        let tokens: Vec<(Token, Option<Span>)> = tokens
+            .iter()
+            .map(|(token, span)| (token.clone(), Some(*span)))
+            .collect();
+        Parser::new(tokens).parse_root(true)
+    }
+}
+
+/// Recursive descent parser over the lexed tokens.
+///
+/// ```text
+/// root       := range (('to' | 'in') (unit | currency))*
+/// range      := comparison ('..' comparison)?
+/// comparison := expression (('<' | '<=' | '>' | '>=' | '==' | '!=') expression)?
+/// expression := term (('+' | '-') term)*
+/// term       := unary (('*' | '/') unary | implied)*
+/// unary      := '-' unary | power
+/// power      := degrees ('^' unary)?
+/// degrees    := postfix '°'*
+/// postfix    := primary ('[' range ']')*
+/// primary    := number (unit duration? | currency)? | variable currency?
+///             | variable '(' arguments ')' | '(' range ')' currency?
+///             | '[' (range (',' range)*)? ']' | date time? | time
+/// duration   := (number unit)*
+/// arguments  := (argument (',' argument)*)?
+/// argument   := lambda | range ('=' range)?
+/// lambda     := (name | '(' name (',' name)* ')') '->' range
+/// unit       := factor (('*' | '/') factor | '/' '(' factor ('*' factor)* ')')*
+/// factor     := name ('^' integer)?
+/// ```
+///
+/// `implied` is the multiplication implied by `2(3)`, `(2)(3)`, `(2)3` and `(2)x`.
+/// A unary minus directly before a number folds into a negative literal and
+/// anywhere else becomes `-1 * operand`. A name directly after a number is a
+/// unit if it names one, so `5 km` is a quantity while `5 x` is an error. A
+/// currency code, three capital letters, after a number, a name or a
+/// parenthesized expression makes an amount of money, as in `price EUR`.
+/// Quantities of time in a row make a duration, as in `3d 4h`. Lambdas are
+/// only written as call arguments, as in `map(v, x -> x^2)`.
+struct Parser {
+    tokens: Vec<(Token, Option<Span>)>,
+    position: usize,
+    /// How many operands are being parsed inside one another.
+    nesting: usize,
+    /// Where the last unit ended, so that the `)` of `J/(mol*K)` does not
+    /// imply a multiplication.
+    unit_end: Option<usize>,
+}
+
+impl Parser {
+    fn new(mut tokens: Vec<(Token, Option<Span>)>) -> Self {
+        if let Some(end) = tokens.iter().position(|(token, _)| *token == Token::EOL) {
+            tokens.truncate(end);
         }
-        if let Some(e) = tokens.iter().position(|x| x.token == Token::EOL) {
-            end = e;
+        Parser {
+            tokens,
+            position: 0,
+            nesting: 0,
+            unit_end: None,
         }
+    }
 
-        let mut tree: Vec<Token> = vec![];
-        let mut stack: Vec<(usize, usize)> = vec![];
-        stack.push((0, end));
+    fn parse(&mut self) -> Result<Box<AST>, CalcError> {
+        self.parse_root(false)
+    }
 
-        while !stack.is_empty() {
-            let (s, e) = stack.pop().unwrap();
-            let root_index = s + Self::find_root_index(&tokens[s..e]);
-            let t = tokens[root_index].token.clone();
-            tree.push(t);
+    /// Parses all the tokens, as an equation if `equation` is set and the
+    /// tokens contain a top-level `=`.
+    fn parse_root(&mut self, equation: bool) -> Result<Box<AST>, CalcError> {
+        if self.tokens.is_empty() {
+            Err(CalcError::new(
+                ErrorKind::EmptyExpression,
+                "No tokens to parse",
+            ))?
+        }
+        self.check_equations(equation)?;
+        self.check_parens()?;
 
-            if root_index > s && root_index < e - 1 {
-                stack.push(((root_index + 1), e));
-            }
-            if root_index > s {
-                stack.push((s, root_index));
-            }
+        let tree = if equation {
+            self.parse_argument()?
+        } else {
+            let tree = self.parse_range()?;
+            self.parse_conversions(tree)?
+        };
+        if self.position < self.tokens.len() {
+            Err(CalcError::invalid_expression())?
+        }
+        if tree.depth() > MAX_DEPTH {
+            Err(Self::too_deep(MAX_DEPTH))?
         }
+        Ok(tree)
+    }
 
-        let mut resolved: Vec<Box<Self>> = vec![];
-        while !tree.is_empty() {
-            let t = tree.pop().expect("should contain valid token");
-            match t {
-                Token::Operator(_) => {
-                    let left = resolved.pop();
-                    let right = resolved.pop();
-                    if left.is_none() || right.is_none() {
-                        return Err("Invalid Expression".to_string());
-                    }
-                    resolved.push(Self::new(t, left, right));
+    fn too_deep(limit: usize) -> CalcError {
+        CalcError::new(
+            ErrorKind::RecursionLimit,
+            &format!("Expression is nested more than {} levels deep", limit),
+        )
+    }
+
+    /// Reports unbalanced parens and brackets before anything else, pointing
+    /// at the first unmatched one.
+    fn check_parens(&self) -> Result<(), CalcError> {
+        // The closing character each open paren or bracket expects.
+        let mut open: Vec<(char, Option<Span>)> = vec![];
+        for (token, span) in self.tokens.iter() {
+            let close = match token {
+                Token::Paren(ParenType::OpenParen) => {
+                    open.push((')', *span));
+                    continue;
                 }
-                _ => {
-                    resolved.push(Self::new_leaf(t));
+                Token::Bracket(ParenType::OpenParen) => {
+                    open.push((']', *span));
+                    continue;
+                }
+                Token::Paren(ParenType::CloseParen) => ')',
+                Token::Bracket(ParenType::CloseParen) => ']',
+                _ => continue,
+            };
+            match open.pop() {
+                Some((expected, _)) if expected == close => (),
+                Some((expected, span)) => {
+                    let message = format!("Missing {}", expected);
+                    let error = CalcError::new(ErrorKind::MissingParen, &message);
+                    return Err(Self::with_span(error, span));
+                }
+                None => {
+                    let message = format!("Extra {}", close);
+                    let error = CalcError::new(ErrorKind::ExtraParen, &message);
+                    return Err(Self::with_span(error, *span));
                 }
             }
         }
 
-        if resolved.len() > 1 {
-            Err("Invalid Expression".to_string())
-        } else {
-            resolved.pop().ok_or("Invalid Expression".to_string())
+        match open.first() {
+            Some((expected, span)) => {
+                let message = format!("Missing {}", expected);
+                let error = CalcError::new(ErrorKind::MissingParen, &message);
+                Err(Self::with_span(error, *span))
+            }
+            None => Ok(()),
         }
     }
 
-    fn process_parens(tokens: &[Token]) -> Result<Vec<WeightedToken>, String> {
-        let mut level = 0;
-        let mut weighted_tokens: Vec<WeightedToken> = vec![];
-        let mut paren_stack: Vec<i32> = vec![];
-        let mut previous_token: Option<Token> = None;
-        for token in tokens.iter() {
-            const WEIGHT_OFFSET: i32 = 3;
-            let weighted_token: Option<WeightedToken> = match token {
+    /// Rejects `=` anywhere but directly inside a call's arguments, or at the
+    /// top level when `top_level` is set, reporting it the way the lexer
+    /// reports other unexpected characters.
+    fn check_equations(&self, top_level: bool) -> Result<(), CalcError> {
+        let mut calls: Vec<bool> = vec![];
+        let mut previous: Option<&Token> = None;
+        for (token, span) in self.tokens.iter() {
+            match token {
                 Token::Paren(ParenType::OpenParen) => {
-                    level -= WEIGHT_OFFSET;
-                    paren_stack.push(level);
-                    None
-                }
-                Token::Paren(ParenType::CloseParen) => {
-                    if let Some(popped) = paren_stack.pop() {
-                        if popped != level {
-                            return Err("Extra )".to_string());
-                        }
-                    } else {
-                        return Err("Extra )".to_string());
-                    }
-
-                    level += WEIGHT_OFFSET;
-                    None
+                    calls.push(matches!(previous, Some(Token::Variable(_))))
                 }
-                _ => Some(WeightedToken {
-                    token: token.clone(),
-                    weight: level,
-                }),
-            };
-
-            let (implied_multiply, implied_level) = match (previous_token, token) {
-                (Some(Token::Number(_)), Token::Paren(ParenType::OpenParen)) => {
-                    (true, level + WEIGHT_OFFSET)
+                Token::Bracket(ParenType::OpenParen) => calls.push(false),
+                Token::Paren(ParenType::CloseParen) | Token::Bracket(ParenType::CloseParen) => {
+                    calls.pop();
                 }
-                (Some(Token::Variable(_)), Token::Paren(ParenType::OpenParen)) => {
-                    (true, level + WEIGHT_OFFSET)
+                Token::Equals if calls.last().copied().unwrap_or(top_level) => (),
+                Token::Equals => {
+                    return Err(match span {
+                        Some((start, end)) => CalcError::new(
+                            ErrorKind::InvalidCharacter,
+                            &format!("Unexpected character \"=\" at position {}", start + 1),
+                        )
+                        .with_span((*start, *end)),
+                        None => CalcError::invalid_expression(),
+                    });
                 }
-                (Some(Token::Paren(ParenType::CloseParen)), Token::Paren(ParenType::OpenParen)) => {
-                    (true, level + WEIGHT_OFFSET)
+                _ => (),
+            }
+            previous = Some(token);
+        }
+        Ok(())
+    }
+
+    fn with_span(error: CalcError, span: Option<Span>) -> CalcError {
+        match span {
+            Some(span) => error.with_span(span),
+            None => error,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn previous(&self) -> Option<&Token> {
+        self.position
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map(|(token, _)| token)
+    }
+
+    fn advance(&mut self) -> Option<(Token, Option<Span>)> {
+        let next = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        next
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<Option<Span>, CalcError> {
+        match self.advance() {
+            Some((token, span)) if token == expected => Ok(span),
+            _ => Err(CalcError::invalid_expression()),
+        }
+    }
+
+    fn binary(oper: Token, left: Box<AST>, right: Box<AST>) -> Box<AST> {
+        let span = AST::join_spans(left.span, right.span);
+        AST::new(oper, Some(left), Some(right)).with_span(span)
+    }
+
+    fn parse_range(&mut self) -> Result<Box<AST>, CalcError> {
+        let from = self.parse_comparison()?;
+        if self.peek() != Some(&Token::Range) {
+            return Ok(from);
+        }
+        self.advance();
+        let to = self.parse_comparison()?;
+        Ok(AST::new_range(from, to))
+    }
+
+    /// Comparisons do not chain, so `1 < x < 3` is an error rather than
+    /// comparing the 1 or 0 of `1 < x` with 3.
+    fn parse_comparison(&mut self) -> Result<Box<AST>, CalcError> {
+        let left = self.parse_expression()?;
+        if !matches!(self.peek(), Some(Token::Comparison(_))) {
+            return Ok(left);
+        }
+        let (oper, _) = self.advance().expect("comparison was peeked");
+        let right = self.parse_expression()?;
+        if matches!(self.peek(), Some(Token::Comparison(_))) {
+            Err(CalcError::new(
+                ErrorKind::InvalidExpression,
+                "Comparisons cannot be chained; use parentheses",
+            ))?
+        }
+        Ok(Self::binary(oper, left, right))
+    }
+
+    fn parse_expression(&mut self) -> Result<Box<AST>, CalcError> {
+        let mut left = self.parse_term()?;
+        while let Some(Token::Operator(Op::Add | Op::Sub)) = self.peek() {
+            let (oper, _) = self.advance().expect("operator was peeked");
+            let right = self.parse_term()?;
+            left = Self::binary(oper, left, right);
+        }
+        Ok(left)
+    }
+
+    fn parse_term(&mut self) -> Result<Box<AST>, CalcError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let oper = match (self.previous(), self.peek()) {
+                (_, Some(Token::Operator(Op::Mult | Op::Div))) => {
+                    self.advance().expect("operator was peeked").0
                 }
-                (Some(Token::Paren(ParenType::CloseParen)), Token::Number(_)) => (true, level),
-                (Some(Token::Paren(ParenType::CloseParen)), Token::Variable(_)) => (true, level),
-                _ => (false, 0),
+                _ if self.unit_end == Some(self.position) || self.conversion_at(0) => break,
+                (
+                    Some(Token::Number(_) | Token::Paren(ParenType::CloseParen)),
+                    Some(Token::Paren(ParenType::OpenParen)),
+                )
+                | (
+                    Some(Token::Paren(ParenType::CloseParen)),
+                    Some(Token::Number(_) | Token::Variable(_)),
+                ) => Token::Operator(Op::Mult),
+                _ => break,
             };
+            let right = self.parse_unary()?;
+            left = Self::binary(oper, left, right);
+        }
+        Ok(left)
+    }
 
-            if implied_multiply {
-                weighted_tokens.push(WeightedToken {
-                    token: Token::Operator(Op::Mult),
-                    weight: implied_level,
-                });
-            }
+    /// Every nested operand, whether in parens, after a minus or in an
+    /// exponent, is parsed through here, so this is where nesting is limited.
+    fn parse_unary(&mut self) -> Result<Box<AST>, CalcError> {
+        if self.nesting >= MAX_NESTING {
+            return Err(Self::too_deep(MAX_NESTING));
+        }
+        self.nesting += 1;
+        let operand = self.parse_negation();
+        self.nesting -= 1;
+        operand
+    }
 
-            if let Some(t) = weighted_token {
-                weighted_tokens.push(t.clone());
+    /// Unary minus binds looser than `^`, so `-2^2` is `-(2^2)`.
+    fn parse_negation(&mut self) -> Result<Box<AST>, CalcError> {
+        if self.peek() != Some(&Token::Operator(Op::Sub)) {
+            return self.parse_power();
+        }
+
+        let (_, minus_span) = self.advance().expect("minus was peeked");
+        let postfix_follows = matches!(
+            self.tokens.get(self.position + 1),
+            Some((Token::Operator(Op::Pow) | Token::Degree, _))
+        );
+        if let Some((Token::Number(num), span)) = self.tokens.get(self.position).cloned() {
+            if !postfix_follows {
+                self.position += 1;
+                let negated = match num {
+                    Num::Float(x) => Num::Float(-x),
+                    Num::Integer(x) => Num::Integer(-x),
+                    Num::Decimal(x) => Num::Decimal(-x),
+                };
+                let span = AST::join_spans(minus_span, span);
+                let number = AST::new_leaf(Token::Number(negated)).with_span(span);
+                return self.parse_quantity(number);
             }
+        }
+
+        let operand = self.parse_unary()?;
+        let minus_one = AST::new_leaf(Token::Number(Num::Integer(-1))).with_span(minus_span);
+        Ok(Self::binary(Token::Operator(Op::Mult), minus_one, operand))
+    }
 
-            previous_token = Some(token.clone());
+    /// `^` is right-associative and its exponent may be negated: `2^-3^2`
+    /// is `2^(-(3^2))`.
+    fn parse_power(&mut self) -> Result<Box<AST>, CalcError> {
+        let base = self.parse_degrees()?;
+        if self.peek() != Some(&Token::Operator(Op::Pow)) {
+            return Ok(base);
         }
+        let (oper, _) = self.advance().expect("operator was peeked");
+        let exponent = self.parse_unary()?;
+        Ok(Self::binary(oper, base, exponent))
+    }
 
-        match level.cmp(&0) {
-            Ordering::Greater => Err("Extra )".to_string()),
-            Ordering::Less => Err("Missing )".to_string()),
-            Ordering::Equal => Ok(weighted_tokens),
+    /// A primary followed by `°`, as in `30°` or `(x + 1)°`.
+    fn parse_degrees(&mut self) -> Result<Box<AST>, CalcError> {
+        let mut tree = self.parse_postfix()?;
+        while self.peek() == Some(&Token::Degree) {
+            let (_, span) = self.advance().expect("degree sign was peeked");
+            let span = AST::join_spans(tree.span, span);
+            tree = AST::new_degrees(tree).with_span(span);
         }
+        Ok(tree)
     }
 
-    fn handle_unary_minus(tokens: &[Token]) -> Result<Vec<Token>, String> {
-        let mut tokens_vec: Vec<Token> = tokens.to_vec();
-        if tokens.len() == 1 {
-            return Ok(tokens_vec);
-        } else if tokens.len() == 2 {
-            tokens_vec.insert(0, Token::InvalidToken("padding".to_string()));
+    /// A primary followed by indices, as in `v[0]` or `m[1][2]`.
+    fn parse_postfix(&mut self) -> Result<Box<AST>, CalcError> {
+        let mut tree = self.parse_primary()?;
+        while self.peek() == Some(&Token::Bracket(ParenType::OpenParen)) {
+            self.advance();
+            let index = self.parse_range()?;
+            let close = self.expect(Token::Bracket(ParenType::CloseParen))?;
+            let span = AST::join_spans(tree.span, close);
+            tree = AST::new_index(tree, index).with_span(span);
         }
+        Ok(tree)
+    }
 
-        let mut new_tokens: Vec<Token> = vec![];
+    /// A call argument: an expression, an equation `lhs = rhs` or a lambda.
+    fn parse_argument(&mut self) -> Result<Box<AST>, CalcError> {
+        if let Some((params, length)) = self.lambda_params() {
+            let (_, start) = self.tokens[self.position].clone();
+            self.position += length;
+            let body = self.parse_range()?;
+            let span = AST::join_spans(start, body.span);
+            return Ok(AST::new_lambda(params, body).with_span(span));
+        }
+        let left = self.parse_range()?;
+        if self.peek() != Some(&Token::Equals) {
+            return Ok(left);
+        }
+        self.advance();
+        let right = self.parse_range()?;
+        Ok(AST::new_equation(left, right))
+    }
 
-        let mut skip_next = false;
-        for (i, token_window) in tokens_vec.windows(3).enumerate() {
-            if skip_next {
-                skip_next = false;
-                continue;
+    /// The parameters of the lambda that starts at the next token, as in
+    /// `x -> x^2` or `(acc, x) -> acc + x`, if one does, and how many tokens
+    /// they take up to and including the `->`.
+    fn lambda_params(&self) -> Option<(Vec<String>, usize)> {
+        let token = |offset| {
+            self.tokens
+                .get(self.position + offset)
+                .map(|(token, _)| token)
+        };
+        if let (Some(Token::Variable(name)), Some(Token::Arrow)) = (token(0), token(1)) {
+            return Some((vec![name.clone()], 2));
+        }
+        if token(0) != Some(&Token::Paren(ParenType::OpenParen)) {
+            return None;
+        }
+        let mut params = vec![];
+        let mut offset = 1;
+        loop {
+            match (token(offset), token(offset + 1)) {
+                (Some(Token::Variable(name)), Some(Token::Comma)) => params.push(name.clone()),
+                (Some(Token::Variable(name)), Some(Token::Paren(ParenType::CloseParen))) => {
+                    params.push(name.clone());
+                    break;
+                }
+                _ => return None,
             }
+            offset += 2;
+        }
+        (token(offset + 2) == Some(&Token::Arrow)).then_some((params, offset + 3))
+    }
 
-            match (
-                i,
-                token_window[0].clone(),
-                token_window[1].clone(),
-                token_window[2].clone(),
-            ) {
-                (0, Token::Operator(Op::Sub), Token::Paren(ParenType::OpenParen), _) => {
-                    new_tokens.push(Token::new_number("-1"));
-                    new_tokens.push(token_window[1].clone())
-                }
-                (0, Token::Operator(Op::Sub), Token::Number(Num::Float(n)), _) => {
-                    new_tokens.push(Token::new_number(&format!("-{}", n)));
-                }
-                (0, Token::Operator(Op::Sub), Token::Number(Num::Integer(n)), _) => {
-                    new_tokens.push(Token::new_number(&format!("-{}", n)));
-                }
-                (0, Token::Operator(Op::Sub), v @ Token::Variable(_), _) => {
-                    new_tokens.push(Token::new_paren("("));
-                    new_tokens.push(Token::new_number("-1"));
-                    new_tokens.push(Token::new_op("*"));
-                    new_tokens.push(v);
-                    new_tokens.push(Token::new_paren(")"));
-                }
-                (
-                    0,
-                    Token::InvalidToken(_),
-                    Token::Operator(Op::Sub),
-                    Token::Paren(ParenType::OpenParen),
-                ) => {
-                    new_tokens.push(Token::new_number("-1"));
-                }
-                (
-                    0,
-                    Token::InvalidToken(_),
-                    Token::Operator(Op::Sub),
-                    Token::Number(Num::Float(n)),
-                ) => {
-                    skip_next = true;
-                    new_tokens.push(Token::new_number(&format!("-{}", n)));
-                }
-                (
-                    0,
-                    Token::InvalidToken(_),
-                    Token::Operator(Op::Sub),
-                    Token::Number(Num::Integer(n)),
-                ) => {
-                    skip_next = true;
-                    new_tokens.push(Token::new_number(&format!("-{}", n)));
+    /// `number` followed by the unit it is measured in or its currency, if
+    /// any.
+    fn parse_quantity(&mut self, number: Box<AST>) -> Result<Box<AST>, CalcError> {
+        match self.parse_unit()? {
+            Some((unit, span)) if unit.is_time() && self.time_at(0) => {
+                let span = AST::join_spans(number.span, span);
+                let first = AST::new_quantity(number, unit).with_span(span);
+                self.parse_duration(first)
+            }
+            Some((unit, span)) => {
+                let span = AST::join_spans(number.span, span);
+                Ok(AST::new_quantity(number, unit).with_span(span))
+            }
+            None => Ok(self.parse_currency(number)),
+        }
+    }
+
+    /// The rest of a duration such as `3d 4h 30min` after its first part.
+    /// The parts add up, or all subtract when the first is negative, so
+    /// `-1h 30min` is an hour and a half before.
+    fn parse_duration(&mut self, first: Box<AST>) -> Result<Box<AST>, CalcError> {
+        let negative = match first.left().map(AST::value) {
+            Some(Token::Number(Num::Integer(n))) => *n < 0,
+            Some(Token::Number(Num::Float(x))) => x.is_sign_negative(),
+            Some(Token::Number(Num::Decimal(x))) => x.is_negative(),
+            _ => false,
+        };
+        let oper = if negative { Op::Sub } else { Op::Add };
+        let mut parts = first;
+        let mut count = 1;
+        while self.time_at(0) {
+            let start = self.position;
+            let (number, span) = self.advance().expect("number was peeked");
+            let number = AST::new_leaf(number).with_span(span);
+            match self.parse_unit()? {
+                Some((unit, end)) if unit.is_time() => {
+                    let span = AST::join_spans(span, end);
+                    let part = AST::new_quantity(number, unit).with_span(span);
+                    parts = Self::binary(Token::Operator(oper.clone()), parts, part);
+                    count += 1;
                 }
-                (0, Token::InvalidToken(_), Token::Operator(Op::Sub), v @ Token::Variable(_)) => {
-                    skip_next = true;
-                    new_tokens.push(Token::new_paren("("));
-                    new_tokens.push(Token::new_number("-1"));
-                    new_tokens.push(Token::new_op("*"));
-                    new_tokens.push(v);
-                    new_tokens.push(Token::new_paren(")"));
+                // A compound unit such as the `h/km` of `3d 4h/km`.
+                _ => {
+                    self.position = start;
+                    self.unit_end = Some(start);
+                    break;
                 }
-                (0, Token::InvalidToken(_), _, _) => {
-                    new_tokens.push(token_window[1].clone());
+            }
+        }
+        Ok(if count > 1 {
+            AST::new_duration(parts)
+        } else {
+            parts
+        })
+    }
+
+    /// Whether the tokens `offset` ahead are a positive number and a single
+    /// unit of time, the next part of a duration.
+    fn time_at(&self, offset: usize) -> bool {
+        let token = |offset| {
+            self.tokens
+                .get(self.position + offset)
+                .map(|(token, _)| token)
+        };
+        let positive = match token(offset) {
+            Some(Token::Number(Num::Integer(n))) => *n >= 0,
+            Some(Token::Number(Num::Float(x))) => x.is_sign_positive(),
+            Some(Token::Number(Num::Decimal(x))) => !x.is_negative(),
+            _ => false,
+        };
+        let time = match token(offset + 1) {
+            Some(Token::Variable(name)) => Unit::new(vec![(name.clone(), 1)])
+                .map(|unit| unit.is_time())
+                .unwrap_or(false),
+            _ => false,
+        };
+        positive && time && self.unit_at(offset + 1)
+    }
+
+    /// `operand` followed by a currency code, as in `120 USD` or `price EUR`.
+    fn parse_currency(&mut self, operand: Box<AST>) -> Box<AST> {
+        if !self.currency_at(0) {
+            return operand;
+        }
+        match self.advance() {
+            Some((Token::Variable(currency), span)) => {
+                self.unit_end = Some(self.position);
+                let span = AST::join_spans(operand.span, span);
+                AST::new_money(operand, &currency).with_span(span)
+            }
+            _ => operand,
+        }
+    }
+
+    /// Whether the token `offset` ahead is a currency code rather than a
+    /// function name.
+    fn currency_at(&self, offset: usize) -> bool {
+        let token = |offset| {
+            self.tokens
+                .get(self.position + offset)
+                .map(|(token, _)| token)
+        };
+        matches!(token(offset), Some(Token::Variable(name)) if money::is_currency(name))
+            && token(offset + 1) != Some(&Token::Paren(ParenType::OpenParen))
+    }
+
+    /// Conversions of the whole expression, as in `60 mph to m/s`.
+    fn parse_conversions(&mut self, mut tree: Box<AST>) -> Result<Box<AST>, CalcError> {
+        while let Some(Token::Variable(keyword)) = self.peek() {
+            if keyword != "to" && keyword != "in" {
+                break;
+            }
+            self.advance();
+            if self.currency_at(0) {
+                if let Some((Token::Variable(currency), span)) = self.advance() {
+                    let span = AST::join_spans(tree.span, span);
+                    tree = AST::new_exchange(tree, &currency).with_span(span);
                 }
-                (0, _, _, _) => {
-                    new_tokens.push(token_window[0].clone());
-                    new_tokens.push(token_window[1].clone());
+                continue;
+            }
+            match self.parse_unit()? {
+                Some((unit, span)) => {
+                    let span = AST::join_spans(tree.span, span);
+                    tree = AST::new_conversion(tree, unit).with_span(span);
                 }
-                (
-                    _,
-                    Token::Operator(_) | Token::Paren(ParenType::OpenParen),
-                    Token::Operator(Op::Sub),
-                    Token::Number(Num::Float(n)),
-                ) => {
-                    skip_next = true;
-                    new_tokens.push(Token::new_number(&format!("-{}", n)));
+                None => {
+                    return Err(match self.advance() {
+                        Some((Token::Variable(name), span)) => Self::with_span(
+                            CalcError::new(
+                                ErrorKind::UnknownUnit,
+                                &format!("Unknown unit: {}", name),
+                            ),
+                            span,
+                        ),
+                        _ => CalcError::invalid_expression(),
+                    })
                 }
-                (
-                    _,
-                    Token::Operator(_) | Token::Paren(ParenType::OpenParen),
-                    Token::Operator(Op::Sub),
-                    Token::Number(Num::Integer(n)),
-                ) => {
-                    skip_next = true;
-                    new_tokens.push(Token::new_number(&format!("-{}", n)));
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Whether the token `offset` ahead names a unit rather than a variable
+    /// or a function.
+    fn unit_at(&self, offset: usize) -> bool {
+        let token = |offset| {
+            self.tokens
+                .get(self.position + offset)
+                .map(|(token, _)| token)
+        };
+        match token(offset) {
+            Some(Token::Variable(name)) => {
+                units::is_unit(name)
+                    && token(offset + 1) != Some(&Token::Paren(ParenType::OpenParen))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the tokens `offset` ahead are `to` or `in` and a unit, which
+    /// convert rather than multiply in `(x) to km`.
+    fn conversion_at(&self, offset: usize) -> bool {
+        matches!(
+            self.tokens.get(self.position + offset),
+            Some((Token::Variable(keyword), _)) if keyword == "to" || keyword == "in"
+        ) && (self.unit_at(offset + 1) || self.currency_at(offset + 1))
+    }
+
+    /// A whole power after `^`, as in the `-2` of `s^-2`, if one follows.
+    fn parse_whole_power(&mut self) -> Option<i32> {
+        let token = |offset| {
+            self.tokens
+                .get(self.position + offset)
+                .map(|(token, _)| token)
+        };
+        let (sign, digits) = match (token(1), token(2)) {
+            (Some(Token::Number(Num::Integer(n))), _) => (1, *n),
+            (Some(Token::Operator(Op::Sub)), Some(Token::Number(Num::Integer(n)))) => (-1, *n),
+            _ => return None,
+        };
+        let power = i32::try_from(digits).ok()?;
+        self.position += if sign < 0 { 3 } else { 2 };
+        Some(sign * power)
+    }
+
+    /// A unit such as `km`, `kg*m/s^2` or `J/(mol*K)`, if the next token
+    /// names one.
+    fn parse_unit(&mut self) -> Result<Option<(Unit, Option<Span>)>, CalcError> {
+        if !self.unit_at(0) {
+            return Ok(None);
+        }
+        let mut factors = vec![];
+        let mut span = self.parse_unit_factor(1, &mut factors);
+        loop {
+            let group_follows = self.tokens.get(self.position + 1).map(|(token, _)| token)
+                == Some(&Token::Paren(ParenType::OpenParen))
+                && self.unit_at(2);
+            match self.peek() {
+                Some(Token::Operator(Op::Mult)) if self.unit_at(1) => {
+                    self.advance();
+                    let end = self.parse_unit_factor(1, &mut factors);
+                    span = AST::join_spans(span, end);
                 }
-                (
-                    _,
-                    Token::Operator(_) | Token::Paren(ParenType::OpenParen),
-                    Token::Operator(Op::Sub),
-                    Token::Paren(ParenType::OpenParen),
-                ) => {
-                    new_tokens.push(Token::new_number("-1"));
+                Some(Token::Operator(Op::Div)) if self.unit_at(1) => {
+                    self.advance();
+                    let end = self.parse_unit_factor(-1, &mut factors);
+                    span = AST::join_spans(span, end);
                 }
-                (
-                    _,
-                    Token::Operator(_) | Token::Paren(ParenType::OpenParen),
-                    Token::Operator(Op::Sub),
-                    v @ Token::Variable(_),
-                ) => {
-                    skip_next = true;
-                    new_tokens.push(Token::new_paren("("));
-                    new_tokens.push(Token::new_number("-1"));
-                    new_tokens.push(Token::new_op("*"));
-                    new_tokens.push(v);
-                    new_tokens.push(Token::new_paren(")"));
+                Some(Token::Operator(Op::Div)) if group_follows => {
+                    match self.parse_unit_group(&mut factors) {
+                        Some(end) => span = AST::join_spans(span, end),
+                        None => break,
+                    }
                 }
-                (_, _, t, _) => new_tokens.push(t),
+                _ => break,
             }
         }
-        if !skip_next {
-            if let Some(last) = tokens_vec.last() {
-                new_tokens.push(last.clone());
+        let unit = Unit::new(factors).map_err(|e| Self::with_span(e, span))?;
+        self.unit_end = Some(self.position);
+        Ok(Some((unit, span)))
+    }
+
+    /// A unit name and its power, which is negated if `sign` is negative.
+    fn parse_unit_factor(&mut self, sign: i32, factors: &mut Vec<(String, i32)>) -> Option<Span> {
+        let (symbol, mut span) = match self.advance() {
+            Some((Token::Variable(symbol), span)) => (symbol, span),
+            _ => return None,
+        };
+        let mut power = 1;
+        if self.peek() == Some(&Token::Operator(Op::Pow)) {
+            if let Some(whole) = self.parse_whole_power() {
+                power = whole;
+                span = AST::join_spans(span, self.tokens[self.position - 1].1);
             }
         }
-        Ok(new_tokens)
+        factors.push((symbol, sign * power));
+        span
+    }
+
+    /// A parenthesized product of units after `/`, as in `J/(mol*K)`. Leaves
+    /// the tokens alone if they turn out to be something else, as in
+    /// `5 m/(s*x)`.
+    fn parse_unit_group(&mut self, factors: &mut Vec<(String, i32)>) -> Option<Option<Span>> {
+        let start = self.position;
+        let mut group = vec![];
+        self.position += 2;
+        self.parse_unit_factor(-1, &mut group);
+        while self.peek() == Some(&Token::Operator(Op::Mult)) && self.unit_at(1) {
+            self.advance();
+            self.parse_unit_factor(-1, &mut group);
+        }
+        match self.advance() {
+            Some((Token::Paren(ParenType::CloseParen), span)) => {
+                factors.extend(group);
+                Some(span)
+            }
+            _ => {
+                self.position = start;
+                None
+            }
+        }
+    }
+
+    /// A date literal, joined with a time of day right after it into one
+    /// moment, as in `2026-10-18 14:30`.
+    fn parse_time_of_day(&mut self, date: String, span: Option<Span>) -> Box<AST> {
+        match self.tokens.get(self.position).cloned() {
+            Some((Token::Date(time), end)) if !date.contains(':') && time.contains(':') => {
+                self.position += 1;
+                let moment = Token::Date(format!("{} {}", date, time));
+                AST::new_leaf(moment).with_span(AST::join_spans(span, end))
+            }
+            _ => AST::new_leaf(Token::Date(date)).with_span(span),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Box<AST>, CalcError> {
+        match self.advance() {
+            Some((token @ Token::Number(_), span)) => {
+                self.parse_quantity(AST::new_leaf(token).with_span(span))
+            }
+            Some((Token::Variable(name), span))
+                if self.peek() == Some(&Token::Paren(ParenType::OpenParen)) =>
+            {
+                self.parse_call(&name, span)
+            }
+            Some((token @ Token::Variable(_), span)) => {
+                Ok(self.parse_currency(AST::new_leaf(token).with_span(span)))
+            }
+            Some((Token::Date(date), span)) => Ok(self.parse_time_of_day(date, span)),
+            Some((Token::Paren(ParenType::OpenParen), _)) => {
+                let inner = self.parse_range()?;
+                self.expect(Token::Paren(ParenType::CloseParen))?;
+                Ok(self.parse_currency(inner))
+            }
+            Some((Token::Bracket(ParenType::OpenParen), span)) => self.parse_list(span),
+            _ => Err(CalcError::invalid_expression()),
+        }
+    }
+
+    /// The arguments of a call to `name`, whose `(` is next.
+    fn parse_call(&mut self, name: &str, span: Option<Span>) -> Result<Box<AST>, CalcError> {
+        self.advance();
+        let mut args = vec![];
+        if self.peek() != Some(&Token::Paren(ParenType::CloseParen)) {
+            args.push(self.parse_argument()?);
+            while self.peek() == Some(&Token::Comma) {
+                self.advance();
+                args.push(self.parse_argument()?);
+            }
+        }
+        let close = self.expect(Token::Paren(ParenType::CloseParen))?;
+        let call = AST::new_call(name, args);
+        Ok(call.with_span(AST::join_spans(span, close)))
+    }
+
+    /// The items of a list literal, whose `[` opened at `span`.
+    fn parse_list(&mut self, span: Option<Span>) -> Result<Box<AST>, CalcError> {
+        let mut items = vec![];
+        if self.peek() != Some(&Token::Bracket(ParenType::CloseParen)) {
+            items.push(self.parse_range()?);
+            while self.peek() == Some(&Token::Comma) {
+                self.advance();
+                items.push(self.parse_range()?);
+            }
+        }
+        let close = self.expect(Token::Bracket(ParenType::CloseParen))?;
+        Ok(AST::new_list(items).with_span(AST::join_spans(span, close)))
+    }
+}
+
+/// Drops the nodes one at a time, as dropping a deep tree recursively could
+/// overflow the stack before the parser gets to reject it.
+impl Drop for AST {
+    fn drop(&mut self) {
+        let mut nodes: Vec<Box<AST>> = self.left.take().into_iter().collect();
+        nodes.extend(self.right.take());
+        while let Some(mut node) = nodes.pop() {
+            nodes.extend(node.left.take());
+            nodes.extend(node.right.take());
+        }
     }
 }
 
 impl Display for AST {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Token::Function(name) = &self.value {
+            let args: Vec<String> = self.arguments().iter().map(|arg| arg.to_string()).collect();
+            return write!(f, "'{}' ({})", name, args.join(", "));
+        }
         match (&self.left, &self.right) {
             (None, None) => write!(f, "'{}'", self.value),
             (None, Some(x)) => write!(f, "'{}' (None, {})", self.value, (**x)),
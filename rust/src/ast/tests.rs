@@ -37,7 +37,7 @@ fn no_left_node() {
     let root = AST::new(Token::new_op("/"), None, None);
     let env = HashMap::new();
     let error = AST::evaluate(root, &env).expect_err("should throw 'Invalid Expression'");
-    assert_eq!(error, "Invalid Expression");
+    assert_eq!(error.to_string(), "Invalid Expression");
 }
 
 #[test]
@@ -46,7 +46,7 @@ fn no_right_node() {
     let root = AST::new(Token::new_op("/"), Some(left), None);
     let env = HashMap::new();
     let error = AST::evaluate(root, &env).expect_err("should throw 'Invalid Expression'");
-    assert_eq!(error, "Invalid Expression");
+    assert_eq!(error.to_string(), "Invalid Expression");
 }
 
 #[test]
@@ -56,7 +56,7 @@ fn divide_by_zero() {
     let root = AST::new(Token::new_op("/"), Some(left), Some(right));
     let env = HashMap::new();
     let error = AST::evaluate(root, &env).expect_err("should throw 'Divide by Zero'");
-    assert_eq!(error, "Divide by Zero");
+    assert_eq!(error.to_string(), "Divide by Zero");
 }
 
 #[test]
@@ -99,7 +99,7 @@ fn invalid_tree() {
         Token::EOL,
     ];
     let error_msg = AST::build_tree(&v).expect_err("Tree should fail to build");
-    assert_eq!(error_msg, "Invalid Expression");
+    assert_eq!(error_msg.to_string(), "Invalid Expression");
 }
 
 #[test]
@@ -168,8 +168,8 @@ fn evaluate_paren_missing_close_paren() {
         Token::new_op("/"),
         Token::new_number("10"),
     ];
-    let error: String = AST::build_tree(&v).expect_err("The tree should fail to build.");
-    assert_eq!(error, "Missing )");
+    let error: CalcError = AST::build_tree(&v).expect_err("The tree should fail to build.");
+    assert_eq!(error.to_string(), "Missing )");
 }
 
 #[test]
@@ -194,15 +194,15 @@ fn evaluate_paren_too_many_close_paren() {
         Token::new_op("/"),
         Token::new_number("10"),
     ];
-    let error: String = AST::build_tree(&v).expect_err("The tree should fail to build.");
-    assert_eq!(error, "Extra )");
+    let error: CalcError = AST::build_tree(&v).expect_err("The tree should fail to build.");
+    assert_eq!(error.to_string(), "Extra )");
 }
 
 #[test]
 fn no_operation_in_expression() {
     let v = vec![Token::new_number("10.2"), Token::new_number("0.3")];
-    let error: String = AST::build_tree(&v).expect_err("The tree should fail to build.");
-    assert_eq!(error, "Invalid Expression");
+    let error: CalcError = AST::build_tree(&v).expect_err("The tree should fail to build.");
+    assert_eq!(error.to_string(), "Invalid Expression");
 }
 
 #[test]
@@ -352,3 +352,229 @@ fn negative_numbers_after_operator() {
         };
     }
 }
+
+#[test]
+fn error_kinds() {
+    let v = vec![
+        Token::new_variable("y"),
+        Token::new_op("*"),
+        Token::new_number("2"),
+    ];
+    let tree = AST::build_tree(&v).expect("the tree should build successfully");
+    let error = AST::evaluate(tree, &HashMap::new()).expect_err("y is not defined");
+    assert_eq!(error.kind, ErrorKind::UnknownVariable);
+    assert_eq!(error.to_string(), "Unknown Variable: y");
+
+    let tokens = crate::tokenize("x0(2)").unwrap();
+    let tree = AST::build_tree_spanned(&tokens).expect("the tree should build successfully");
+    let error = AST::evaluate(tree, &HashMap::new()).expect_err("x0 is not defined");
+    assert_eq!(error.to_string(), "Unknown Variable: x0");
+
+    let v = vec![Token::new_paren("("), Token::new_number("2")];
+    let error = AST::build_tree(&v).expect_err("the paren is never closed");
+    assert_eq!(error.kind, ErrorKind::MissingParen);
+
+    let error = AST::build_tree(&[]).expect_err("there is nothing to parse");
+    assert_eq!(error.kind, ErrorKind::EmptyExpression);
+}
+
+#[test]
+fn function_calls() {
+    let v = vec![
+        Token::new_variable("f"),
+        Token::new_paren("("),
+        Token::new_number("1"),
+        Token::Comma,
+        Token::new_variable("x"),
+        Token::new_op("+"),
+        Token::new_number("2"),
+        Token::Comma,
+        Token::new_paren("("),
+        Token::new_number("3"),
+        Token::new_paren(")"),
+        Token::new_paren(")"),
+    ];
+    let tree = AST::build_tree(&v).expect("the call should parse");
+    assert_eq!(tree.to_string(), "'f' ('1', '+' ('x', '2'), '3')");
+    assert_eq!(tree.arguments().len(), 3);
+
+    let v = vec![
+        Token::new_variable("now"),
+        Token::new_paren("("),
+        Token::new_paren(")"),
+    ];
+    let tree = AST::build_tree(&v).expect("calls may have no arguments");
+    assert_eq!(tree.to_string(), "'now' ()");
+    assert!(tree.arguments().is_empty());
+
+    let v = vec![Token::new_number("1"), Token::Comma, Token::new_number("2")];
+    let error = AST::build_tree(&v).expect_err("commas only separate arguments");
+    assert_eq!(error.to_string(), "Invalid Expression");
+}
+
+#[test]
+fn powers() {
+    let env = HashMap::new();
+    let eval = |expression: &str| {
+        let tree = AST::build_tree(&crate::parse_tokens(expression).unwrap()).unwrap();
+        AST::evaluate(tree, &env).expect("should evaluate")
+    };
+    assert_eq!(eval("2^3^2"), 512.0);
+    assert_eq!(eval("(2^3)^2"), 64.0);
+    assert_eq!(eval("-2^2"), -4.0);
+    assert_eq!(eval("(-2)^2"), 4.0);
+    assert_eq!(eval("2^-1"), 0.5);
+    assert_eq!(eval("3 * 2^2 + 1"), 13.0);
+    assert_eq!(eval("4^0.5(2)"), 4.0);
+}
+
+#[test]
+fn negated_groups() {
+    let env = HashMap::new();
+    let eval = |expression: &str| {
+        let tree = AST::build_tree(&crate::parse_tokens(expression).unwrap()).unwrap();
+        AST::evaluate(tree, &env).expect("should evaluate")
+    };
+    assert_eq!(eval("3 / -(25 * 4)"), -0.03);
+    assert_eq!(eval("3 * -(2 + 3)"), -15.0);
+    assert_eq!(eval("3 - -(2 + 3)"), 8.0);
+    assert_eq!(eval("-(2 + 3) * 2"), -10.0);
+    assert_eq!(eval("2^-(1 + 1)"), 0.25);
+    assert_eq!(eval("-(1 + 1)^2"), -4.0);
+}
+
+#[test]
+fn deep_input_is_an_error() {
+    let build = |expression: &str| AST::build_tree(&crate::parse_tokens(expression).unwrap());
+    let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+
+    let env = HashMap::new();
+    let tree = build(&nested(100)).expect("moderate nesting is fine");
+    assert_eq!(AST::evaluate(tree, &env), Ok(1.0));
+    // Chains are walked in a loop however long they are. The tokens are
+    // built directly, as lexing thousands of terms is slow in tests.
+    let mut tokens = vec![Token::new_number("1")];
+    for _ in 1..5000 {
+        tokens.extend([
+            Token::new_op("+"),
+            Token::new_number("2"),
+            Token::new_op("*"),
+        ]);
+        tokens.extend([
+            Token::new_number("3"),
+            Token::new_op("-"),
+            Token::new_number("6"),
+        ]);
+    }
+    let tree = AST::build_tree(&tokens).expect("a long sum is fine");
+    assert_eq!(
+        crate::printer::infix(&tree),
+        format!("1{}", " + 2 * 3 - 6".repeat(4999))
+    );
+    assert_eq!(AST::evaluate(tree, &env), Ok(1.0));
+
+    for expression in [
+        nested(1000),
+        format!("{}1{}", "(1 + ".repeat(1000), ")".repeat(1000)),
+        format!("{}1", "-".repeat(1000)),
+        vec!["2"; 1000].join("^"),
+        format!("1{}", "°".repeat(1000)),
+    ] {
+        let error = build(&expression).expect_err("the input is too deep");
+        assert_eq!(error.kind, ErrorKind::RecursionLimit);
+    }
+}
+
+#[test]
+fn variable_before_paren_multiplies() {
+    let mut env = HashMap::new();
+    env.insert("x0".to_string(), 4.0);
+    let v = vec![
+        Token::new_variable("x0"),
+        Token::new_paren("("),
+        Token::new_number("2"),
+        Token::new_op("+"),
+        Token::new_number("1"),
+        Token::new_paren(")"),
+    ];
+    let tree = AST::build_tree(&v).expect("the tree should build successfully");
+    assert_eq!(AST::evaluate(tree, &env).expect("x0 is defined"), 12.0);
+}
+
+#[test]
+fn spans_locate_errors() {
+    let v = vec![
+        (Token::new_number("1"), (0, 1)),
+        (Token::new_op("/"), (2, 3)),
+        (Token::new_paren("("), (4, 5)),
+        (Token::new_variable("a"), (5, 6)),
+        (Token::new_op("-"), (7, 8)),
+        (Token::new_variable("a"), (9, 10)),
+        (Token::new_paren(")"), (10, 11)),
+    ];
+    let tree = AST::build_tree_spanned(&v).expect("the tree should build successfully");
+    assert_eq!(tree.span(), Some((0, 10)));
+
+    let mut env = HashMap::new();
+    let error = AST::evaluate(AST::build_tree_spanned(&v).unwrap(), &env).unwrap_err();
+    assert_eq!(error.span, Some((5, 6)));
+
+    env.insert("a".to_string(), 1.0);
+    let error = AST::evaluate(tree, &env).expect_err("should divide by zero");
+    assert_eq!(error.kind, ErrorKind::DivideByZero);
+    assert_eq!(error.span, Some((0, 10)));
+
+    let error = AST::build_tree_spanned(&v[..6]).expect_err("the paren is never closed");
+    assert_eq!(error.span, Some((4, 5)));
+}
+
+#[test]
+fn equations() {
+    let spanned = |expression: &str| crate::tokenize(expression).unwrap();
+    let tree = AST::build_tree_spanned(&spanned("solve(x^2 = 2, x)"))
+        .expect("equations are allowed as arguments");
+    assert_eq!(tree.arguments()[0].value(), &Token::Equals);
+    let tree = AST::build_equation_spanned(&spanned("2 * x = y + 1")).expect("should parse");
+    assert_eq!(tree.to_string(), "'=' ('*' ('2', 'x'), '+' ('y', '1'))");
+
+    for (expression, position) in [("10 = 2", 4), ("f((x = 1))", 6), ("(a = 1)", 4)] {
+        let error = AST::build_tree_spanned(&spanned(expression)).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidCharacter);
+        assert_eq!(
+            error.message,
+            format!("Unexpected character \"=\" at position {}", position)
+        );
+    }
+
+    let env = HashMap::from([("y".to_string(), 2.0)]);
+    let tree = AST::build_tree_spanned(&spanned("y(1 = 2)")).unwrap();
+    let error = AST::evaluate(tree, &env).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidExpression);
+    let error = AST::build_equation_spanned(&spanned("x = 1 = 2")).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidExpression);
+}
+
+#[test]
+fn comparisons() {
+    let env = HashMap::from([("x".to_string(), 3.0)]);
+    let eval = |expression: &str| {
+        let tree = AST::build_tree_spanned(&crate::tokenize(expression).unwrap())?;
+        AST::evaluate(tree, &env)
+    };
+    assert_eq!(eval("1 + 2 > 2 * 1"), Ok(1.0));
+    assert_eq!(eval("x <= 3"), Ok(1.0));
+    assert_eq!(eval("x < 3"), Ok(0.0));
+    assert_eq!(eval("x == 3"), Ok(1.0));
+    assert_eq!(eval("x != 3"), Ok(0.0));
+    assert_eq!(eval("(x > 1) + (x > 2) + (x > 3)"), Ok(2.0));
+    assert_eq!(eval("1 km >= 999 m"), Ok(1.0));
+    // Infinities differ by NaN, which is equal to nothing.
+    assert_eq!(eval("2.0^1024 == 2.0^1024"), Ok(0.0));
+    assert_eq!(eval("2.0^1024 != 2.0^1024"), Ok(1.0));
+
+    let error = eval("1 < x < 5").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidExpression);
+    let error = eval("2026-10-18 > 1 d 2 h").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::TypeMismatch);
+    assert_eq!(error.message, "Cannot compare date with duration");
+}
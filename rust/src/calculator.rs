@@ -0,0 +1,386 @@
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    ast::AST,
+    compiled::Compiled,
+    constants::CONSTANTS,
+    datetime,
+    derivative::derivative_in,
+    environment::Environment,
+    error::{CalcError, ErrorKind},
+    finance, forms,
+    functions::{builtins, Arity, Callable, NativeFunction, UserFunction},
+    integrate::{integrate, Integral},
+    matrix,
+    money::Rates,
+    number_theory,
+    printer::infix,
+    settings::{AngleMode, DecimalMode, Settings},
+    simplify::simplify,
+    solve::solve,
+    stats, tokenize,
+    value::Value,
+};
+
+/// A calculator session: the variables, constants and functions expressions
+/// can refer to, the session settings, and the history of results.
+///
+/// ```
+/// use cli_calculator::calculator::Calculator;
+///
+/// let mut calculator = Calculator::new();
+/// calculator.set_var("rate", 0.25).unwrap();
+/// calculator.define_fn("tax", &["price"], "price * rate").unwrap();
+/// let value = calculator.eval("tax(80) + 1").unwrap();
+/// assert_eq!(value.as_number(), Some(21.0));
+/// assert_eq!(calculator.get_var("x0"), Some(value));
+///
+/// calculator.register_function("half", 1, |args| args[0] / 2.0);
+/// calculator.register_constant("g", 9.81).unwrap();
+/// assert_eq!(calculator.eval("half(g)").unwrap().as_number(), Some(4.905));
+/// ```
+#[derive(Debug)]
+pub struct Calculator {
+    variables: HashMap<String, Value>,
+    constants: HashMap<String, Value>,
+    functions: HashMap<String, Box<dyn Callable>>,
+    settings: Settings,
+    rates: Rates,
+    history: Vec<Value>,
+}
+
+impl Default for Calculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Calculator {
+    pub fn new() -> Self {
+        Self::with_settings(Settings::default())
+    }
+
+    pub fn with_settings(settings: Settings) -> Self {
+        let constants = CONSTANTS
+            .iter()
+            .map(|constant| (constant.name.to_owned(), constant.to_value()))
+            .collect();
+        let mut calculator = Calculator {
+            variables: HashMap::new(),
+            constants,
+            functions: HashMap::new(),
+            settings,
+            rates: Rates::default(),
+            history: vec![],
+        };
+        for function in builtins() {
+            let name = function.name().to_owned();
+            calculator.register(&name, function);
+        }
+        for function in datetime::functions()
+            .into_iter()
+            .chain(finance::functions())
+            .chain(matrix::functions())
+            .chain(number_theory::functions())
+            .chain(stats::functions())
+        {
+            let name = function.name().to_owned();
+            calculator.register(&name, function);
+        }
+        calculator
+    }
+
+    /// Evaluates `expression` and records the result, which later expressions
+    /// can refer to as `x0`, `x1`, ...
+    pub fn eval(&mut self, expression: &str) -> Result<Value, CalcError> {
+        let value = self.compile(expression)?.eval(self)?;
+        self.variables
+            .insert(Self::result_name(self.history.len()), value.clone());
+        self.history.push(value.clone());
+        Ok(value)
+    }
+
+    /// Parses `expression` once so it can be evaluated many times.
+    pub fn compile(&self, expression: &str) -> Result<Expr, CalcError> {
+        let tree = AST::build_tree_spanned(&tokenize(expression)?)?;
+        Ok(Expr {
+            source: expression.to_owned(),
+            tree,
+        })
+    }
+
+    /// Compiles `expression` with `params` resolved to argument slots, for
+    /// evaluating the same formula over many rows of data:
+    ///
+    /// ```
+    /// # use cli_calculator::calculator::Calculator;
+    /// let calculator = Calculator::new();
+    /// let total = calculator.compile_with("price * qty", &["price", "qty"]).unwrap();
+    /// assert_eq!(total.eval_numbers(&calculator, &[2.5, 4.0]), Ok(10.0));
+    /// ```
+    pub fn compile_with(&self, expression: &str, params: &[&str]) -> Result<Compiled, CalcError> {
+        for param in params {
+            Self::check_name(param)?;
+        }
+        Compiled::new(expression, &self.compile(expression)?.tree, params)
+    }
+
+    /// The derivative of `expression` with respect to `var`, which prints as
+    /// an expression and can be evaluated at a point:
+    ///
+    /// ```
+    /// # use cli_calculator::{calculator::Calculator, environment::Scope};
+    /// let calculator = Calculator::new();
+    /// let slope = calculator.diff("x^3 + 2*x", "x").unwrap();
+    /// assert_eq!(slope.to_string(), "3 * x ^ 2 + 2");
+    /// let at_one = Scope::new(&calculator, vec![("x".to_owned(), 1.0.into())]);
+    /// assert_eq!(slope.eval(&at_one).unwrap().as_number(), Some(5.0));
+    /// ```
+    pub fn diff(&self, expression: &str, var: &str) -> Result<Expr, CalcError> {
+        Self::check_name(var)?;
+        let tree = derivative_in(&self.compile(expression)?.tree, var, self.settings.angle)?;
+        Ok(Expr {
+            source: infix(&tree),
+            tree,
+        })
+    }
+
+    /// The roots of `equation` for `var`, which is either `lhs = rhs` or an
+    /// expression equal to zero. With an interval every root in it is found,
+    /// otherwise the one Newton's method reaches from the current value of `var`:
+    ///
+    /// ```
+    /// # use cli_calculator::calculator::Calculator;
+    /// let calculator = Calculator::new();
+    /// let roots = calculator.solve("x^2 = 4", "x", Some((-5.0, 5.0))).unwrap();
+    /// assert_eq!(roots, vec![-2.0, 2.0]);
+    /// ```
+    pub fn solve(
+        &self,
+        equation: &str,
+        var: &str,
+        interval: Option<(f64, f64)>,
+    ) -> Result<Vec<f64>, CalcError> {
+        Self::check_name(var)?;
+        let tree = AST::build_equation_spanned(&tokenize(equation)?)?;
+        solve(&tree, var, interval, self)
+    }
+
+    /// The integral of `expression` for `var` from `a` to `b`, with an
+    /// estimate of its error:
+    ///
+    /// ```
+    /// # use cli_calculator::calculator::Calculator;
+    /// let calculator = Calculator::new();
+    /// let area = calculator.integrate("x^2", "x", 0.0, 3.0).unwrap();
+    /// assert!((area.value - 9.0).abs() <= area.error.max(1e-12));
+    /// ```
+    pub fn integrate(
+        &self,
+        expression: &str,
+        var: &str,
+        a: f64,
+        b: f64,
+    ) -> Result<Integral, CalcError> {
+        Self::check_name(var)?;
+        let tree = self.compile(expression)?.tree;
+        let integrand = |x: f64| forms::number_at("integrate", &tree, var, x, self);
+        integrate(&integrand, a, b)
+    }
+
+    /// Returns `expression` in canonical form, e.g. `2(3+4)` becomes
+    /// `2 * (3 + 4)`.
+    pub fn format(&self, expression: &str) -> Result<String, CalcError> {
+        Ok(infix(&self.compile(expression)?.tree))
+    }
+
+    /// Returns the text of `expression` after folding constants and removing
+    /// redundant operations, e.g. `2 * 3 + x * 1` becomes `6 + x`, and calling
+    /// built-in functions with constant arguments, e.g. `sin(0)`. In decimal
+    /// mode, where values are exact, constants are folded in decimal arithmetic
+    /// and `x - x` and `x * 0` become `0` too.
+    pub fn simplify(&self, expression: &str) -> Result<String, CalcError> {
+        let tree = self.compile(expression)?.tree;
+        Ok(infix(&simplify(
+            &tree,
+            self.settings.decimal.is_some(),
+            self,
+        )))
+    }
+
+    pub fn set_var(&mut self, name: &str, value: impl Into<Value>) -> Result<(), CalcError> {
+        Self::check_name(name)?;
+        if self.constants.contains_key(name) {
+            return Err(CalcError::new(
+                ErrorKind::ReadOnly,
+                &format!("Cannot assign to constant: {}", name),
+            ));
+        }
+        self.variables.insert(name.to_owned(), value.into());
+        Ok(())
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<Value> {
+        self.variable(name)
+    }
+
+    /// Defines a function callable from expressions, e.g.
+    /// `define_fn("area", &["w", "h"], "w * h")`.
+    pub fn define_fn(&mut self, name: &str, params: &[&str], body: &str) -> Result<(), CalcError> {
+        Self::check_name(name)?;
+        if forms::is_form(name) {
+            return Err(CalcError::new(
+                ErrorKind::InvalidName,
+                &format!("{} is reserved", name),
+            ));
+        }
+        for param in params {
+            Self::check_name(param)?;
+        }
+        let body = self.compile(body)?.tree;
+        let params = params.iter().map(|param| param.to_string()).collect();
+        self.register(name, UserFunction::new(params, body));
+        Ok(())
+    }
+
+    /// Makes a Rust closure callable from expressions with exactly `arity`
+    /// numeric arguments, e.g. `register_function("tax", 1, |args| args[0] * 0.2)`.
+    pub fn register_function<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[f64]) -> f64 + 'static,
+    {
+        self.register(
+            name,
+            NativeFunction::new(name, Arity::Exact(arity), function),
+        );
+    }
+
+    /// Registers any [`Callable`] under `name`, replacing an existing function
+    /// of the same name.
+    pub fn register(&mut self, name: &str, function: impl Callable + 'static) {
+        self.functions.insert(name.to_owned(), Box::new(function));
+    }
+
+    /// Defines a read-only value. Constants shadow variables of the same name.
+    pub fn register_constant(
+        &mut self,
+        name: &str,
+        value: impl Into<Value>,
+    ) -> Result<(), CalcError> {
+        Self::check_name(name)?;
+        self.variables.remove(name);
+        self.constants.insert(name.to_owned(), value.into());
+        Ok(())
+    }
+
+    /// Every constant, built-in or registered, sorted by name regardless of case.
+    pub fn constants(&self) -> Vec<(&str, &Value)> {
+        let mut constants: Vec<(&str, &Value)> = self
+            .constants
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect();
+        constants.sort_by_key(|(name, _)| (name.to_lowercase(), *name));
+        constants
+    }
+
+    pub fn history(&self) -> &[Value] {
+        &self.history
+    }
+
+    /// The variable name the result at `index` of the history is stored under.
+    pub fn result_name(index: usize) -> String {
+        format!("x{}", index)
+    }
+
+    /// The exchange rates `120 USD to EUR` converts at.
+    pub fn rates(&self) -> &Rates {
+        &self.rates
+    }
+
+    /// Replaces the exchange rates, e.g. with `Rates::load("rates.json")`.
+    pub fn set_rates(&mut self, rates: Rates) {
+        self.rates = rates;
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    fn check_name(name: &str) -> Result<(), CalcError> {
+        let mut chars = name.chars();
+        let valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if valid {
+            Ok(())
+        } else {
+            Err(CalcError::new(
+                ErrorKind::InvalidName,
+                &format!("Invalid name: {:?}", name),
+            ))
+        }
+    }
+}
+
+impl Environment for Calculator {
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.constants
+            .get(name)
+            .or_else(|| self.variables.get(name))
+            .cloned()
+    }
+
+    fn function(&self, name: &str) -> Option<&dyn Callable> {
+        self.functions.get(name).map(|function| function.as_ref())
+    }
+
+    fn globals(&self) -> &dyn Environment {
+        self
+    }
+
+    fn angle_mode(&self) -> AngleMode {
+        self.settings.angle
+    }
+
+    fn rates(&self) -> Option<&Rates> {
+        Some(&self.rates)
+    }
+
+    fn decimal(&self) -> Option<DecimalMode> {
+        self.settings.decimal
+    }
+}
+
+/// A parsed expression that can be evaluated repeatedly.
+#[derive(Debug)]
+pub struct Expr {
+    source: String,
+    tree: Box<AST>,
+}
+
+impl Expr {
+    pub fn eval(&self, env: &dyn Environment) -> Result<Value, CalcError> {
+        self.tree.eval(env)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn tree(&self) -> &AST {
+        &self.tree
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
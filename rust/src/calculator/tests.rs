@@ -0,0 +1,211 @@
+use std::f64::consts::PI;
+
+use crate::constants::CONSTANTS;
+
+use super::*;
+
+#[test]
+fn eval_records_history() {
+    let mut calculator = Calculator::new();
+    assert_eq!(calculator.eval("10 + 3"), Ok(Value::Integer(13)));
+    assert_eq!(calculator.eval("x0 / 2"), Ok(Value::Number(6.5)));
+    assert_eq!(
+        calculator.history(),
+        &[Value::Integer(13), Value::Number(6.5)]
+    );
+    assert_eq!(calculator.get_var("x1"), Some(Value::Number(6.5)));
+}
+
+#[test]
+fn failed_eval_does_not_advance_history() {
+    let mut calculator = Calculator::new();
+    let error = calculator.eval("1 + nope").expect_err("nope is undefined");
+    assert_eq!(error.kind, ErrorKind::UnknownVariable);
+    assert_eq!(error.span, Some((4, 8)));
+    assert!(calculator.history().is_empty());
+    assert_eq!(calculator.eval("2"), Ok(Value::Integer(2)));
+    assert_eq!(calculator.get_var("x0"), Some(Value::Integer(2)));
+}
+
+#[test]
+fn variables_and_constants() {
+    let mut calculator = Calculator::new();
+    assert_eq!(calculator.get_var("pi"), Some(Value::Number(PI)));
+    calculator.set_var("r", 2.0).expect("r is a valid name");
+    assert_eq!(calculator.eval("pi * r * r"), Ok(Value::Number(PI * 4.0)));
+
+    let error = calculator
+        .set_var("2r", 1.0)
+        .expect_err("names cannot start with a digit");
+    assert_eq!(error.kind, ErrorKind::InvalidName);
+}
+
+#[test]
+fn user_functions() {
+    let mut calculator = Calculator::new();
+    calculator
+        .define_fn("area", &["w", "h"], "w * h")
+        .expect("the body should parse");
+    calculator
+        .define_fn("square", &["x"], "area(x, x)")
+        .expect("the body should parse");
+    assert_eq!(calculator.eval("area(2, 3 + 1)"), Ok(Value::Integer(8)));
+    assert_eq!(calculator.eval("square(-3) - 1"), Ok(Value::Integer(8)));
+
+    let error = calculator
+        .eval("area(1)")
+        .expect_err("area takes two arguments");
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+    assert_eq!(error.span, Some((0, 7)));
+
+    let error = calculator
+        .eval("volume(1)")
+        .expect_err("volume is undefined");
+    assert_eq!(error.kind, ErrorKind::UnknownVariable);
+}
+
+#[test]
+fn function_bodies_only_see_globals() {
+    let mut calculator = Calculator::new();
+    calculator.define_fn("f", &["x"], "x + y").unwrap();
+    calculator.define_fn("g", &["y"], "f(1)").unwrap();
+    let error = calculator
+        .eval("g(2)")
+        .expect_err("y is not visible inside f");
+    assert_eq!(error.kind, ErrorKind::UnknownVariable);
+
+    calculator.set_var("y", 10.0).unwrap();
+    assert_eq!(calculator.eval("g(2)"), Ok(Value::Number(11.0)));
+}
+
+#[test]
+fn runaway_recursion_is_an_error() {
+    let mut calculator = Calculator::new();
+    calculator.define_fn("f", &["x"], "f(x + 1)").unwrap();
+    let error = calculator.eval("f(0)").expect_err("f never terminates");
+    assert_eq!(error.kind, ErrorKind::RecursionLimit);
+}
+
+#[test]
+fn compiled_expressions_are_reusable() {
+    let mut calculator = Calculator::new();
+    let expr = calculator.compile("price * 2").expect("should parse");
+    assert_eq!(expr.source(), "price * 2");
+    for price in [1.0, 2.5, 10.0] {
+        calculator.set_var("price", price).unwrap();
+        assert_eq!(expr.eval(&calculator), Ok(Value::Number(price * 2.0)));
+    }
+}
+
+#[test]
+fn native_functions_and_constants() {
+    let mut calculator = Calculator::new();
+    calculator.register_function("tax", 1, |args| args[0] * 0.2);
+    calculator.register_constant("g", 9.81).unwrap();
+    assert_eq!(calculator.eval("tax(50) + 1"), Ok(Value::Number(11.0)));
+    assert_eq!(calculator.eval("2 * g"), Ok(Value::Number(19.62)));
+    assert_eq!(
+        calculator.eval("sqrt(16) + max(1, 5, 3)"),
+        Ok(Value::Number(9.0))
+    );
+
+    let error = calculator
+        .eval("tax(1, 2)")
+        .expect_err("tax takes one argument");
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+    assert_eq!(error.message, "Expected 1 argument(s) but got 2");
+
+    let error = calculator.set_var("g", 1.0).expect_err("g is a constant");
+    assert_eq!(error.kind, ErrorKind::ReadOnly);
+    assert_eq!(calculator.get_var("g"), Some(Value::Number(9.81)));
+}
+
+#[test]
+fn user_functions_replace_builtins() {
+    let mut calculator = Calculator::new();
+    calculator.define_fn("abs", &["x"], "x * 2").unwrap();
+    assert_eq!(calculator.eval("abs(-3)"), Ok(Value::Integer(-6)));
+}
+
+#[test]
+fn derivatives() {
+    let mut calculator = Calculator::new();
+    assert_eq!(
+        calculator.eval("diff(x^3 + sin(x), x, 0)"),
+        Ok(Value::Number(1.0))
+    );
+    calculator.set_var("x", 2.0).unwrap();
+    assert_eq!(calculator.eval("diff(x^3, x)"), Ok(Value::Number(12.0)));
+    assert_eq!(
+        calculator.eval("diff(diff(x^3, x), x, 1)"),
+        Ok(Value::Integer(6))
+    );
+
+    let error = calculator.eval("diff(x^2, 2)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    assert_eq!(error.message, "diff expects a variable name, not '2'");
+    let error = calculator.eval("diff(x)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+
+    let error = calculator.define_fn("diff", &["x"], "x").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidName);
+}
+
+#[test]
+fn builtin_constants_are_read_only() {
+    let mut calculator = Calculator::new();
+    assert_eq!(calculator.eval("tau / pi"), Ok(Value::Number(2.0)));
+    assert_eq!(
+        calculator.eval("c to km/s").unwrap().to_string(),
+        "299792.458 km/s"
+    );
+    assert_eq!(
+        calculator.eval("-inf"),
+        Ok(Value::Number(f64::NEG_INFINITY))
+    );
+    let golden = calculator.eval("phi^2 - phi").unwrap().as_number().unwrap();
+    assert!((golden - 1.0).abs() < 1e-15);
+
+    for constant in CONSTANTS {
+        let error = calculator
+            .set_var(constant.name, 1.0)
+            .expect_err("built-in constants cannot be assigned");
+        assert_eq!(error.kind, ErrorKind::ReadOnly);
+    }
+    assert_eq!(
+        calculator.get_var("e"),
+        Some(Value::Number(std::f64::consts::E))
+    );
+
+    let names: Vec<&str> = calculator
+        .constants()
+        .iter()
+        .map(|(name, _)| *name)
+        .collect();
+    assert_eq!(names.len(), CONSTANTS.len());
+    assert_eq!(&names[..4], &["c", "e", "eps0", "G"]);
+}
+
+#[test]
+fn decimal_mode_is_exact() {
+    let mut calculator = Calculator::new();
+    assert_ne!(calculator.eval("0.1 + 0.2"), Ok(Value::Number(0.3)));
+    assert_eq!(
+        calculator.simplify("2 * (x - x)"),
+        Ok("2 * (x - x)".to_owned())
+    );
+
+    calculator.settings_mut().decimal = Some(DecimalMode::default());
+    assert_eq!(calculator.eval("0.1 + 0.2").unwrap().to_string(), "0.3");
+    assert_eq!(
+        calculator.eval("2^100").unwrap().to_string(),
+        "1267650600228229401496703205376"
+    );
+    calculator
+        .define_fn("tax", &["x"], "x * 0.07")
+        .expect("the body should parse");
+    assert_eq!(calculator.eval("tax(19.99)").unwrap().to_string(), "1.3993");
+    assert_eq!(calculator.simplify("2 * (x - x)"), Ok("0".to_owned()));
+    // Without an exact form the result falls back to a float.
+    assert_eq!(calculator.eval("2^0.5"), Ok(Value::Number(2f64.sqrt())));
+}
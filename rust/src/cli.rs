@@ -0,0 +1,487 @@
+#[cfg(test)]
+mod tests;
+
+use std::{error::Error, fmt::Display};
+
+use crate::{
+    render::Markup,
+    settings::{AngleMode, DecimalMode, NumberFormat, Settings},
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Command {
+    Eval,
+    Repl,
+    Check,
+    Tokens,
+    Ast,
+    Format,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Options {
+    pub command: Option<Command>,
+    pub expressions: Vec<String>,
+    pub file: Option<String>,
+    pub format: NumberFormat,
+    pub precision: Option<usize>,
+    pub angle: AngleMode,
+    /// Exact decimal arithmetic instead of floating point, if set.
+    pub decimal: Option<DecimalMode>,
+    pub json: bool,
+    /// Print results as `expression = value` in this markup.
+    pub markup: Option<Markup>,
+    pub raw: bool,
+    pub quiet: bool,
+    pub color: bool,
+    pub defines: Vec<(String, String)>,
+    /// File of exchange rates to load before evaluating.
+    pub rates: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            command: None,
+            expressions: vec![],
+            file: None,
+            format: NumberFormat::Auto,
+            precision: None,
+            angle: AngleMode::Radians,
+            decimal: None,
+            json: false,
+            markup: None,
+            raw: false,
+            quiet: false,
+            color: true,
+            defines: vec![],
+            rates: None,
+        }
+    }
+}
+
+impl Options {
+    /// The calculator settings selected on the command line.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            format: self.format,
+            precision: self.precision,
+            angle: self.angle,
+            decimal: self.decimal,
+            ..Settings::default()
+        }
+    }
+
+    /// The command to run, falling back to `eval` when expressions or a file
+    /// were given and to `repl` otherwise.
+    pub fn command(&self) -> Command {
+        match self.command {
+            Some(command) => command,
+            None if !self.expressions.is_empty() || self.file.is_some() => Command::Eval,
+            None => Command::Repl,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Run(Options),
+    Help,
+    Version,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ArgError {
+    UnknownOption(String),
+    MissingValue(&'static str),
+    InvalidValue {
+        option: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    UnexpectedArgument(String),
+}
+
+impl Display for ArgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArgError::UnknownOption(option) => write!(f, "unknown option '{}'", option),
+            ArgError::MissingValue(option) => write!(f, "option '--{}' requires a value", option),
+            ArgError::InvalidValue {
+                option,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value '{}' for '--{}' (expected {})",
+                value, option, expected
+            ),
+            ArgError::UnexpectedArgument(arg) => {
+                write!(f, "unexpected argument '{}'", arg)
+            }
+        }
+    }
+}
+
+impl Error for ArgError {}
+
+struct OptionSpec {
+    long: &'static str,
+    short: Option<char>,
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+const COMMANDS: &[(&str, Command, &str)] = &[
+    (
+        "eval",
+        Command::Eval,
+        "Evaluate expressions and print the results",
+    ),
+    (
+        "repl",
+        Command::Repl,
+        "Start the interactive prompt (default)",
+    ),
+    (
+        "check",
+        Command::Check,
+        "Parse expressions without evaluating them",
+    ),
+    (
+        "tokens",
+        Command::Tokens,
+        "Print the tokens of each expression",
+    ),
+    (
+        "ast",
+        Command::Ast,
+        "Print the syntax tree of each expression",
+    ),
+    (
+        "fmt",
+        Command::Format,
+        "Print each expression in canonical form",
+    ),
+];
+
+const OPTIONS: &[OptionSpec] = &[
+    OptionSpec {
+        long: "expression",
+        short: Some('e'),
+        value: Some("EXPR"),
+        help: "Expression to evaluate, ';' separates several (repeatable)",
+    },
+    OptionSpec {
+        long: "file",
+        short: Some('f'),
+        value: Some("PATH"),
+        help: "Read expressions from a file, one per line",
+    },
+    OptionSpec {
+        long: "format",
+        short: None,
+        value: Some("auto|fixed|sci|eng"),
+        help: "Number format for results",
+    },
+    OptionSpec {
+        long: "precision",
+        short: Some('p'),
+        value: Some("DIGITS"),
+        help: "Digits shown after the decimal point",
+    },
+    OptionSpec {
+        long: "angle",
+        short: None,
+        value: Some("deg|rad|grad"),
+        help: "Angle unit used by trigonometric functions",
+    },
+    OptionSpec {
+        long: "decimal",
+        short: None,
+        value: Some("half-even|half-up[:PLACES]"),
+        help: "Compute with exact decimals, rounding results to PLACES digits (default 20)",
+    },
+    OptionSpec {
+        long: "define",
+        short: Some('D'),
+        value: Some("NAME=VALUE"),
+        help: "Define a variable before evaluating (repeatable)",
+    },
+    OptionSpec {
+        long: "rates",
+        short: None,
+        value: Some("FILE"),
+        help: "Load exchange rates from a CSV or JSON file",
+    },
+    OptionSpec {
+        long: "format-expr",
+        short: None,
+        value: None,
+        help: "Print expressions in canonical form instead of evaluating (same as 'fmt')",
+    },
+    OptionSpec {
+        long: "json",
+        short: None,
+        value: None,
+        help: "Print results as JSON",
+    },
+    OptionSpec {
+        long: "latex",
+        short: None,
+        value: None,
+        help: "Print each expression and result as LaTeX",
+    },
+    OptionSpec {
+        long: "mathml",
+        short: None,
+        value: None,
+        help: "Print each expression and result as MathML",
+    },
+    OptionSpec {
+        long: "raw",
+        short: None,
+        value: None,
+        help: "Print bare values without the 'x0 = ' prefix",
+    },
+    OptionSpec {
+        long: "quiet",
+        short: Some('q'),
+        value: None,
+        help: "Only report errors",
+    },
+    OptionSpec {
+        long: "no-color",
+        short: None,
+        value: None,
+        help: "Disable colored output",
+    },
+    OptionSpec {
+        long: "help",
+        short: Some('h'),
+        value: None,
+        help: "Print this help and exit",
+    },
+    OptionSpec {
+        long: "version",
+        short: Some('V'),
+        value: None,
+        help: "Print version information and exit",
+    },
+];
+
+/// Parses the command line, excluding the program name.
+pub fn parse_args<I>(args: I) -> Result<Action, ArgError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    let mut only_positional = false;
+    let mut seen_positional = false;
+
+    while let Some(arg) = args.next() {
+        if only_positional || !arg.starts_with('-') {
+            let command = COMMANDS.iter().find(|(name, _, _)| *name == arg);
+            match command {
+                Some((_, command, _)) if !seen_positional && options.command.is_none() => {
+                    options.command = Some(*command);
+                }
+                _ => options.expressions.push(arg),
+            }
+            seen_positional = true;
+            continue;
+        }
+        if arg == "--" {
+            only_positional = true;
+            continue;
+        }
+
+        let (spec, inline_value) = match find_option(&arg) {
+            Ok(found) => found,
+            // A minus that starts no short option starts an expression, as in `-2^2`.
+            Err(_) if !arg.starts_with("--") && arg.len() > 1 => {
+                options.expressions.push(arg);
+                seen_positional = true;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let mut value = || -> Result<String, ArgError> {
+            match inline_value.clone() {
+                Some(value) => Ok(value),
+                None => args.next().ok_or(ArgError::MissingValue(spec.long)),
+            }
+        };
+
+        match spec.long {
+            "expression" => options.expressions.push(value()?),
+            "file" => options.file = Some(value()?),
+            "format" => options.format = parse_format(value()?)?,
+            "precision" => options.precision = Some(parse_precision(value()?)?),
+            "angle" => options.angle = parse_angle(value()?)?,
+            "decimal" => options.decimal = Some(parse_decimal(value()?)?),
+            "define" => options.defines.push(parse_define(value()?)?),
+            "rates" => options.rates = Some(value()?),
+            "format-expr" => options.command = Some(Command::Format),
+            "json" => options.json = true,
+            "latex" => options.markup = Some(Markup::Latex),
+            "mathml" => options.markup = Some(Markup::MathMl),
+            "raw" => options.raw = true,
+            "quiet" => options.quiet = true,
+            "no-color" => options.color = false,
+            "help" => return Ok(Action::Help),
+            "version" => return Ok(Action::Version),
+            _ => unreachable!("every option spec is handled"),
+        }
+        if spec.value.is_none() && inline_value.is_some() {
+            return Err(ArgError::UnexpectedArgument(arg));
+        }
+    }
+
+    Ok(Action::Run(options))
+}
+
+fn find_option(arg: &str) -> Result<(&'static OptionSpec, Option<String>), ArgError> {
+    let (spec, inline_value) = if let Some(long) = arg.strip_prefix("--") {
+        let (name, value) = match long.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_owned())),
+            None => (long, None),
+        };
+        (OPTIONS.iter().find(|spec| spec.long == name), value)
+    } else {
+        let mut chars = arg[1..].chars();
+        let short = chars.next();
+        let rest: String = chars.collect();
+        let value = if rest.is_empty() { None } else { Some(rest) };
+        (
+            OPTIONS
+                .iter()
+                .find(|spec| spec.short.is_some() && spec.short == short),
+            value,
+        )
+    };
+
+    match spec {
+        Some(spec) => Ok((spec, inline_value)),
+        None => Err(ArgError::UnknownOption(arg.to_owned())),
+    }
+}
+
+fn parse_format(value: String) -> Result<NumberFormat, ArgError> {
+    match value.as_str() {
+        "auto" => Ok(NumberFormat::Auto),
+        "fixed" => Ok(NumberFormat::Fixed),
+        "sci" => Ok(NumberFormat::Scientific),
+        "eng" => Ok(NumberFormat::Engineering),
+        _ => Err(ArgError::InvalidValue {
+            option: "format",
+            value,
+            expected: "auto, fixed, sci or eng",
+        }),
+    }
+}
+
+fn parse_precision(value: String) -> Result<usize, ArgError> {
+    match value.parse::<usize>() {
+        Ok(digits) if digits <= 17 => Ok(digits),
+        _ => Err(ArgError::InvalidValue {
+            option: "precision",
+            value,
+            expected: "a whole number from 0 to 17",
+        }),
+    }
+}
+
+fn parse_angle(value: String) -> Result<AngleMode, ArgError> {
+    AngleMode::from_name(&value).ok_or(ArgError::InvalidValue {
+        option: "angle",
+        value,
+        expected: "deg, rad or grad",
+    })
+}
+
+fn parse_decimal(value: String) -> Result<DecimalMode, ArgError> {
+    DecimalMode::parse(&value).ok_or(ArgError::InvalidValue {
+        option: "decimal",
+        value,
+        expected: "half-even or half-up, optionally with up to 20 places as in half-up:2",
+    })
+}
+
+fn parse_define(value: String) -> Result<(String, String), ArgError> {
+    let valid_name = |name: &str| {
+        let mut chars = name.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    match value.split_once('=') {
+        Some((name, expression)) if valid_name(name.trim()) && !expression.trim().is_empty() => {
+            Ok((name.trim().to_owned(), expression.trim().to_owned()))
+        }
+        _ => Err(ArgError::InvalidValue {
+            option: "define",
+            value,
+            expected: "NAME=VALUE",
+        }),
+    }
+}
+
+/// Builds the `--help` output from the command and option tables.
+pub fn help_text() -> String {
+    let mut help = format!(
+        "{} {}\n{}\n\nUsage: {} [COMMAND] [OPTIONS] [EXPRESSION]...\n\nCommands:\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        "Parses and solves arithmetic expressions while respecting order of operations.",
+        env!("CARGO_PKG_NAME"),
+    );
+
+    let width = COMMANDS
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, _, description) in COMMANDS {
+        help.push_str(&format!(
+            "  {:width$}  {}\n",
+            name,
+            description,
+            width = width
+        ));
+    }
+
+    help.push_str("\nOptions:\n");
+    let usages: Vec<String> = OPTIONS
+        .iter()
+        .map(|spec| {
+            let short = match spec.short {
+                Some(c) => format!("-{}, ", c),
+                None => "    ".to_owned(),
+            };
+            match spec.value {
+                Some(value) => format!("{}--{} <{}>", short, spec.long, value),
+                None => format!("{}--{}", short, spec.long),
+            }
+        })
+        .collect();
+    let width = usages.iter().map(|usage| usage.len()).max().unwrap_or(0);
+    for (usage, spec) in usages.iter().zip(OPTIONS) {
+        help.push_str(&format!(
+            "  {:width$}  {}\n",
+            usage,
+            spec.help,
+            width = width
+        ));
+    }
+    help.push_str(
+        "\nArguments that start with '-' but name no option are read as expressions.\n\
+         Put expressions that look like options after '--', each quoted as one\n\
+         argument, as in: -- \"-pi / 2\"\n",
+    );
+    help
+}
+
+pub fn version_text() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
@@ -0,0 +1,305 @@
+use super::*;
+use crate::{
+    calculator::Calculator,
+    commands,
+    decimal::Rounding,
+    units::{Quantity, Unit},
+    value::Value,
+};
+
+fn parse(args: &[&str]) -> Result<Action, ArgError> {
+    parse_args(args.iter().map(|arg| arg.to_string()))
+}
+
+fn parse_options(args: &[&str]) -> Options {
+    match parse(args).expect("arguments should parse") {
+        Action::Run(options) => options,
+        action => panic!("expected options, got {:?}", action),
+    }
+}
+
+#[test]
+fn no_arguments_starts_repl() {
+    let options = parse_options(&[]);
+    assert_eq!(options, Options::default());
+    assert_eq!(options.command(), Command::Repl);
+}
+
+#[test]
+fn legacy_expression_flag() {
+    let options = parse_options(&["--expression", "1 + 2; x0 * 3"]);
+    assert_eq!(options.expressions, vec!["1 + 2; x0 * 3"]);
+    assert_eq!(options.command(), Command::Eval);
+}
+
+#[test]
+fn repeated_expressions() {
+    let options = parse_options(&["-e", "1", "--expression=2", "-e3", "-e", "-4"]);
+    assert_eq!(options.expressions, vec!["1", "2", "3", "-4"]);
+}
+
+#[test]
+fn subcommands() {
+    let options = parse_options(&["tokens", "1 + 2"]);
+    assert_eq!(options.command(), Command::Tokens);
+    assert_eq!(options.expressions, vec!["1 + 2"]);
+
+    let options = parse_options(&["ast", "-e", "1"]);
+    assert_eq!(options.command(), Command::Ast);
+
+    let options = parse_options(&["check", "eval"]);
+    assert_eq!(options.command(), Command::Check);
+    assert_eq!(options.expressions, vec!["eval"]);
+
+    let options = parse_options(&["fmt", "2(3)"]);
+    assert_eq!(options.command(), Command::Format);
+    let options = parse_options(&["--format-expr", "2(3)"]);
+    assert_eq!(options.command(), Command::Format);
+
+    let options = parse_options(&["--quiet", "repl"]);
+    assert_eq!(options.command(), Command::Repl);
+    assert!(options.quiet);
+}
+
+#[test]
+fn command_name_after_expression_is_an_expression() {
+    let options = parse_options(&["1 + 2", "ast"]);
+    assert_eq!(options.command, None);
+    assert_eq!(options.expressions, vec!["1 + 2", "ast"]);
+}
+
+#[test]
+fn double_dash_ends_options() {
+    let options = parse_options(&["eval", "--", "-5 + 2", "--json"]);
+    assert_eq!(options.expressions, vec!["-5 + 2", "--json"]);
+    assert!(!options.json);
+}
+
+#[test]
+fn leading_minus_without_an_option_is_an_expression() {
+    let options = parse_options(&["fmt", "-2^2", "-p", "3", "-x0 * 2"]);
+    assert_eq!(options.command, Some(Command::Format));
+    assert_eq!(options.expressions, vec!["-2^2", "-x0 * 2"]);
+    assert_eq!(options.precision, Some(3));
+
+    let options = parse_options(&["-(1 + 2)", "eval"]);
+    assert_eq!(options.command, None);
+    assert_eq!(options.expressions, vec!["-(1 + 2)", "eval"]);
+}
+
+#[test]
+fn all_options() {
+    let options = parse_options(&[
+        "eval",
+        "--file",
+        "input.txt",
+        "--format",
+        "sci",
+        "-p",
+        "3",
+        "--angle=deg",
+        "--decimal",
+        "half-up:2",
+        "--json",
+        "--latex",
+        "--raw",
+        "-q",
+        "--no-color",
+        "-D",
+        "r = 2",
+        "--define",
+        "area=pi * r * r",
+        "--rates",
+        "rates.csv",
+    ]);
+    assert_eq!(
+        options,
+        Options {
+            command: Some(Command::Eval),
+            expressions: vec![],
+            file: Some("input.txt".to_owned()),
+            format: NumberFormat::Scientific,
+            precision: Some(3),
+            angle: AngleMode::Degrees,
+            decimal: Some(DecimalMode {
+                places: 2,
+                rounding: Rounding::HalfUp,
+            }),
+            json: true,
+            markup: Some(Markup::Latex),
+            raw: true,
+            quiet: true,
+            color: false,
+            defines: vec![
+                ("r".to_owned(), "2".to_owned()),
+                ("area".to_owned(), "pi * r * r".to_owned()),
+            ],
+            rates: Some("rates.csv".to_owned()),
+        }
+    );
+}
+
+#[test]
+fn help_and_version() {
+    assert_eq!(parse(&["-e", "1", "--help"]), Ok(Action::Help));
+    assert_eq!(parse(&["-h"]), Ok(Action::Help));
+    assert_eq!(parse(&["--version"]), Ok(Action::Version));
+    assert_eq!(parse(&["-V"]), Ok(Action::Version));
+}
+
+#[test]
+fn invalid_arguments() {
+    assert_eq!(
+        parse(&["--bogus"]),
+        Err(ArgError::UnknownOption("--bogus".to_owned()))
+    );
+    assert_eq!(parse(&["-e"]), Err(ArgError::MissingValue("expression")));
+    assert_eq!(
+        parse(&["--angle", "turns"]),
+        Err(ArgError::InvalidValue {
+            option: "angle",
+            value: "turns".to_owned(),
+            expected: "deg, rad or grad",
+        })
+    );
+    assert_eq!(
+        parse(&["--precision", "-1"]),
+        Err(ArgError::InvalidValue {
+            option: "precision",
+            value: "-1".to_owned(),
+            expected: "a whole number from 0 to 17",
+        })
+    );
+    assert_eq!(
+        parse(&["--decimal", "half-up:21"]),
+        Err(ArgError::InvalidValue {
+            option: "decimal",
+            value: "half-up:21".to_owned(),
+            expected: "half-even or half-up, optionally with up to 20 places as in half-up:2",
+        })
+    );
+    assert_eq!(
+        parse(&["--define", "2x=1"]),
+        Err(ArgError::InvalidValue {
+            option: "define",
+            value: "2x=1".to_owned(),
+            expected: "NAME=VALUE",
+        })
+    );
+    assert_eq!(
+        parse(&["--json=yes"]),
+        Err(ArgError::UnexpectedArgument("--json=yes".to_owned()))
+    );
+}
+
+#[test]
+fn error_messages() {
+    assert_eq!(
+        ArgError::UnknownOption("-x".to_owned()).to_string(),
+        "unknown option '-x'"
+    );
+    assert_eq!(
+        ArgError::MissingValue("file").to_string(),
+        "option '--file' requires a value"
+    );
+    assert_eq!(
+        ArgError::InvalidValue {
+            option: "format",
+            value: "hex".to_owned(),
+            expected: "auto, fixed or sci",
+        }
+        .to_string(),
+        "invalid value 'hex' for '--format' (expected auto, fixed or sci)"
+    );
+}
+
+#[test]
+fn help_lists_every_command_and_option() {
+    let help = help_text();
+    for (name, _, description) in COMMANDS {
+        assert!(help.contains(name));
+        assert!(help.contains(description));
+    }
+    for spec in OPTIONS {
+        assert!(help.contains(&format!("--{}", spec.long)));
+        assert!(help.contains(spec.help));
+    }
+    assert!(help.contains("  -e, --expression <EXPR>"));
+    assert!(help.contains("after '--'"));
+}
+
+#[test]
+fn engineering_format() {
+    let mut options = parse_options(&["--format", "eng", "--raw"]);
+    assert_eq!(options.format, NumberFormat::Engineering);
+    let output = |value: Value, options: &Options| {
+        crate::format_output("", 0, &value, &options.settings(), options)
+    };
+    assert_eq!(output(Value::Number(4700.0), &options), "4.7 k");
+    assert_eq!(output(Value::Number(-0.00033), &options), "-330.0 µ");
+    assert_eq!(output(Value::Number(12.0), &options), "12.0");
+    assert_eq!(output(Value::Number(2e21), &options), "2.0e21");
+
+    // The prefix joins a unit that can take one.
+    let quantity = |value, unit| Value::Quantity(Quantity::new(value, Unit::parse(unit).unwrap()));
+    assert_eq!(output(quantity(4700.0, "V"), &options), "4.7 kV");
+    assert_eq!(output(quantity(0.0033, "A"), &options), "3.3 mA");
+    assert_eq!(output(quantity(4700.0, "m/s"), &options), "4.7e3 m/s");
+    assert_eq!(output(quantity(5000.0, "km"), &options), "5.0e3 km");
+
+    options.precision = Some(1);
+    assert_eq!(output(Value::Number(999.96), &options), "1.0 k");
+}
+
+#[test]
+fn list_output() {
+    let options = parse_options(&["--format", "eng", "--raw"]);
+    let list = Value::List(vec![
+        Value::Number(4700.0),
+        Value::List(vec![Value::Number(12.0)]),
+    ]);
+    assert_eq!(
+        crate::format_output("", 0, &list, &options.settings(), &options),
+        "[4.7 k, [12.0]]"
+    );
+    let json = crate::result_json("[4700, [12]]", 0, &Ok(list)).to_string();
+    assert!(json.contains("\"value\": [4700, [12]]"), "{}", json);
+    assert!(json.contains("\"type\": \"list\""), "{}", json);
+}
+
+#[test]
+fn integer_output_keeps_every_digit() {
+    let options = parse_options(&["--raw"]);
+    let fib = Calculator::new().eval("fib(100)").unwrap();
+    assert_eq!(
+        crate::format_output("", 0, &fib, &options.settings(), &options),
+        "354224848179261915075"
+    );
+    let json = crate::result_json("fib(100)", 0, &Ok(fib)).to_string();
+    assert!(
+        json.contains("\"value\": 354224848179261915075, \"type\": \"integer\""),
+        "{}",
+        json
+    );
+
+    let options = parse_options(&["-p", "2", "--raw"]);
+    let count = Calculator::new().eval("nCr(50, 25)").unwrap();
+    assert_eq!(
+        crate::format_output("", 0, &count, &options.settings(), &options),
+        "126410606437752.0"
+    );
+}
+
+#[test]
+fn decimal_output_follows_the_session() {
+    let options = parse_options(&["-p", "2", "--raw"]);
+    let mut calculator = Calculator::with_settings(options.settings());
+    commands::run(&mut calculator, ":decimal half-up").unwrap();
+    let value = calculator.eval("0.125").unwrap();
+    let output = crate::format_output("0.125", 0, &value, calculator.settings(), &options);
+    assert_eq!(output, "0.13");
+
+    commands::run(&mut calculator, ":decimal half-even").unwrap();
+    let output = crate::format_output("0.125", 0, &value, calculator.settings(), &options);
+    assert_eq!(output, "0.12");
+}
@@ -0,0 +1,378 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    calculator::Calculator,
+    constants,
+    error::{CalcError, ErrorKind},
+    finance, format_value,
+    money::Rates,
+    render,
+    settings::{AngleMode, DecimalMode},
+    tokenize,
+    tokens::{ParenType, Token},
+    value::Value,
+};
+
+/// Commands the REPL accepts besides expressions, as (name, argument, description).
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("help", "", "List the available commands"),
+    (
+        "fmt",
+        "EXPRESSION",
+        "Show an expression in canonical form with minimal parentheses",
+    ),
+    ("latex", "EXPRESSION", "Render an expression as LaTeX"),
+    (
+        "mathml",
+        "EXPRESSION",
+        "Render an expression as presentation MathML",
+    ),
+    (
+        "angle",
+        "[deg|rad|grad]",
+        "Show or set the unit of angles in trigonometric functions",
+    ),
+    (
+        "decimal",
+        "[off|half-even|half-up] [PLACES]",
+        "Show or set exact decimal arithmetic and how it rounds",
+    ),
+    ("constants", "", "List the constants and their values"),
+    (
+        "rates",
+        "[FILE]",
+        "Load exchange rates from a CSV or JSON file, or list them",
+    ),
+    (
+        "echo",
+        "on|off",
+        "Print each input in canonical form before its result",
+    ),
+    (
+        "diff",
+        "EXPRESSION, VARIABLE",
+        "Show the derivative of an expression",
+    ),
+    (
+        "simplify",
+        "EXPRESSION",
+        "Show what an expression reduces to without evaluating it",
+    ),
+    (
+        "integrate",
+        "EXPRESSION, VARIABLE, FROM, TO",
+        "Show a definite integral with its estimated error",
+    ),
+    (
+        "solve",
+        "EQUATION, VARIABLE[, FROM, TO]",
+        "List the roots of an equation, all of them between FROM and TO",
+    ),
+    (
+        "amortize",
+        "PRINCIPAL, RATE, PERIODS",
+        "Show the schedule that repays a loan at RATE per period",
+    ),
+];
+
+/// Whether `line` is a command such as `:simplify x * 1` rather than an expression.
+pub fn is_command(line: &str) -> bool {
+    line.trim_start().starts_with(':')
+}
+
+/// Runs a REPL command and returns the text to print.
+pub fn run(calculator: &mut Calculator, line: &str) -> Result<String, CalcError> {
+    let line = line.trim().trim_start_matches(':');
+    let (name, argument) = match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (line, ""),
+    };
+    match name {
+        "help" => Ok(help_text()),
+        "fmt" => calculator.format(argument),
+        "latex" => Ok(render::latex(calculator.compile(argument)?.tree())),
+        "mathml" => Ok(render::mathml(calculator.compile(argument)?.tree())),
+        "angle" if argument.is_empty() => {
+            Ok(format!("angle {}", calculator.settings().angle.name()))
+        }
+        "angle" => {
+            calculator.settings_mut().angle = AngleMode::from_name(argument).ok_or_else(|| {
+                CalcError::new(ErrorKind::InvalidArgument, "Usage: :angle deg|rad|grad")
+            })?;
+            Ok(format!("angle {}", argument))
+        }
+        "decimal" if argument.is_empty() => Ok(decimal_text(calculator.settings().decimal)),
+        "decimal" => {
+            calculator.settings_mut().decimal = match argument {
+                "off" => None,
+                _ => Some(DecimalMode::parse(argument).ok_or_else(|| {
+                    CalcError::new(
+                        ErrorKind::InvalidArgument,
+                        "Usage: :decimal off|half-even|half-up [PLACES]",
+                    )
+                })?),
+            };
+            Ok(decimal_text(calculator.settings().decimal))
+        }
+        "constants" => Ok(constants_text(calculator)),
+        "rates" if argument.is_empty() => Ok(rates_text(calculator.rates())),
+        "rates" => {
+            calculator.set_rates(Rates::load(argument)?);
+            Ok(format!(
+                "Loaded {} exchange rates from {}",
+                calculator.rates().len(),
+                argument
+            ))
+        }
+        "echo" => {
+            calculator.settings_mut().echo = parse_switch(name, argument)?;
+            Ok(format!("echo {}", argument))
+        }
+        "diff" => match arguments(argument)?.as_slice() {
+            [expression, var] => Ok(calculator.diff(expression, var)?.to_string()),
+            _ => Err(CalcError::new(
+                ErrorKind::InvalidArgument,
+                "Usage: :diff EXPRESSION, VARIABLE",
+            )),
+        },
+        "simplify" => calculator.simplify(argument),
+        "integrate" => integrate(calculator, argument),
+        "solve" => solve(calculator, argument),
+        "amortize" => amortize(calculator, argument),
+        _ => Err(CalcError::new(
+            ErrorKind::UnknownCommand,
+            &format!("Unknown command: :{} (try :help)", name),
+        )),
+    }
+}
+
+/// The comma-separated arguments of a command, as in `:integrate sin(x), x,
+/// 0, pi`. Commas inside parentheses or brackets, as in `max(0.01, 0.02)`,
+/// belong to the argument they are in.
+fn arguments(argument: &str) -> Result<Vec<&str>, CalcError> {
+    // Spans count characters, not bytes.
+    let byte = |position: usize| {
+        argument
+            .char_indices()
+            .nth(position)
+            .map_or(argument.len(), |(index, _)| index)
+    };
+    let mut parts = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (token, (from, to)) in tokenize(argument)? {
+        match token {
+            Token::Paren(ParenType::OpenParen) | Token::Bracket(ParenType::OpenParen) => depth += 1,
+            Token::Paren(ParenType::CloseParen) | Token::Bracket(ParenType::CloseParen) => {
+                depth = depth.saturating_sub(1)
+            }
+            Token::Comma if depth == 0 => {
+                parts.push(argument[byte(start)..byte(from)].trim());
+                start = to;
+            }
+            _ => {}
+        }
+    }
+    parts.push(argument[byte(start)..].trim());
+    Ok(parts)
+}
+
+/// `:integrate sin(x), x, 0, pi`, printed as the value and its error bound.
+fn integrate(calculator: &Calculator, argument: &str) -> Result<String, CalcError> {
+    let [expression, var, from, to] = arguments(argument)?[..] else {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            "Usage: :integrate EXPRESSION, VARIABLE, FROM, TO",
+        ));
+    };
+    let integral = calculator.integrate(
+        expression,
+        var,
+        bound(calculator, from)?,
+        bound(calculator, to)?,
+    )?;
+    Ok(format!(
+        "{} +/- {:.1e}",
+        format_value(integral.value),
+        integral.error
+    ))
+}
+
+/// Evaluates a command argument that must be a number, such as an interval end.
+fn bound(calculator: &Calculator, text: &str) -> Result<f64, CalcError> {
+    let value = calculator.compile(text)?.eval(calculator)?;
+    value.as_number().ok_or_else(|| {
+        CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("Expected a number but got {}", value.described()),
+        )
+    })
+}
+
+/// `:solve x^2 = 2, x` or `:solve x^2 = 2, x, -5, 5`.
+fn solve(calculator: &Calculator, argument: &str) -> Result<String, CalcError> {
+    let (equation, var, interval) = match arguments(argument)?[..] {
+        [equation, var] => (equation, var, None),
+        [equation, var, from, to] => (
+            equation,
+            var,
+            Some((bound(calculator, from)?, bound(calculator, to)?)),
+        ),
+        _ => {
+            return Err(CalcError::new(
+                ErrorKind::InvalidArgument,
+                "Usage: :solve EQUATION, VARIABLE[, FROM, TO]",
+            ))
+        }
+    };
+    let roots = calculator.solve(equation, var, interval)?;
+    let lines: Vec<String> = roots
+        .iter()
+        .map(|root| format!("{} = {}", var, format_value(*root)))
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+/// `:amortize 200000, 0.05/12, 360`, one row per payment and a row of totals.
+fn amortize(calculator: &Calculator, argument: &str) -> Result<String, CalcError> {
+    let [principal, rate, periods] = arguments(argument)?[..] else {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            "Usage: :amortize PRINCIPAL, RATE, PERIODS",
+        ));
+    };
+    let schedule = finance::amortize(
+        bound(calculator, principal)?,
+        bound(calculator, rate)?,
+        bound(calculator, periods)?,
+    )?;
+
+    let mut rows =
+        vec![["period", "payment", "interest", "principal", "balance"].map(str::to_owned)];
+    for row in &schedule {
+        rows.push([
+            row.period.to_string(),
+            format!("{:.2}", row.payment),
+            format!("{:.2}", row.interest),
+            format!("{:.2}", row.principal),
+            format!("{:.2}", row.balance),
+        ]);
+    }
+    let total = |part: fn(&finance::Installment) -> f64| {
+        format!("{:.2}", schedule.iter().map(part).sum::<f64>())
+    };
+    rows.push([
+        "total".to_owned(),
+        total(|row| row.payment),
+        total(|row| row.interest),
+        total(|row| row.principal),
+        String::new(),
+    ]);
+
+    let widths: Vec<usize> = (0..5)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect();
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:>width$}", cell, width = width))
+                .collect();
+            cells.join("  ").trim_end().to_owned()
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+fn decimal_text(mode: Option<DecimalMode>) -> String {
+    match mode {
+        Some(mode) => format!("decimal {}", mode),
+        None => "decimal off".to_owned(),
+    }
+}
+
+fn parse_switch(name: &str, argument: &str) -> Result<bool, CalcError> {
+    match argument {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!("Usage: :{} on|off", name),
+        )),
+    }
+}
+
+/// One line per constant, with the description and unit of built-in ones.
+fn constants_text(calculator: &Calculator) -> String {
+    let constants = calculator.constants();
+    let width = constants
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<String> = constants
+        .iter()
+        .map(|(name, value)| {
+            // Built-in units are listed after the description.
+            let magnitude = match value {
+                Value::Quantity(quantity) => Value::Number(quantity.value),
+                value => (*value).clone(),
+            };
+            let line = format!(
+                "{:width$} = {}",
+                name,
+                scientific(&magnitude),
+                width = width
+            );
+            match constants::lookup(name) {
+                Some(constant) if constant.unit.is_empty() => {
+                    format!("{}  {}", line, constant.description)
+                }
+                Some(constant) => format!("{}  {} [{}]", line, constant.description, constant.unit),
+                None => line,
+            }
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// One line per currency with the amount of it one unit of the base buys.
+fn rates_text(rates: &Rates) -> String {
+    if rates.is_empty() {
+        return "No exchange rates; load them with :rates FILE".to_owned();
+    }
+    let lines: Vec<String> = rates
+        .iter()
+        .map(|(currency, rate)| format!("{} = {}", currency, rate))
+        .collect();
+    lines.join("\n")
+}
+
+/// Very large and very small values in exponent notation, e.g. `6.6743e-11`.
+fn scientific(value: &Value) -> String {
+    match value.as_number() {
+        Some(x) if x.is_finite() && x != 0.0 && !(1e-4..1e15).contains(&x.abs()) => {
+            format!("{:e}", x)
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn help_text() -> String {
+    let usages: Vec<String> = COMMANDS
+        .iter()
+        .map(|(name, argument, _)| format!(":{} {}", name, argument))
+        .collect();
+    let width = usages.iter().map(|usage| usage.len()).max().unwrap_or(0);
+    let lines: Vec<String> = usages
+        .iter()
+        .zip(COMMANDS)
+        .map(|(usage, (_, _, description))| {
+            format!("{:width$}  {}", usage, description, width = width)
+        })
+        .collect();
+    lines.join("\n")
+}
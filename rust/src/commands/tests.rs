@@ -0,0 +1,241 @@
+use super::*;
+
+#[test]
+fn recognizes_commands() {
+    assert!(is_command(":help"));
+    assert!(is_command("  :simplify x"));
+    assert!(!is_command("x0 + 1"));
+}
+
+#[test]
+fn simplify_command() {
+    let mut calculator = Calculator::new();
+    assert_eq!(
+        run(&mut calculator, ":simplify 2*3 + x * 1"),
+        Ok("6 + x".to_owned())
+    );
+    assert!(calculator.history().is_empty());
+
+    let error = run(&mut calculator, ":simplify").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::EmptyExpression);
+
+    assert_eq!(
+        run(&mut calculator, ":simplify y * (x - x)"),
+        Ok("y * (x - x)".to_owned())
+    );
+    run(&mut calculator, ":decimal half-even").unwrap();
+    assert_eq!(
+        run(&mut calculator, ":simplify y * (x - x)"),
+        Ok("0".to_owned())
+    );
+    assert_eq!(
+        run(&mut calculator, ":simplify 0.1 + 0.2 + x"),
+        Ok("0.3 + x".to_owned())
+    );
+}
+
+#[test]
+fn fmt_and_echo_commands() {
+    let mut calculator = Calculator::new();
+    assert_eq!(
+        run(&mut calculator, ":fmt ((1+2))*x(3)"),
+        Ok("(1 + 2) * x(3)".to_owned())
+    );
+
+    assert_eq!(run(&mut calculator, ":echo on"), Ok("echo on".to_owned()));
+    assert!(calculator.settings().echo);
+    let error = run(&mut calculator, ":echo yes").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    assert!(calculator.settings().echo);
+}
+
+#[test]
+fn render_commands() {
+    let mut calculator = Calculator::new();
+    assert_eq!(
+        run(&mut calculator, ":latex sqrt(x^2 + 1) / 2"),
+        Ok("\\frac{\\sqrt{x^{2} + 1}}{2}".to_owned())
+    );
+    assert!(run(&mut calculator, ":mathml 1/x")
+        .unwrap()
+        .ends_with("<mfrac><mn>1</mn><mi>x</mi></mfrac></math>"));
+}
+
+#[test]
+fn diff_command() {
+    let mut calculator = Calculator::new();
+    assert_eq!(
+        run(&mut calculator, ":diff x^3 + sin(x), x"),
+        Ok("3 * x ^ 2 + cos(x)".to_owned())
+    );
+    assert_eq!(
+        run(&mut calculator, ":diff max(x, 1) * t, t"),
+        Ok("max(x, 1)".to_owned())
+    );
+    let error = run(&mut calculator, ":diff x^2").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn unknown_commands() {
+    let mut calculator = Calculator::new();
+    let error = run(&mut calculator, ":frobnicate 1").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnknownCommand);
+    assert_eq!(error.message, "Unknown command: :frobnicate (try :help)");
+    assert!(run(&mut calculator, ":help").unwrap().contains(":simplify"));
+}
+
+#[test]
+fn solve_command() {
+    let mut calculator = Calculator::new();
+    assert_eq!(
+        run(&mut calculator, ":solve x^2 = 4, x, -5, 5"),
+        Ok("x = -2.0\nx = 2.0".to_owned())
+    );
+    assert_eq!(
+        run(&mut calculator, ":solve max(x, 1, 2) = 3, x"),
+        Ok("x = 3.0".to_owned())
+    );
+    assert_eq!(
+        run(&mut calculator, ":solve x, x, 1 - 2, pi"),
+        Ok("x = 0.0".to_owned())
+    );
+    assert_eq!(
+        run(
+            &mut calculator,
+            ":solve max(x, 1) = 3, x, min(0, 1), max(4, 5)"
+        ),
+        Ok("x = 3.0".to_owned())
+    );
+    let error = run(&mut calculator, ":solve x^2 = 2").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn integrate_command() {
+    let mut calculator = Calculator::new();
+    let output = run(&mut calculator, ":integrate 2 * x, x, 0, 1").unwrap();
+    assert!(output.starts_with("1.0 +/- "), "{}", output);
+    let output = run(
+        &mut calculator,
+        ":integrate max(x, 0), x, min(-1, 0), hypot(0, 1)",
+    )
+    .unwrap();
+    assert!(output.starts_with("0.5 +/- "), "{}", output);
+    let error = run(&mut calculator, ":integrate x, x, 0").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn angle_command() {
+    let mut calculator = Calculator::new();
+    assert_eq!(run(&mut calculator, ":angle"), Ok("angle rad".to_owned()));
+    assert_eq!(
+        run(&mut calculator, ":angle deg"),
+        Ok("angle deg".to_owned())
+    );
+    assert_eq!(calculator.settings().angle, AngleMode::Degrees);
+    assert_eq!(
+        run(&mut calculator, ":diff sin(x), x"),
+        Ok("cos(x) * 0.017453292519943295".to_owned())
+    );
+    let error = run(&mut calculator, ":angle turns").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    assert_eq!(calculator.settings().angle, AngleMode::Degrees);
+}
+
+#[test]
+fn constants_command() {
+    let mut calculator = Calculator::new();
+    calculator.register_constant("answer", 42.0).unwrap();
+    let output = run(&mut calculator, ":constants").unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "answer = 42.0");
+    assert!(lines.contains(&"G      = 6.6743e-11  gravitational constant [m^3/(kg*s^2)]"));
+    assert!(lines.contains(
+        &"pi     = 3.141592653589793  ratio of a circle's circumference to its diameter"
+    ));
+}
+
+#[test]
+fn rates_command() {
+    let mut calculator = Calculator::new();
+    assert_eq!(
+        run(&mut calculator, ":rates"),
+        Ok("No exchange rates; load them with :rates FILE".to_owned())
+    );
+    let path = std::env::temp_dir().join(format!("rates-{}.csv", std::process::id()));
+    std::fs::write(&path, "USD,1\nEUR,0.92\n").unwrap();
+    let loaded = run(&mut calculator, &format!(":rates {}", path.display()));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        loaded,
+        Ok(format!("Loaded 2 exchange rates from {}", path.display()))
+    );
+    assert_eq!(
+        run(&mut calculator, ":rates"),
+        Ok("EUR = 0.92\nUSD = 1".to_owned())
+    );
+
+    let error = run(&mut calculator, ":rates /nonexistent/rates.json").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    assert_eq!(calculator.rates().len(), 2);
+}
+
+#[test]
+fn decimal_command() {
+    let mut calculator = Calculator::new();
+    assert_eq!(
+        run(&mut calculator, ":decimal"),
+        Ok("decimal off".to_owned())
+    );
+    assert_eq!(
+        run(&mut calculator, ":decimal half-up 2"),
+        Ok("decimal half-up 2".to_owned())
+    );
+    assert_eq!(calculator.eval("10 / 3 * 3").unwrap().to_string(), "9.99");
+    assert_eq!(
+        run(&mut calculator, ":decimal off"),
+        Ok("decimal off".to_owned())
+    );
+    assert_eq!(calculator.settings().decimal, None);
+
+    let error = run(&mut calculator, ":decimal up").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn amortize_command() {
+    let mut calculator = Calculator::new();
+    let table = run(&mut calculator, ":amortize 1000, 0.01, 3").unwrap();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "period  payment  interest  principal  balance");
+    assert_eq!(lines[1], "     1   340.02     10.00     330.02   669.98");
+    assert_eq!(lines[3], "     3   340.02      3.37     336.66     0.00");
+    assert_eq!(lines[4], " total  1020.07     20.07    1000.00");
+
+    // Commas inside a call belong to its argument.
+    assert_eq!(
+        run(&mut calculator, ":amortize 1000, max(0.01, 0.005), 3"),
+        Ok(table)
+    );
+
+    let error = run(&mut calculator, ":amortize 1000, 0.01").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = run(&mut calculator, ":amortize 1000, 0.01, 2.5").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+    assert_eq!(
+        error.message,
+        "amortize: the number of periods must be a positive whole number"
+    );
+    for periods in ["10^18", "100000000"] {
+        let command = format!(":amortize 100000, 0.01, {}", periods);
+        let error = run(&mut calculator, &command).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArgument);
+        assert_eq!(
+            error.message,
+            "amortize: a schedule is limited to 1000000 periods"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Display;
+
+use crate::{
+    ast::AST,
+    environment::Environment,
+    error::{CalcError, ErrorKind},
+    value::Value,
+    vm::Program,
+};
+
+/// An expression prepared for repeated evaluation with different bindings.
+///
+/// Names listed as parameters when compiling are resolved to slots once, so
+/// evaluating only indexes into the values passed in. Any other name is looked
+/// up in the environment on each evaluation. The expression runs as a
+/// [`Program`] on the stack machine in [`crate::vm`].
+#[derive(Debug)]
+pub struct Compiled {
+    source: String,
+    params: Vec<String>,
+    program: Program,
+}
+
+impl Compiled {
+    pub fn new(source: &str, tree: &AST, params: &[&str]) -> Result<Self, CalcError> {
+        let params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+        let program = Program::compile(tree, &params)?;
+        Ok(Compiled {
+            source: source.to_owned(),
+            params,
+            program,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The parameter names, in the order their values are passed to `eval`.
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Evaluates with `args` bound to the parameters in order.
+    pub fn eval(&self, env: &dyn Environment, args: &[Value]) -> Result<Value, CalcError> {
+        if args.len() != self.params.len() {
+            return Err(CalcError::new(
+                ErrorKind::ArityMismatch,
+                &format!(
+                    "Expected {} argument(s) but got {}",
+                    self.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+        self.program.run(env, args)
+    }
+
+    /// Like `eval`, for the common case where every binding is a number.
+    pub fn eval_numbers(&self, env: &dyn Environment, args: &[f64]) -> Result<f64, CalcError> {
+        let args: Vec<Value> = args.iter().map(|x| Value::Number(*x)).collect();
+        let value = self.eval(env, &args)?;
+        value.as_number().ok_or_else(|| {
+            CalcError::new(
+                ErrorKind::TypeMismatch,
+                &format!("Expected a number but got {}", value.described()),
+            )
+        })
+    }
+}
+
+impl Display for Compiled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
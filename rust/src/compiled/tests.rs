@@ -0,0 +1,71 @@
+use crate::calculator::Calculator;
+
+use super::*;
+
+#[test]
+fn params_are_bound_by_position() {
+    let calculator = Calculator::new();
+    let expr = calculator
+        .compile_with("price * qty - discount", &["price", "qty", "discount"])
+        .unwrap();
+    assert_eq!(expr.params(), &["price", "qty", "discount"]);
+    assert_eq!(expr.eval_numbers(&calculator, &[2.0, 3.0, 1.0]), Ok(5.0));
+    assert_eq!(expr.eval_numbers(&calculator, &[10.0, 0.5, 0.0]), Ok(5.0));
+    assert_eq!(
+        expr.eval(&calculator, &vec![Value::Number(1.0); 3]),
+        Ok(Value::Number(0.0))
+    );
+}
+
+#[test]
+fn other_names_come_from_the_environment() {
+    let mut calculator = Calculator::new();
+    calculator.set_var("rate", 0.5).unwrap();
+    calculator.define_fn("double", &["x"], "x * 2").unwrap();
+    let expr = calculator
+        .compile_with("double(amount) * rate + max(amount, 1)", &["amount"])
+        .unwrap();
+    assert_eq!(expr.eval_numbers(&calculator, &[4.0]), Ok(8.0));
+
+    calculator.set_var("rate", 1.0).unwrap();
+    assert_eq!(expr.eval_numbers(&calculator, &[4.0]), Ok(12.0));
+}
+
+#[test]
+fn params_shadow_variables() {
+    let mut calculator = Calculator::new();
+    calculator.set_var("x", 100.0).unwrap();
+    let expr = calculator.compile_with("x(2) + x", &["x"]).unwrap();
+    assert_eq!(expr.eval_numbers(&calculator, &[3.0]), Ok(9.0));
+}
+
+#[test]
+fn evaluation_errors() {
+    let calculator = Calculator::new();
+    let expr = calculator.compile_with("a / b + d", &["a", "b"]).unwrap();
+
+    let error = expr.eval_numbers(&calculator, &[1.0]).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+
+    let error = expr.eval_numbers(&calculator, &[1.0, 0.0]).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DivideByZero);
+    assert_eq!(error.span, Some((0, 5)));
+
+    let error = expr.eval_numbers(&calculator, &[1.0, 2.0]).unwrap_err();
+    assert_eq!(error.to_string(), "Unknown Variable: d");
+    assert_eq!(error.span, Some((8, 9)));
+
+    let error = calculator
+        .compile_with("a", &["2a"])
+        .expect_err("params must be valid names");
+    assert_eq!(error.kind, ErrorKind::InvalidName);
+}
+
+#[test]
+fn special_forms_see_params() {
+    let calculator = Calculator::new();
+    let expr = calculator
+        .compile_with("diff(x^2 * a, x, 3) + a", &["a"])
+        .unwrap();
+    assert_eq!(expr.eval_numbers(&calculator, &[2.0]), Ok(14.0));
+}
@@ -0,0 +1,85 @@
+use std::f64::consts::{E, PI, TAU};
+
+use crate::{
+    units::{Quantity, Unit},
+    value::Value,
+};
+
+/// A named, read-only value every calculator starts with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constant {
+    pub name: &'static str,
+    pub value: f64,
+    /// SI unit of a physical constant, empty for plain numbers.
+    pub unit: &'static str,
+    pub description: &'static str,
+}
+
+impl Constant {
+    /// The value the calculator binds the name to: a quantity in the
+    /// constant's unit, or a plain number if it has none.
+    pub fn to_value(&self) -> Value {
+        match Unit::parse(self.unit) {
+            Ok(unit) if !self.unit.is_empty() => Value::Quantity(Quantity::new(self.value, unit)),
+            _ => Value::Number(self.value),
+        }
+    }
+}
+
+const fn constant(
+    name: &'static str,
+    value: f64,
+    unit: &'static str,
+    description: &'static str,
+) -> Constant {
+    Constant {
+        name,
+        value,
+        unit,
+        description,
+    }
+}
+
+/// The built-in constants. Physical constants use the CODATA 2018 values,
+/// which are exact where the SI defines them.
+pub const CONSTANTS: &[Constant] = &[
+    constant(
+        "pi",
+        PI,
+        "",
+        "ratio of a circle's circumference to its diameter",
+    ),
+    constant(
+        "tau",
+        TAU,
+        "",
+        "ratio of a circle's circumference to its radius",
+    ),
+    constant("e", E, "", "base of the natural logarithm"),
+    constant("phi", 1.618033988749895, "", "golden ratio"),
+    constant("inf", f64::INFINITY, "", "infinity"),
+    constant("nan", f64::NAN, "", "not a number"),
+    constant("c", 299792458.0, "m/s", "speed of light in vacuum"),
+    constant("G", 6.6743e-11, "m^3/(kg*s^2)", "gravitational constant"),
+    constant("g0", 9.80665, "m/s^2", "standard acceleration of gravity"),
+    constant("h", 6.62607015e-34, "J*s", "Planck constant"),
+    constant(
+        "hbar",
+        1.0545718176461565e-34,
+        "J*s",
+        "reduced Planck constant",
+    ),
+    constant("k_B", 1.380649e-23, "J/K", "Boltzmann constant"),
+    constant("N_A", 6.02214076e23, "1/mol", "Avogadro constant"),
+    constant("R", 8.31446261815324, "J/(mol*K)", "molar gas constant"),
+    constant("q_e", 1.602176634e-19, "C", "elementary charge"),
+    constant("m_e", 9.1093837015e-31, "kg", "electron mass"),
+    constant("m_p", 1.67262192369e-27, "kg", "proton mass"),
+    constant("eps0", 8.8541878128e-12, "F/m", "vacuum permittivity"),
+    constant("mu0", 1.25663706212e-6, "N/A^2", "vacuum permeability"),
+];
+
+/// The built-in constant called `name`.
+pub fn lookup(name: &str) -> Option<&'static Constant> {
+    CONSTANTS.iter().find(|constant| constant.name == name)
+}
@@ -0,0 +1,403 @@
+//! Dates, times of day and durations, for deadline arithmetic such as
+//! `2026-12-25 - today()` or `now() + 90min`.
+//!
+//! Dates and times are in UTC and have a resolution of a second. A
+//! difference of two dates is a [`Duration`], shown as `68d 3h`; adding a
+//! duration or a time quantity to a date moves it.
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    error::{CalcError, ErrorKind},
+    functions::{Arity, ValueFunction},
+    tokens::Op,
+    units::{self, Quantity, Unit},
+    value::Value,
+};
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// What a [`DateTime`] stands for, which decides how it is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A calendar day, as in `2026-10-18`.
+    Date,
+    /// A moment on a day, as in `2026-10-18 14:30`.
+    DateTime,
+    /// A time of day on no particular day, as in `14:30`.
+    Clock,
+}
+
+/// A date, a date with a time, or a time of day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateTime {
+    /// Since 1970-01-01 00:00 UTC, or since midnight for a time of day.
+    seconds: f64,
+    kind: Kind,
+}
+
+/// Days from 1970-01-01 to the given day of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The year, month and day `days` after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn invalid_date(text: &str) -> CalcError {
+    CalcError::new(
+        ErrorKind::InvalidArgument,
+        &format!("Invalid date or time: {}", text),
+    )
+}
+
+impl DateTime {
+    /// Reads a literal as the lexer finds it: `2026-10-18`, `14:30`,
+    /// `14:30:05` or a date and a time separated by a space.
+    pub fn parse(text: &str) -> Result<DateTime, CalcError> {
+        let number = |part: &str| part.parse::<u32>().map_err(|_| invalid_date(text));
+        let date = |part: &str| -> Result<f64, CalcError> {
+            let fields: Vec<&str> = part.split('-').collect();
+            let [year, month, day] = fields.as_slice() else {
+                return Err(invalid_date(text));
+            };
+            let (year, month, day) = (number(year)? as i64, number(month)?, number(day)?);
+            if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+                return Err(invalid_date(text));
+            }
+            Ok(days_from_civil(year, month, day) as f64 * SECONDS_PER_DAY)
+        };
+        let clock = |part: &str| -> Result<f64, CalcError> {
+            let fields = part
+                .split(':')
+                .map(number)
+                .collect::<Result<Vec<u32>, CalcError>>()?;
+            let (hour, minute, second) = match fields.as_slice() {
+                [hour, minute] => (*hour, *minute, 0),
+                [hour, minute, second] => (*hour, *minute, *second),
+                _ => return Err(invalid_date(text)),
+            };
+            if hour > 23 || minute > 59 || second > 59 {
+                return Err(invalid_date(text));
+            }
+            Ok((hour * 3600 + minute * 60 + second) as f64)
+        };
+        match text.split_once(' ') {
+            Some((day, time)) => Ok(DateTime {
+                seconds: date(day)? + clock(time.trim())?,
+                kind: Kind::DateTime,
+            }),
+            None if text.contains(':') => Ok(DateTime {
+                seconds: clock(text)?,
+                kind: Kind::Clock,
+            }),
+            None => Ok(DateTime {
+                seconds: date(text)?,
+                kind: Kind::Date,
+            }),
+        }
+    }
+
+    /// The current moment, to the second.
+    pub fn now() -> DateTime {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_secs() as f64);
+        DateTime {
+            seconds,
+            kind: Kind::DateTime,
+        }
+    }
+
+    /// The current day.
+    pub fn today() -> DateTime {
+        let now = Self::now();
+        DateTime {
+            seconds: (now.seconds / SECONDS_PER_DAY).floor() * SECONDS_PER_DAY,
+            kind: Kind::Date,
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Seconds since 1970-01-01 00:00 UTC, or since midnight for a time of day.
+    pub fn seconds(&self) -> f64 {
+        self.seconds
+    }
+
+    /// The ISO day of the week, from 1 for Monday to 7 for Sunday, or `None`
+    /// for a time of day.
+    pub fn weekday(&self) -> Option<u32> {
+        if self.kind == Kind::Clock {
+            return None;
+        }
+        // 1970-01-01 was a Thursday.
+        let days = (self.seconds / SECONDS_PER_DAY).floor() as i64;
+        Some((days + 3).rem_euclid(7) as u32 + 1)
+    }
+
+    /// This moment moved by `seconds`. A date moved by whole days stays a
+    /// date, and a time of day wraps around midnight.
+    fn shifted(&self, seconds: f64) -> DateTime {
+        let moved = self.seconds + seconds;
+        match self.kind {
+            Kind::Clock => DateTime {
+                seconds: moved.rem_euclid(SECONDS_PER_DAY),
+                kind: Kind::Clock,
+            },
+            Kind::Date if seconds % SECONDS_PER_DAY == 0.0 => DateTime {
+                seconds: moved,
+                kind: Kind::Date,
+            },
+            _ => DateTime {
+                seconds: moved,
+                kind: Kind::DateTime,
+            },
+        }
+    }
+
+    fn write_clock(f: &mut std::fmt::Formatter<'_>, seconds: i64) -> std::fmt::Result {
+        let (hour, minute, second) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+        write!(f, "{:02}:{:02}", hour, minute)?;
+        if second != 0 {
+            write!(f, ":{:02}", second)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let seconds = self.seconds.floor() as i64;
+        if self.kind == Kind::Clock {
+            return Self::write_clock(f, seconds);
+        }
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)?;
+        if self.kind == Kind::DateTime {
+            f.write_str(" ")?;
+            Self::write_clock(f, seconds.rem_euclid(86400))?;
+        }
+        Ok(())
+    }
+}
+
+/// A length of time, shown in days, hours, minutes and seconds as in
+/// `3d 4h 30min`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Duration {
+    pub seconds: f64,
+}
+
+impl Duration {
+    pub fn new(seconds: f64) -> Self {
+        Duration { seconds }
+    }
+}
+
+impl Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.seconds.is_finite() {
+            return write!(f, "{}s", self.seconds);
+        }
+        // Whole milliseconds, so that `0.1d` does not show a stray fraction.
+        let millis = (self.seconds.abs() * 1000.0).round() as u64;
+        let (days, rest) = (millis / 86_400_000, millis % 86_400_000);
+        let (hours, rest) = (rest / 3_600_000, rest % 3_600_000);
+        let (minutes, rest) = (rest / 60_000, rest % 60_000);
+        let seconds = rest as f64 / 1000.0;
+        let mut parts = vec![];
+        for (amount, unit) in [(days, "d"), (hours, "h"), (minutes, "min")] {
+            if amount != 0 {
+                parts.push(format!("{}{}", amount, unit));
+            }
+        }
+        if seconds != 0.0 || parts.is_empty() {
+            parts.push(format!("{}s", seconds));
+        }
+        let sign = if self.seconds < 0.0 && millis != 0 {
+            "-"
+        } else {
+            ""
+        };
+        write!(f, "{}{}", sign, parts.join(" "))
+    }
+}
+
+/// The length of `value` in seconds, if it is a duration or a time quantity.
+fn seconds_of(value: &Value) -> Option<f64> {
+    match value {
+        Value::Duration(duration) => Some(duration.seconds),
+        Value::Quantity(quantity) if quantity.unit.dimension() == units::TIME => {
+            Some(quantity.value * quantity.unit.scale())
+        }
+        _ => None,
+    }
+}
+
+/// A time quantity such as `3d 4h` as a duration.
+pub fn duration_of(value: Value) -> Result<Value, CalcError> {
+    seconds_of(&value)
+        .map(|seconds| Value::Duration(Duration::new(seconds)))
+        .ok_or_else(|| {
+            CalcError::new(
+                ErrorKind::TypeMismatch,
+                &format!("Expected a duration but got {}", value.described()),
+            )
+        })
+}
+
+/// A duration as a quantity of seconds, which can be converted to any unit
+/// of time.
+pub fn as_quantity(duration: &Duration) -> Quantity {
+    let second = Unit::new(vec![("s".to_owned(), 1)]).unwrap_or_default();
+    Quantity::new(duration.seconds, second)
+}
+
+/// `left oper right` where a side is a date or a duration. Dates move by
+/// durations and time quantities, and their differences are durations.
+/// Durations scale by numbers and otherwise act as quantities of seconds.
+pub fn operate(oper: &Op, left: Value, right: Value) -> Result<Value, CalcError> {
+    let verb = match oper {
+        Op::Add => "add",
+        Op::Sub => "subtract",
+        Op::Mult => "multiply",
+        Op::Div => "divide",
+        Op::Pow => "raise",
+    };
+    let mismatch = || {
+        CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!(
+                "Cannot {} {} and {}",
+                verb,
+                left.described(),
+                right.described()
+            ),
+        )
+    };
+    let duration = |seconds: f64| Ok(Value::Duration(Duration::new(seconds)));
+    match (oper, &left, &right) {
+        (Op::Add, Value::Date(date), other) | (Op::Add, other, Value::Date(date)) => {
+            match (date.kind, other) {
+                // A day and a time of day make a moment.
+                (Kind::Date, Value::Date(clock)) if clock.kind == Kind::Clock => {
+                    Ok(Value::Date(date.shifted(clock.seconds)))
+                }
+                (Kind::Clock, Value::Date(day)) if day.kind == Kind::Date => {
+                    Ok(Value::Date(day.shifted(date.seconds)))
+                }
+                (_, other) => match seconds_of(other) {
+                    Some(seconds) => Ok(Value::Date(date.shifted(seconds))),
+                    None => Err(mismatch()),
+                },
+            }
+        }
+        (Op::Sub, Value::Date(a), Value::Date(b)) => {
+            if (a.kind == Kind::Clock) != (b.kind == Kind::Clock) {
+                return Err(mismatch());
+            }
+            duration(a.seconds - b.seconds)
+        }
+        (Op::Sub, Value::Date(date), other) => match seconds_of(other) {
+            Some(seconds) => Ok(Value::Date(date.shifted(-seconds))),
+            None => Err(mismatch()),
+        },
+        (_, Value::Date(_), _) | (_, _, Value::Date(_)) => Err(mismatch()),
+        (Op::Add | Op::Sub, _, _) => match (seconds_of(&left), seconds_of(&right)) {
+            (Some(a), Some(b)) if *oper == Op::Add => duration(a + b),
+            (Some(a), Some(b)) => duration(a - b),
+            _ => units::operate(oper, left, right),
+        },
+        (Op::Mult, Value::Duration(d), Value::Number(x))
+        | (Op::Mult, Value::Number(x), Value::Duration(d)) => duration(d.seconds * x),
+        (Op::Div, Value::Duration(d), Value::Number(x)) => {
+            if *x == 0.0 {
+                return Err(CalcError::new(ErrorKind::DivideByZero, "Divide by Zero"));
+            }
+            duration(d.seconds / x)
+        }
+        // How many times one length of time fits in another is a number.
+        (Op::Div, Value::Duration(d), other) | (Op::Div, other, Value::Duration(d))
+            if seconds_of(other).is_some() =>
+        {
+            let other_seconds = seconds_of(other).unwrap_or_default();
+            let (a, b) = match &left {
+                Value::Duration(_) => (d.seconds, other_seconds),
+                _ => (other_seconds, d.seconds),
+            };
+            if b == 0.0 {
+                return Err(CalcError::new(ErrorKind::DivideByZero, "Divide by Zero"));
+            }
+            Ok(Value::Number(a / b))
+        }
+        // Otherwise a duration is a quantity of seconds, as in `1h 30min * 60 km/h`.
+        _ => units::operate(oper, left, right),
+    }
+}
+
+/// `today()`, `now()` and `weekday(date)`.
+pub fn functions() -> Vec<ValueFunction> {
+    vec![
+        ValueFunction::new("today", Arity::Exact(0), |_| {
+            Ok(Value::Date(DateTime::today()))
+        }),
+        ValueFunction::new("now", Arity::Exact(0), |_| Ok(Value::Date(DateTime::now()))),
+        ValueFunction::new("weekday", Arity::Exact(1), |args| match &args[0] {
+            Value::Date(date) => date
+                .weekday()
+                .map(|day| Value::Number(day as f64))
+                .ok_or_else(|| {
+                    CalcError::new(
+                        ErrorKind::InvalidArgument,
+                        "weekday expects a date, not a time of day",
+                    )
+                }),
+            value => Err(CalcError::new(
+                ErrorKind::TypeMismatch,
+                &format!("weekday expects a date but got {}", value.described()),
+            )),
+        }),
+    ]
+}
@@ -0,0 +1,121 @@
+use crate::calculator::Calculator;
+
+use super::*;
+
+fn eval(expression: &str) -> Result<String, CalcError> {
+    Calculator::new()
+        .eval(expression)
+        .map(|value| value.to_string())
+}
+
+#[test]
+fn calendar() {
+    assert_eq!(days_from_civil(1970, 1, 1), 0);
+    assert_eq!(days_from_civil(2000, 3, 1), 11017);
+    assert_eq!(days_from_civil(1969, 12, 31), -1);
+    for days in [-719468, -1, 0, 11016, 11017, 20744, 2932896] {
+        let (year, month, day) = civil_from_days(days);
+        assert_eq!(days_from_civil(year, month, day), days);
+    }
+    assert_eq!(civil_from_days(20744), (2026, 10, 18));
+}
+
+#[test]
+fn literals() {
+    let date = DateTime::parse("2026-10-18").unwrap();
+    assert_eq!(date.kind(), Kind::Date);
+    assert_eq!(date.to_string(), "2026-10-18");
+    assert_eq!(date.weekday(), Some(7));
+    let moment = DateTime::parse("2024-02-29 23:59:30").unwrap();
+    assert_eq!(moment.kind(), Kind::DateTime);
+    assert_eq!(moment.to_string(), "2024-02-29 23:59:30");
+    assert_eq!(DateTime::parse("9:05").unwrap().to_string(), "09:05");
+    for invalid in ["2026-02-29", "2026-13-01", "2026-04-31", "24:00", "12:60"] {
+        let error = DateTime::parse(invalid).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArgument, "{}", invalid);
+    }
+}
+
+#[test]
+fn durations() {
+    let shown = |seconds: f64| Duration::new(seconds).to_string();
+    assert_eq!(shown(273600.0), "3d 4h");
+    assert_eq!(shown(5400.0), "1h 30min");
+    assert_eq!(shown(-90.5), "-1min 30.5s");
+    assert_eq!(shown(0.0), "0s");
+    assert_eq!(shown(0.0001), "0s");
+    assert_eq!(eval("3d 4h").unwrap(), "3d 4h");
+    assert_eq!(eval("-1h 30min").unwrap(), "-1h 30min");
+    assert_eq!(eval("2 * 1h 30min").unwrap(), "3h");
+    assert_eq!(eval("1h 30min / 2").unwrap(), "45min");
+    assert_eq!(eval("1d 12h / 1h").unwrap(), "36.0");
+    assert_eq!(eval("1h 30min to min").unwrap(), "90.0 min");
+    assert_eq!(eval("1h 30min + 15 min").unwrap(), "1h 45min");
+    assert_eq!(eval("1h 30min * 60 km/h").unwrap(), "90.0 km");
+    // A single quantity of time is still a quantity, in its own unit.
+    assert_eq!(eval("3d").unwrap(), "3.0 d");
+    assert_eq!(eval("3 s * 2").unwrap(), "6.0 s");
+    assert_eq!(eval("60 km / 2 h").unwrap(), "30.0 km/h");
+    assert_eq!(eval("3d to h").unwrap(), "72.0 h");
+}
+
+#[test]
+fn date_arithmetic() {
+    assert_eq!(eval("2026-12-25 - 2026-10-18").unwrap(), "68d");
+    assert_eq!(eval("2026-10-18 - 2026-12-25").unwrap(), "-68d");
+    assert_eq!(eval("2026-10-18 + 3d").unwrap(), "2026-10-21");
+    assert_eq!(eval("2026-10-18 + 2wk - 1d").unwrap(), "2026-10-31");
+    assert_eq!(eval("2026-10-18 + 3d 4h").unwrap(), "2026-10-21 04:00");
+    assert_eq!(
+        eval("2026-10-18 14:30 + 90min").unwrap(),
+        "2026-10-18 16:00"
+    );
+    assert_eq!(eval("2026-12-31 23:00 + 2h").unwrap(), "2027-01-01 01:00");
+    assert_eq!(eval("2026-10-18 + 14:30").unwrap(), "2026-10-18 14:30");
+    assert_eq!(eval("14:30 + 2026-10-18").unwrap(), "2026-10-18 14:30");
+    assert_eq!(eval("23:30 + 45min").unwrap(), "00:15");
+    assert_eq!(eval("17:00 - 9:15").unwrap(), "7h 45min");
+    assert_eq!(eval("weekday(2026-10-19)").unwrap(), "1.0");
+    assert_eq!(eval("weekday(2026-10-19 + 5d)").unwrap(), "6.0");
+}
+
+#[test]
+fn clock_functions() {
+    let mut calculator = Calculator::new();
+    let today = calculator.eval("today()").unwrap();
+    let Value::Date(today) = today else {
+        panic!("today() should be a date");
+    };
+    assert_eq!(today.kind(), Kind::Date);
+    assert_eq!(today.seconds() % 86400.0, 0.0);
+    let until = calculator.eval("now() - today()").unwrap();
+    let Value::Duration(until) = until else {
+        panic!("a difference of dates should be a duration");
+    };
+    assert!((0.0..86400.0).contains(&until.seconds));
+}
+
+#[test]
+fn mismatches() {
+    let error = |expression| eval(expression).unwrap_err().kind;
+    assert_eq!(error("2026-10-18 + 2026-10-19"), ErrorKind::TypeMismatch);
+    assert_eq!(error("2026-10-18 + 5 km"), ErrorKind::TypeMismatch);
+    assert_eq!(error("2026-10-18 * 2"), ErrorKind::TypeMismatch);
+    assert_eq!(error("2026-10-18 - 14:30"), ErrorKind::TypeMismatch);
+    assert_eq!(error("weekday(14:30)"), ErrorKind::InvalidArgument);
+    assert_eq!(error("weekday(3)"), ErrorKind::TypeMismatch);
+    assert_eq!(error("sqrt(2026-10-18)"), ErrorKind::TypeMismatch);
+    assert_eq!(error("1h 30min / 0"), ErrorKind::DivideByZero);
+    assert_eq!(error("1h 30min + 5 km"), ErrorKind::DimensionMismatch);
+    let message = |expression| eval(expression).unwrap_err().message;
+    assert_eq!(
+        message("2026-10-18 * 2"),
+        "Cannot multiply a date and an integer"
+    );
+    assert_eq!(
+        message("2026-10-18 * 2.5"),
+        "Cannot multiply a date and a float"
+    );
+    let invalid = eval("1 + 2026-02-30").unwrap_err();
+    assert_eq!(invalid.span, Some((4, 14)));
+}
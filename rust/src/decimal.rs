@@ -0,0 +1,324 @@
+#[cfg(test)]
+mod tests;
+
+use std::{cmp::Ordering, fmt::Display, ops::Neg};
+
+use crate::{
+    error::{CalcError, ErrorKind},
+    settings::DecimalMode,
+    tokens::Op,
+};
+
+/// Fractional digits kept by division, and by products that would need more.
+pub const MAX_SCALE: u32 = 20;
+
+/// How a decimal is rounded to fewer digits.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Rounding {
+    /// Ties go to the even neighbour, so `0.125` becomes `0.12`.
+    HalfEven,
+    /// Ties go away from zero, so `0.125` becomes `0.13`.
+    HalfUp,
+}
+
+impl Rounding {
+    /// The name used by `--decimal` and `:decimal`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rounding::HalfEven => "half-even",
+            Rounding::HalfUp => "half-up",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rounding> {
+        [Rounding::HalfEven, Rounding::HalfUp]
+            .into_iter()
+            .find(|rounding| rounding.name() == name)
+    }
+}
+
+/// An exact decimal number, `digits / 10^scale`, without trailing zeros after
+/// the decimal point so that equal numbers compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    digits: i128,
+    scale: u32,
+}
+
+fn pow10(exponent: u32) -> Option<i128> {
+    10i128.checked_pow(exponent)
+}
+
+/// `digits` with its last `drop` digits rounded away.
+fn round_digits(digits: i128, drop: u32, rounding: Rounding) -> i128 {
+    let Some(divisor) = pow10(drop) else {
+        // More digits than an i128 holds: less than half of the unit.
+        return 0;
+    };
+    let (quotient, remainder) = (digits / divisor, (digits % divisor).abs());
+    let away = match remainder.cmp(&(divisor - remainder)) {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        Ordering::Equal => rounding == Rounding::HalfUp || quotient % 2 != 0,
+    };
+    if away {
+        quotient + digits.signum()
+    } else {
+        quotient
+    }
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal {
+        digits: 0,
+        scale: 0,
+    };
+
+    pub fn new(mut digits: i128, mut scale: u32) -> Self {
+        while scale > 0 && digits % 10 == 0 {
+            digits /= 10;
+            scale -= 1;
+        }
+        Decimal { digits, scale }
+    }
+
+    /// Reads a number written as `-12.50` or `1.5e3`, keeping every digit.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+            None => (text, 0),
+        };
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if whole.len() + fraction.len() == 0 || !all_digits(whole) || !all_digits(fraction) {
+            return None;
+        }
+        let mut digits: i128 = 0;
+        for c in whole.chars().chain(fraction.chars()) {
+            digits = digits
+                .checked_mul(10)?
+                .checked_add(c.to_digit(10)? as i128)?;
+        }
+        if negative {
+            digits = -digits;
+        }
+        let scale = fraction.len() as i32 - exponent;
+        if scale < 0 {
+            digits = digits.checked_mul(pow10(scale.unsigned_abs())?)?;
+            return Some(Self::new(digits, 0));
+        }
+        Some(Self::new(digits, scale as u32))
+    }
+
+    /// The decimal a float prints as, which is the number it was written as:
+    /// `0.1` is exactly one tenth rather than the nearest binary fraction.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if value.is_finite() {
+            Self::parse(&value.to_string())
+        } else {
+            None
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.digits < 0
+    }
+
+    /// Number of digits after the decimal point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Both digit counts brought to the larger scale.
+    fn aligned(&self, other: &Decimal) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        Some((
+            self.digits.checked_mul(pow10(scale - self.scale)?)?,
+            other.digits.checked_mul(pow10(scale - other.scale)?)?,
+            scale,
+        ))
+    }
+
+    pub fn checked_add(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.aligned(other)?;
+        Some(Self::new(a.checked_add(b)?, scale))
+    }
+
+    pub fn checked_sub(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.aligned(other)?;
+        Some(Self::new(a.checked_sub(b)?, scale))
+    }
+
+    pub fn checked_mul(&self, other: &Decimal) -> Option<Decimal> {
+        let digits = self.digits.checked_mul(other.digits)?;
+        let scale = self.scale + other.scale;
+        if scale > MAX_SCALE {
+            let digits = round_digits(digits, scale - MAX_SCALE, Rounding::HalfEven);
+            return Some(Self::new(digits, MAX_SCALE));
+        }
+        Some(Self::new(digits, scale))
+    }
+
+    /// The quotient to `MAX_SCALE` digits, fewer if the digits would not
+    /// fit, rounded half to even. `None` when dividing by zero.
+    pub fn checked_div(&self, other: &Decimal) -> Option<Decimal> {
+        self.checked_div_to(other, MAX_SCALE, Rounding::HalfEven)
+    }
+
+    /// The quotient to `places` digits after the decimal point, fewer if the
+    /// digits would not fit.
+    pub fn checked_div_to(
+        &self,
+        other: &Decimal,
+        places: u32,
+        rounding: Rounding,
+    ) -> Option<Decimal> {
+        if other.is_zero() {
+            return None;
+        }
+        for scale in (0..=places).rev() {
+            // self / other = (a * 10^(scale + other.scale - self.scale) / b) / 10^scale,
+            // with the power moved to the divisor when it is negative.
+            let (numerator, divisor) = match (scale + other.scale).checked_sub(self.scale) {
+                Some(shift) => (
+                    pow10(shift).and_then(|p| self.digits.checked_mul(p)),
+                    Some(other.digits),
+                ),
+                None => (
+                    Some(self.digits),
+                    pow10(self.scale - scale - other.scale)
+                        .and_then(|p| other.digits.checked_mul(p)),
+                ),
+            };
+            let (Some(numerator), Some(divisor)) = (numerator, divisor) else {
+                continue;
+            };
+            let (quotient, remainder) = (numerator / divisor, numerator % divisor);
+            let sign = if (numerator < 0) != (divisor < 0) {
+                -1
+            } else {
+                1
+            };
+            let (remainder, divisor) = (remainder.abs(), divisor.abs());
+            let away = match remainder.cmp(&(divisor - remainder)) {
+                Ordering::Greater => true,
+                Ordering::Less => false,
+                Ordering::Equal => rounding == Rounding::HalfUp || quotient % 2 != 0,
+            };
+            let quotient = if away { quotient + sign } else { quotient };
+            return Some(Self::new(quotient, scale));
+        }
+        None
+    }
+
+    /// This number to the power `exponent`, with products rounded as
+    /// `checked_mul` rounds them and a negative power divided out at the end.
+    pub fn checked_powi(&self, exponent: i64) -> Option<Decimal> {
+        let mut result = Decimal::new(1, 0);
+        let mut base = *self;
+        let mut remaining = exponent.unsigned_abs();
+        while remaining > 0 {
+            if remaining % 2 == 1 {
+                result = result.checked_mul(&base)?;
+            }
+            remaining /= 2;
+            if remaining > 0 {
+                base = base.checked_mul(&base)?;
+            }
+        }
+        if exponent < 0 {
+            Decimal::new(1, 0).checked_div(&result)
+        } else {
+            Some(result)
+        }
+    }
+
+    /// The number as a whole number, if it is one.
+    pub fn to_integer(&self) -> Option<i128> {
+        (self.scale == 0).then_some(self.digits)
+    }
+
+    /// This number with at most `places` digits after the decimal point.
+    pub fn round(&self, places: u32, rounding: Rounding) -> Decimal {
+        if self.scale <= places {
+            return *self;
+        }
+        Self::new(
+            round_digits(self.digits, self.scale - places, rounding),
+            places,
+        )
+    }
+
+    /// The number rounded to exactly `places` digits after the decimal
+    /// point, padding with zeros: `2.5` to two places is `2.50`.
+    pub fn to_fixed(&self, places: u32, rounding: Rounding) -> String {
+        let rounded = self.round(places, rounding);
+        let text = rounded.to_string();
+        let padding = (places - rounded.scale) as usize;
+        match (places, rounded.scale) {
+            (0, _) => text,
+            (_, 0) => format!("{}.{}", text, "0".repeat(padding)),
+            _ => format!("{}{}", text, "0".repeat(padding)),
+        }
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal::new(-self.digits, self.scale)
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.digits < 0 { "-" } else { "" };
+        let digits = self.digits.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, whole, fraction)
+    }
+}
+
+/// `left oper right` in decimal arithmetic, rounded to the places of `mode`.
+/// `None` when the result has no exact form: a fractional power, or more
+/// digits than a decimal holds.
+pub fn operate(
+    oper: &Op,
+    left: &Decimal,
+    right: &Decimal,
+    mode: DecimalMode,
+) -> Result<Option<Decimal>, CalcError> {
+    let result = match oper {
+        Op::Add => left.checked_add(right),
+        Op::Sub => left.checked_sub(right),
+        Op::Mult => left.checked_mul(right),
+        Op::Div if right.is_zero() => {
+            return Err(CalcError::new(ErrorKind::DivideByZero, "Divide by Zero"))
+        }
+        Op::Div => left.checked_div_to(right, mode.places, mode.rounding),
+        Op::Pow => right
+            .to_integer()
+            .and_then(|exponent| i64::try_from(exponent).ok())
+            .filter(|exponent| *exponent >= 0 || !left.is_zero())
+            .and_then(|exponent| left.checked_powi(exponent)),
+    };
+    Ok(result.map(|result| result.round(mode.places, mode.rounding)))
+}
@@ -0,0 +1,112 @@
+use super::*;
+
+fn decimal(text: &str) -> Decimal {
+    Decimal::parse(text).expect("a valid decimal")
+}
+
+#[test]
+fn parse_and_display() {
+    for (text, shown) in [
+        ("12.50", "12.5"),
+        ("-0.001", "-0.001"),
+        ("+7", "7"),
+        (".5", "0.5"),
+        ("1.5e3", "1500"),
+        ("25E-4", "0.0025"),
+        ("0.000", "0"),
+    ] {
+        assert_eq!(decimal(text).to_string(), shown, "{}", text);
+    }
+    for text in ["", "-", "1.2.3", "1e", "12a", "1e99"] {
+        assert_eq!(Decimal::parse(text), None, "{}", text);
+    }
+    assert_eq!(decimal("1.10"), decimal("1.1"));
+    assert_eq!(Decimal::from_f64(0.1), Some(decimal("0.1")));
+    assert_eq!(Decimal::from_f64(f64::NAN), None);
+}
+
+#[test]
+fn arithmetic_is_exact() {
+    let sum = decimal("0.1").checked_add(&decimal("0.2")).unwrap();
+    assert_eq!(sum, decimal("0.3"));
+    let difference = decimal("1").checked_sub(&decimal("0.99")).unwrap();
+    assert_eq!(difference, decimal("0.01"));
+    let product = decimal("19.99").checked_mul(&decimal("3")).unwrap();
+    assert_eq!(product, decimal("59.97"));
+
+    let third = decimal("10").checked_div(&decimal("3")).unwrap();
+    assert_eq!(third.to_string(), "3.33333333333333333333");
+    assert_eq!(third.scale(), MAX_SCALE);
+    let negative = decimal("-2").checked_div(&decimal("3")).unwrap();
+    assert_eq!(negative.to_string(), "-0.66666666666666666667");
+    assert_eq!(decimal("1").checked_div(&Decimal::ZERO), None);
+
+    let huge = decimal("1e37");
+    assert_eq!(huge.checked_mul(&huge), None);
+}
+
+#[test]
+fn rounding() {
+    let tie = decimal("0.125");
+    assert_eq!(tie.round(2, Rounding::HalfEven), decimal("0.12"));
+    assert_eq!(tie.round(2, Rounding::HalfUp), decimal("0.13"));
+    assert_eq!(
+        decimal("-0.125").round(2, Rounding::HalfUp),
+        decimal("-0.13")
+    );
+    assert_eq!(
+        decimal("0.135").round(2, Rounding::HalfEven),
+        decimal("0.14")
+    );
+    assert_eq!(decimal("2.5").round(0, Rounding::HalfEven), decimal("2"));
+    assert_eq!(decimal("1.2").round(5, Rounding::HalfUp), decimal("1.2"));
+
+    assert_eq!(decimal("2.5").to_fixed(2, Rounding::HalfUp), "2.50");
+    assert_eq!(decimal("7").to_fixed(3, Rounding::HalfUp), "7.000");
+    assert_eq!(decimal("1234.5").to_fixed(0, Rounding::HalfUp), "1235");
+    assert_eq!(decimal("0.004").to_fixed(2, Rounding::HalfUp), "0.00");
+}
+
+#[test]
+fn division_and_powers() {
+    let third = decimal("2").checked_div_to(&decimal("3"), 2, Rounding::HalfUp);
+    assert_eq!(third, Some(decimal("0.67")));
+    // The dividend may have more places than the quotient keeps.
+    let eighth = decimal("0.125").checked_div_to(&decimal("1"), 2, Rounding::HalfUp);
+    assert_eq!(eighth, Some(decimal("0.13")));
+    let eighth = decimal("0.125").checked_div_to(&decimal("1"), 2, Rounding::HalfEven);
+    assert_eq!(eighth, Some(decimal("0.12")));
+    let small = decimal("-0.0001").checked_div_to(&decimal("0.5"), 2, Rounding::HalfUp);
+    assert_eq!(small, Some(decimal("0")));
+    let tenths = decimal("1.2345").checked_div_to(&decimal("0.5"), 1, Rounding::HalfUp);
+    assert_eq!(tenths, Some(decimal("2.5")));
+    assert_eq!(decimal("1.1").checked_powi(2), Some(decimal("1.21")));
+    assert_eq!(decimal("2").checked_powi(-2), Some(decimal("0.25")));
+    assert_eq!(decimal("2").checked_powi(200), None);
+    assert_eq!(decimal("42.0").to_integer(), Some(42));
+    assert_eq!(decimal("4.2").to_integer(), None);
+    assert_eq!(-decimal("1.5"), decimal("-1.5"));
+}
+
+#[test]
+fn operators_round_to_the_mode() {
+    let cents = DecimalMode {
+        places: 2,
+        rounding: Rounding::HalfUp,
+    };
+    let sum = operate(&Op::Add, &decimal("0.1"), &decimal("0.2"), cents);
+    assert_eq!(sum, Ok(Some(decimal("0.3"))));
+    let share = operate(&Op::Div, &decimal("10"), &decimal("3"), cents);
+    assert_eq!(share, Ok(Some(decimal("3.33"))));
+    let eighth = operate(&Op::Div, &decimal("0.125"), &decimal("1"), cents);
+    assert_eq!(eighth, Ok(Some(decimal("0.13"))));
+    let root = operate(&Op::Pow, &decimal("2"), &decimal("0.5"), cents);
+    assert_eq!(root, Ok(None));
+    let error = operate(&Op::Div, &decimal("1"), &Decimal::ZERO, cents).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DivideByZero);
+
+    for rounding in [Rounding::HalfEven, Rounding::HalfUp] {
+        assert_eq!(Rounding::from_name(rounding.name()), Some(rounding));
+    }
+    assert_eq!(Rounding::from_name("down"), None);
+}
@@ -0,0 +1,215 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::AST,
+    error::{CalcError, ErrorKind},
+    forms,
+    settings::AngleMode,
+    simplify::simplify,
+    tokens::{Num, Op, Token},
+};
+
+/// The derivative of `tree` with respect to `var`, simplified, with angles
+/// in radians.
+///
+/// Handles sums, products, quotients, powers and the built-in functions of
+/// one argument through the chain rule. Calls that do not involve `var` are
+/// treated as constants.
+pub fn derivative(tree: &AST, var: &str) -> Result<Box<AST>, CalcError> {
+    derivative_in(tree, var, AngleMode::Radians)
+}
+
+/// Like `derivative`, for trigonometric functions measuring angles in `angle`:
+/// in degrees, the derivative of `sin(x)` is `cos(x) * pi / 180`.
+pub fn derivative_in(tree: &AST, var: &str, angle: AngleMode) -> Result<Box<AST>, CalcError> {
+    let result = differentiate(tree, var, angle)?;
+    // Without functions to call, `ln(2)` and the like stay as written.
+    Ok(simplify(&result, true, &HashMap::<String, f64>::new()))
+}
+
+fn differentiate(tree: &AST, var: &str, angle: AngleMode) -> Result<Box<AST>, CalcError> {
+    if !depends_on(tree, var) {
+        return Ok(number(0));
+    }
+    // Long sums and products are differentiated a term at a time, keeping
+    // the derivative of everything to the left of each operator.
+    let (first, links) = tree.chain();
+    if !links.is_empty() {
+        let mut result = differentiate(first, var, angle)?;
+        for (link, oper, v) in links {
+            let u = link.left().ok_or_else(CalcError::invalid_expression)?;
+            result = link_derivative(oper, result, u, v, var, angle)?;
+        }
+        return Ok(result);
+    }
+    let result = match (tree.value(), tree.left(), tree.right()) {
+        (Token::Variable(_), _, _) => number(1),
+        (Token::Operator(oper @ (Op::Add | Op::Sub | Op::Mult | Op::Div)), Some(u), Some(v)) => {
+            let du = differentiate(u, var, angle)?;
+            link_derivative(oper, du, u, v, var, angle)?
+        }
+        (Token::Operator(Op::Pow), Some(u), Some(v)) if !depends_on(v, var) => mul(
+            mul(copy(v), pow(copy(u), sub(copy(v), number(1)))),
+            differentiate(u, var, angle)?,
+        ),
+        (Token::Operator(Op::Pow), Some(u), Some(v)) if !depends_on(u, var) => mul(
+            mul(copy(tree), call("ln", copy(u))),
+            differentiate(v, var, angle)?,
+        ),
+        // d(u^v) = u^v * (v' * ln(u) + v * u' / u)
+        (Token::Operator(Op::Pow), Some(u), Some(v)) => mul(
+            copy(tree),
+            add(
+                mul(differentiate(v, var, angle)?, call("ln", copy(u))),
+                div(mul(copy(v), differentiate(u, var, angle)?), copy(u)),
+            ),
+        ),
+        (Token::Degree, Some(u), _) => AST::new_degrees(differentiate(u, var, angle)?),
+        (Token::Function(name), _, _) => match (name.as_str(), tree.arguments().as_slice()) {
+            // `x(u)` multiplies, as when it is evaluated.
+            (name, [u]) if name == var => {
+                let product = mul(AST::new_leaf(Token::new_variable(name)), copy(u));
+                differentiate(&product, var, angle)?
+            }
+            ("diff", [u, inner]) => {
+                let inner = forms::variable_name("diff", inner)?;
+                let inner_derivative = derivative_in(u, inner, angle)?;
+                differentiate(&inner_derivative, var, angle)?
+            }
+            (name, [u]) => mul(outer(name, u, angle)?, differentiate(u, var, angle)?),
+            (name, _) => return Err(not_differentiable(name)),
+        },
+        // A comparison steps between 0 and 1, so it has no derivative there.
+        (Token::Comparison(_), _, _) => return Err(not_differentiable("a comparison")),
+        _ => return Err(CalcError::invalid_expression()),
+    };
+    Ok(result)
+}
+
+/// The derivative of `u oper v`, given `du`, the derivative of `u`.
+fn link_derivative(
+    oper: &Op,
+    du: Box<AST>,
+    u: &AST,
+    v: &AST,
+    var: &str,
+    angle: AngleMode,
+) -> Result<Box<AST>, CalcError> {
+    let dv = differentiate(v, var, angle)?;
+    let result = match oper {
+        Op::Add | Op::Sub => binary(oper.clone(), du, dv),
+        Op::Mult => add(mul(du, copy(v)), mul(copy(u), dv)),
+        Op::Div => div(
+            sub(mul(du, copy(v)), mul(copy(u), dv)),
+            pow(copy(v), number(2)),
+        ),
+        _ => return Err(CalcError::invalid_expression()),
+    };
+    Ok(result)
+}
+
+/// The derivative of the function `name` evaluated at `u`, the outer factor
+/// of the chain rule.
+fn outer(name: &str, u: &AST, angle: AngleMode) -> Result<Box<AST>, CalcError> {
+    let u = || copy(u);
+    let one_over = |denominator| div(number(1), denominator);
+    // Radians per unit of angle for trigonometric functions, and units per
+    // radian for their inverses.
+    let per_unit = |result| scale(result, angle.to_radians(1.0));
+    let per_radian = |result| scale(result, angle.from_radians(1.0));
+    let result = match name {
+        "sin" => per_unit(call("cos", u())),
+        "cos" => per_unit(negate(call("sin", u()))),
+        "tan" => per_unit(one_over(pow(call("cos", u()), number(2)))),
+        "exp" => call("exp", u()),
+        "ln" => one_over(u()),
+        "log" => one_over(mul(u(), call("ln", number(10)))),
+        "log2" => one_over(mul(u(), call("ln", number(2)))),
+        "sqrt" => one_over(mul(number(2), call("sqrt", u()))),
+        "cbrt" => one_over(mul(number(3), pow(call("cbrt", u()), number(2)))),
+        "asin" => per_radian(one_over(call("sqrt", sub(number(1), pow(u(), number(2)))))),
+        "acos" => per_radian(negate(one_over(call(
+            "sqrt",
+            sub(number(1), pow(u(), number(2))),
+        )))),
+        "atan" => per_radian(one_over(add(number(1), pow(u(), number(2))))),
+        "deg" => AST::new_leaf(Token::Number(Num::Float(1f64.to_degrees()))),
+        "rad" => AST::new_leaf(Token::Number(Num::Float(1f64.to_radians()))),
+        "sinh" => call("cosh", u()),
+        "cosh" => call("sinh", u()),
+        "tanh" => one_over(pow(call("cosh", u()), number(2))),
+        "abs" => call("sign", u()),
+        _ => return Err(not_differentiable(name)),
+    };
+    Ok(result)
+}
+
+fn scale(tree: Box<AST>, factor: f64) -> Box<AST> {
+    if factor == 1.0 {
+        tree
+    } else {
+        mul(tree, AST::new_leaf(Token::Number(Num::Float(factor))))
+    }
+}
+
+fn not_differentiable(name: &str) -> CalcError {
+    CalcError::new(
+        ErrorKind::NotDifferentiable,
+        &format!("Cannot differentiate {}", name),
+    )
+}
+
+/// Whether `var` occurs anywhere in `tree`.
+pub fn depends_on(tree: &AST, var: &str) -> bool {
+    let mut stack = vec![tree];
+    while let Some(node) = stack.pop() {
+        match node.value() {
+            Token::Variable(name) | Token::Function(name) if name == var => return true,
+            _ => stack.extend(node.left().into_iter().chain(node.right())),
+        }
+    }
+    false
+}
+
+fn copy(tree: &AST) -> Box<AST> {
+    Box::new(tree.clone())
+}
+
+fn number(n: i64) -> Box<AST> {
+    AST::new_leaf(Token::Number(Num::Integer(n)))
+}
+
+fn call(name: &str, arg: Box<AST>) -> Box<AST> {
+    AST::new_call(name, vec![arg])
+}
+
+fn binary(oper: Op, left: Box<AST>, right: Box<AST>) -> Box<AST> {
+    AST::new_operator(oper, left, right)
+}
+
+fn add(left: Box<AST>, right: Box<AST>) -> Box<AST> {
+    binary(Op::Add, left, right)
+}
+
+fn sub(left: Box<AST>, right: Box<AST>) -> Box<AST> {
+    binary(Op::Sub, left, right)
+}
+
+fn mul(left: Box<AST>, right: Box<AST>) -> Box<AST> {
+    binary(Op::Mult, left, right)
+}
+
+fn div(left: Box<AST>, right: Box<AST>) -> Box<AST> {
+    binary(Op::Div, left, right)
+}
+
+fn pow(left: Box<AST>, right: Box<AST>) -> Box<AST> {
+    binary(Op::Pow, left, right)
+}
+
+fn negate(operand: Box<AST>) -> Box<AST> {
+    mul(number(-1), operand)
+}
@@ -0,0 +1,114 @@
+use std::f64::consts::PI;
+
+use crate::{
+    calculator::Calculator, environment::Scope, parse_expression, printer::infix, value::Value,
+};
+
+use super::*;
+
+fn diff(expression: &str) -> String {
+    infix(&derivative(&parse_expression(expression).unwrap(), "x").unwrap())
+}
+
+#[test]
+fn polynomials() {
+    assert_eq!(diff("5"), "0");
+    assert_eq!(diff("y"), "0");
+    assert_eq!(diff("x"), "1");
+    assert_eq!(diff("3 * x + y"), "3");
+    assert_eq!(diff("x^3 - 2*x^2 + 7"), "3 * x ^ 2 - 2 * (2 * x)");
+    assert_eq!(diff("x^-1"), "-x ^ -2");
+}
+
+#[test]
+fn products_and_quotients() {
+    assert_eq!(diff("x * y"), "y");
+    assert_eq!(diff("x * x"), "x + x");
+    assert_eq!(diff("1 / x"), "-1 / x ^ 2");
+    assert_eq!(diff("x / (x + 1)"), "(x + 1 - x) / (x + 1) ^ 2");
+}
+
+#[test]
+fn chain_rule() {
+    assert_eq!(diff("x^3 + sin(x)"), "3 * x ^ 2 + cos(x)");
+    assert_eq!(diff("sin(2 * x)"), "cos(2 * x) * 2");
+    assert_eq!(diff("cos(x^2)"), "-sin(x ^ 2) * (2 * x)");
+    assert_eq!(diff("ln(y)"), "0");
+    assert_eq!(diff("exp(x) * 2"), "exp(x) * 2");
+    assert_eq!(diff("sqrt(x)"), "1 / (2 * sqrt(x))");
+    assert_eq!(diff("2^x"), "2 ^ x * ln(2)");
+    assert_eq!(diff("x^x"), "x ^ x * (ln(x) + x / x)");
+}
+
+#[test]
+fn derivatives_match_finite_differences() {
+    let calculator = Calculator::new();
+    let h = 1e-6;
+    for expression in [
+        "x^3 + sin(x)",
+        "x / (1 + x^2)",
+        "tan(x) * exp(-x)",
+        "atan(x) + acos(x / 2) - asin(x / 3)",
+        "log(x) + log2(x) + cbrt(x)",
+        "tanh(x) * cosh(x) - sinh(x) + abs(x - 5)",
+        "x^x",
+    ] {
+        let tree = parse_expression(expression).unwrap();
+        let derivative = derivative(&tree, "x").unwrap();
+        for x in [0.3, 1.0, 1.7] {
+            let at = |tree: &AST, x: f64| {
+                let scope = Scope::new(&calculator, vec![("x".to_owned(), Value::Number(x))]);
+                tree.eval(&scope).unwrap().as_number().unwrap()
+            };
+            let numeric = (at(&tree, x + h) - at(&tree, x - h)) / (2.0 * h);
+            let exact = at(&derivative, x);
+            assert!(
+                (numeric - exact).abs() < 1e-5,
+                "d/dx {} at {}: {} vs {}",
+                expression,
+                x,
+                exact,
+                numeric
+            );
+        }
+    }
+}
+
+#[test]
+fn unsupported_functions() {
+    let error = derivative(&parse_expression("floor(x)").unwrap(), "x").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::NotDifferentiable);
+    assert_eq!(error.message, "Cannot differentiate floor");
+    assert_eq!(diff("floor(y) * x"), "floor(y)");
+
+    let error = derivative(&parse_expression("x > 1").unwrap(), "x").unwrap_err();
+    assert_eq!(error.message, "Cannot differentiate a comparison");
+}
+
+#[test]
+fn angle_units() {
+    let tree = parse_expression("sin(x) + asin(x)").unwrap();
+    let radians = infix(&derivative(&tree, "x").unwrap());
+    assert_eq!(radians, "cos(x) + 1 / sqrt(1 - x ^ 2)");
+    let degrees = derivative_in(&tree, "x", AngleMode::Degrees).unwrap();
+    let at = |x: f64| {
+        let calculator = Calculator::new();
+        let scope = Scope::new(&calculator, vec![("x".to_owned(), Value::Number(x))]);
+        degrees.eval(&scope).unwrap().as_number().unwrap()
+    };
+    // In degrees, sin grows pi/180 times slower and asin 180/pi times faster.
+    let expected = 0.0f64.cos() * PI / 180.0 + 180.0 / PI;
+    assert!((at(0.0) - expected).abs() < 1e-12);
+
+    assert_eq!(diff("30° * x"), "30°");
+}
+
+#[test]
+fn long_sums() {
+    let mut tokens = vec![Token::new_variable("x")];
+    for _ in 1..5000 {
+        tokens.extend([Token::new_op("+"), Token::new_variable("x")]);
+    }
+    let tree = AST::build_tree(&tokens).unwrap();
+    assert_eq!(infix(&derivative(&tree, "x").unwrap()), "5000");
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use crate::{
+    functions::Callable,
+    money::Rates,
+    settings::{AngleMode, DecimalMode},
+    value::Value,
+};
+
+/// Resolves the names an expression refers to while it is evaluated.
+pub trait Environment {
+    fn variable(&self, name: &str) -> Option<Value>;
+
+    fn function(&self, name: &str) -> Option<&dyn Callable>;
+
+    /// The outermost environment, which function bodies are evaluated against.
+    fn globals(&self) -> &dyn Environment;
+
+    /// Number of function calls currently being evaluated.
+    fn depth(&self) -> usize {
+        0
+    }
+
+    /// The unit angles are measured in by trigonometric functions.
+    fn angle_mode(&self) -> AngleMode {
+        AngleMode::Radians
+    }
+
+    /// The exchange rates amounts of money are converted at, if any.
+    fn rates(&self) -> Option<&Rates> {
+        None
+    }
+
+    /// How plain numbers are computed exactly, or `None` for floating point.
+    fn decimal(&self) -> Option<DecimalMode> {
+        None
+    }
+}
+
+impl Environment for HashMap<String, f64> {
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.get(name).map(|x| Value::Number(*x))
+    }
+
+    fn function(&self, _name: &str) -> Option<&dyn Callable> {
+        None
+    }
+
+    fn globals(&self) -> &dyn Environment {
+        self
+    }
+}
+
+/// Local bindings layered over a parent environment.
+pub struct Scope<'a> {
+    parent: &'a dyn Environment,
+    bindings: Vec<(String, Value)>,
+    depth: usize,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(parent: &'a dyn Environment, bindings: Vec<(String, Value)>) -> Self {
+        Scope {
+            parent,
+            bindings,
+            depth: parent.depth(),
+        }
+    }
+
+    /// Sets how many function calls deep this scope is.
+    pub fn nested(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+impl Environment for Scope<'_> {
+    fn variable(&self, name: &str) -> Option<Value> {
+        match self.bindings.iter().find(|(bound, _)| bound == name) {
+            Some((_, value)) => Some(value.clone()),
+            None => self.parent.variable(name),
+        }
+    }
+
+    fn function(&self, name: &str) -> Option<&dyn Callable> {
+        self.parent.function(name)
+    }
+
+    fn globals(&self) -> &dyn Environment {
+        self.parent.globals()
+    }
+
+    fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn angle_mode(&self) -> AngleMode {
+        self.parent.angle_mode()
+    }
+
+    fn rates(&self) -> Option<&Rates> {
+        self.parent.rates()
+    }
+
+    fn decimal(&self) -> Option<DecimalMode> {
+        self.parent.decimal()
+    }
+}
@@ -0,0 +1,99 @@
+use std::{error::Error, fmt::Display};
+
+/// Start (inclusive) and end (exclusive) character offsets into the input.
+pub type Span = (usize, usize);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ErrorKind {
+    NonAscii,
+    InvalidCharacter,
+    EmptyExpression,
+    InvalidExpression,
+    MissingParen,
+    ExtraParen,
+    UnknownVariable,
+    UnknownFunction,
+    ArityMismatch,
+    RecursionLimit,
+    InvalidName,
+    ReadOnly,
+    TypeMismatch,
+    Domain,
+    UnknownCommand,
+    InvalidArgument,
+    NotDifferentiable,
+    DivideByZero,
+    NoConvergence,
+    UnknownUnit,
+    DimensionMismatch,
+    UnknownCurrency,
+    CurrencyMismatch,
+    IndexOutOfRange,
+    LengthMismatch,
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::NonAscii => "NonAscii",
+            ErrorKind::InvalidCharacter => "InvalidCharacter",
+            ErrorKind::EmptyExpression => "EmptyExpression",
+            ErrorKind::InvalidExpression => "InvalidExpression",
+            ErrorKind::MissingParen => "MissingParen",
+            ErrorKind::ExtraParen => "ExtraParen",
+            ErrorKind::UnknownVariable => "UnknownVariable",
+            ErrorKind::UnknownFunction => "UnknownFunction",
+            ErrorKind::ArityMismatch => "ArityMismatch",
+            ErrorKind::RecursionLimit => "RecursionLimit",
+            ErrorKind::InvalidName => "InvalidName",
+            ErrorKind::ReadOnly => "ReadOnly",
+            ErrorKind::TypeMismatch => "TypeMismatch",
+            ErrorKind::Domain => "Domain",
+            ErrorKind::UnknownCommand => "UnknownCommand",
+            ErrorKind::InvalidArgument => "InvalidArgument",
+            ErrorKind::NotDifferentiable => "NotDifferentiable",
+            ErrorKind::DivideByZero => "DivideByZero",
+            ErrorKind::NoConvergence => "NoConvergence",
+            ErrorKind::UnknownUnit => "UnknownUnit",
+            ErrorKind::DimensionMismatch => "DimensionMismatch",
+            ErrorKind::UnknownCurrency => "UnknownCurrency",
+            ErrorKind::CurrencyMismatch => "CurrencyMismatch",
+            ErrorKind::IndexOutOfRange => "IndexOutOfRange",
+            ErrorKind::LengthMismatch => "LengthMismatch",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CalcError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl CalcError {
+    pub fn new(kind: ErrorKind, message: &str) -> Self {
+        CalcError {
+            kind,
+            message: message.to_owned(),
+            span: None,
+        }
+    }
+
+    pub fn invalid_expression() -> Self {
+        Self::new(ErrorKind::InvalidExpression, "Invalid Expression")
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+}
+
+impl Display for CalcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CalcError {}
@@ -0,0 +1,384 @@
+//! Time value of money, with the arguments, defaults and sign conventions
+//! of the spreadsheet functions of the same names.
+//!
+//! Money paid out is negative and money received positive, so borrowing
+//! 200000 at 5% a year over 30 years is `pmt(0.05/12, 360, 200000)`, a
+//! payment of -1073.64 a month. Where a function takes a `type`, 0 means
+//! payments at the end of each period and 1 at the start.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    error::{CalcError, ErrorKind},
+    functions::{Arity, ValueFunction},
+    list,
+    solve::{self, Function},
+    value::Value,
+};
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// The rate `rate` and `irr` start from when not given a guess.
+const GUESS: f64 = 0.1;
+
+/// The rates searched for a sign change when Newton's method strays from
+/// the guess, as it does when the guess is far from the answer.
+const SEARCH: (f64, f64) = (-0.99, 10.0);
+
+/// The value today of `nper` payments of `pmt` and a final `fv`.
+pub fn pv(rate: f64, nper: f64, pmt: f64, fv: f64, due: bool) -> f64 {
+    if rate == 0.0 {
+        return -(fv + pmt * nper);
+    }
+    let growth = (1.0 + rate).powf(nper);
+    -(fv + pmt * annuity(rate, due) * (growth - 1.0) / rate) / growth
+}
+
+/// What `pv` grows to after `nper` payments of `pmt`.
+pub fn fv(rate: f64, nper: f64, pmt: f64, pv: f64, due: bool) -> f64 {
+    if rate == 0.0 {
+        return -(pv + pmt * nper);
+    }
+    let growth = (1.0 + rate).powf(nper);
+    -(pv * growth + pmt * annuity(rate, due) * (growth - 1.0) / rate)
+}
+
+/// The payment that takes `pv` to `fv` in `nper` periods.
+pub fn pmt(rate: f64, nper: f64, pv: f64, fv: f64, due: bool) -> Result<f64, String> {
+    if nper == 0.0 {
+        return Err("the number of periods must not be zero".to_owned());
+    }
+    if rate == 0.0 {
+        return Ok(-(pv + fv) / nper);
+    }
+    let growth = (1.0 + rate).powf(nper);
+    Ok(-rate * (fv + pv * growth) / (annuity(rate, due) * (growth - 1.0)))
+}
+
+/// How many payments of `pmt` take `pv` to `fv`.
+pub fn nper(rate: f64, pmt: f64, pv: f64, fv: f64, due: bool) -> Result<f64, String> {
+    if rate == 0.0 {
+        return match pmt {
+            0.0 => Err("the payment must not be zero".to_owned()),
+            _ => Ok(-(pv + fv) / pmt),
+        };
+    }
+    let payment = pmt * annuity(rate, due);
+    let ratio = (payment - fv * rate) / (payment + pv * rate);
+    if ratio > 0.0 {
+        Ok(ratio.ln() / rate.ln_1p())
+    } else {
+        Err("the payments never reach the future value".to_owned())
+    }
+}
+
+/// The rate per period at which `nper` payments of `pmt` take `pv` to `fv`.
+pub fn rate(
+    nper: f64,
+    pmt: f64,
+    pv: f64,
+    fv: f64,
+    due: bool,
+    guess: f64,
+) -> Result<f64, CalcError> {
+    let balance = |rate: f64| -> Result<f64, CalcError> {
+        if rate == 0.0 {
+            return Ok(pv + pmt * nper + fv);
+        }
+        let growth = (1.0 + rate).powf(nper);
+        Ok(pv * growth + pmt * annuity(rate, due) * (growth - 1.0) / rate + fv)
+    };
+    let slope = |rate: f64| -> Result<f64, CalcError> {
+        let h = 1e-6 * (1.0 + rate.abs());
+        Ok((balance(rate + h)? - balance(rate - h)?) / (2.0 * h))
+    };
+    find_rate("rate", &balance, &slope, guess)
+}
+
+/// The value today of `flows` at the end of periods 1, 2, ...
+pub fn npv(rate: f64, flows: &[f64]) -> f64 {
+    let times = (1..=flows.len()).map(|period| period as f64);
+    discounted(rate, flows.iter().copied().zip(times))
+}
+
+/// The rate at which `flows`, the first now and one each period after it,
+/// are worth nothing today.
+pub fn irr(flows: &[f64]) -> Result<f64, CalcError> {
+    let times = (0..flows.len()).map(|period| period as f64);
+    internal_rate("irr", &flows.iter().copied().zip(times).collect::<Vec<_>>())
+}
+
+/// Like `npv`, for `(amount, day)` flows on any days, discounted to the
+/// day of the first flow over years of 365 days.
+pub fn xnpv(rate: f64, flows: &[(f64, f64)]) -> f64 {
+    discounted(rate, in_years(flows))
+}
+
+/// Like `irr`, for `(amount, day)` flows on any days.
+pub fn xirr(flows: &[(f64, f64)]) -> Result<f64, CalcError> {
+    internal_rate("xirr", &in_years(flows).collect::<Vec<_>>())
+}
+
+/// One row of an amortization schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installment {
+    pub period: usize,
+    pub payment: f64,
+    pub interest: f64,
+    pub principal: f64,
+    /// What is left to repay after this installment.
+    pub balance: f64,
+}
+
+/// The schedule that repays `principal` at `rate` per period with `periods`
+/// equal payments at the end of each period. The last payment absorbs the
+/// rounding of the others, so the balance ends at exactly zero. Like a
+/// range, a schedule has at most `list::MAX_LENGTH` rows.
+pub fn amortize(principal: f64, rate: f64, periods: f64) -> Result<Vec<Installment>, CalcError> {
+    let domain =
+        |message: &str| CalcError::new(ErrorKind::Domain, &format!("amortize: {}", message));
+    if periods < 1.0 || periods.fract() != 0.0 {
+        return Err(domain(
+            "the number of periods must be a positive whole number",
+        ));
+    }
+    if periods > list::MAX_LENGTH {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!(
+                "amortize: a schedule is limited to {} periods",
+                list::MAX_LENGTH
+            ),
+        ));
+    }
+    if rate <= -1.0 {
+        return Err(domain("the rate must be greater than -1"));
+    }
+    let payment = -pmt(rate, periods, principal, 0.0, false).map_err(|e| domain(&e))?;
+    let periods = periods as usize;
+    let mut balance = principal;
+    let mut schedule = Vec::with_capacity(periods);
+    for period in 1..=periods {
+        let interest = balance * rate;
+        let principal = if period == periods {
+            balance
+        } else {
+            payment - interest
+        };
+        balance -= principal;
+        schedule.push(Installment {
+            period,
+            payment: principal + interest,
+            interest,
+            principal,
+            balance,
+        });
+    }
+    Ok(schedule)
+}
+
+/// `1 + rate` for payments at the start of each period, which earn
+/// interest for one period more.
+fn annuity(rate: f64, due: bool) -> f64 {
+    if due {
+        1.0 + rate
+    } else {
+        1.0
+    }
+}
+
+fn discounted(rate: f64, flows: impl Iterator<Item = (f64, f64)>) -> f64 {
+    flows
+        .map(|(amount, time)| amount / (1.0 + rate).powf(time))
+        .sum()
+}
+
+fn in_years(flows: &[(f64, f64)]) -> impl Iterator<Item = (f64, f64)> + '_ {
+    let start = flows.first().map_or(0.0, |(_, day)| *day);
+    flows
+        .iter()
+        .map(move |(amount, day)| (*amount, (day - start) / 365.0))
+}
+
+fn internal_rate(name: &str, flows: &[(f64, f64)]) -> Result<f64, CalcError> {
+    if !(flows.iter().any(|(amount, _)| *amount > 0.0)
+        && flows.iter().any(|(amount, _)| *amount < 0.0))
+    {
+        return Err(CalcError::new(
+            ErrorKind::Domain,
+            &format!("{}: the cash flows need both a payment and a receipt", name),
+        ));
+    }
+    let value = |rate: f64| Ok(discounted(rate, flows.iter().copied()));
+    let slope = |rate: f64| {
+        Ok(flows
+            .iter()
+            .map(|(amount, time)| -time * amount / (1.0 + rate).powf(time + 1.0))
+            .sum())
+    };
+    find_rate(name, &value, &slope, GUESS)
+}
+
+/// The root of `f` nearest `guess` among rates above -1.
+fn find_rate(name: &str, f: &Function, slope: &Function, guess: f64) -> Result<f64, CalcError> {
+    match solve::newton(f, slope, guess) {
+        Ok(rate) if rate > -1.0 && rate.is_finite() => return Ok(rate),
+        _ => {}
+    }
+    let roots = solve::roots_between(f, slope, SEARCH.0, SEARCH.1)?;
+    roots
+        .into_iter()
+        .min_by(|a, b| (a - guess).abs().total_cmp(&(b - guess).abs()))
+        .ok_or_else(|| {
+            CalcError::new(
+                ErrorKind::NoConvergence,
+                &format!("{}: no rate fits the cash flows", name),
+            )
+        })
+}
+
+/// The number `args[index]`, or `default` when it was left out.
+fn optional(args: &[f64], index: usize, default: f64) -> f64 {
+    args.get(index).copied().unwrap_or(default)
+}
+
+fn number(name: &str, value: &Value) -> Result<f64, CalcError> {
+    value.as_number().ok_or_else(|| {
+        CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("{} expects numbers but got {}", name, value.described()),
+        )
+    })
+}
+
+/// The day of a date argument, counted from 1970-01-01.
+fn day(name: &str, value: &Value) -> Result<f64, CalcError> {
+    match value {
+        Value::Date(date) => Ok((date.seconds() / SECONDS_PER_DAY).floor()),
+        value => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("{} expects a date but got {}", name, value.described()),
+        )),
+    }
+}
+
+/// `(amount, day)` pairs from arguments that alternate amounts and dates,
+/// as in `xirr(-1000, 2026-01-01, 1100, 2027-01-01)`, or from a list of
+/// amounts and a list of their dates.
+fn dated_flows(name: &str, args: &[Value]) -> Result<Vec<(f64, f64)>, CalcError> {
+    if let [Value::List(amounts), Value::List(dates)] = args {
+        if amounts.len() != dates.len() {
+            return Err(CalcError::new(
+                ErrorKind::LengthMismatch,
+                &format!("{} expects as many dates as amounts", name),
+            ));
+        }
+        let pairs = amounts.iter().zip(dates);
+        return pairs
+            .map(|(amount, date)| Ok((number(name, amount)?, day(name, date)?)))
+            .collect();
+    }
+    if !args.len().is_multiple_of(2) {
+        return Err(CalcError::new(
+            ErrorKind::ArityMismatch,
+            &format!("{} expects each amount to be followed by its date", name),
+        ));
+    }
+    args.chunks(2)
+        .map(|pair| Ok((number(name, &pair[0])?, day(name, &pair[1])?)))
+        .collect()
+}
+
+/// A function of numbers whose errors are about its arguments. One of any
+/// number of arguments also takes them as a list, as in `irr([-100, 110])`.
+fn numeric<F>(name: &'static str, arity: Arity, function: F) -> ValueFunction
+where
+    F: Fn(&[f64]) -> Result<f64, CalcError> + 'static,
+{
+    ValueFunction::new(name, arity, move |args| {
+        let args = match arity {
+            Arity::AtLeast(_) => list::spread(args),
+            _ => args.to_vec(),
+        };
+        let numbers = args
+            .iter()
+            .map(|arg| number(name, arg))
+            .collect::<Result<Vec<f64>, CalcError>>()?;
+        function(&numbers).map(Value::Number)
+    })
+}
+
+fn domain(name: &str) -> impl Fn(String) -> CalcError + '_ {
+    move |message| CalcError::new(ErrorKind::Domain, &format!("{}: {}", name, message))
+}
+
+pub fn functions() -> Vec<ValueFunction> {
+    vec![
+        numeric("pv", Arity::Between(3, 5), |args| {
+            let [rate, nper, pmt] = [args[0], args[1], args[2]];
+            Ok(pv(
+                rate,
+                nper,
+                pmt,
+                optional(args, 3, 0.0),
+                optional(args, 4, 0.0) != 0.0,
+            ))
+        }),
+        numeric("fv", Arity::Between(3, 5), |args| {
+            let [rate, nper, pmt] = [args[0], args[1], args[2]];
+            Ok(fv(
+                rate,
+                nper,
+                pmt,
+                optional(args, 3, 0.0),
+                optional(args, 4, 0.0) != 0.0,
+            ))
+        }),
+        numeric("pmt", Arity::Between(3, 5), |args| {
+            let [rate, nper, pv] = [args[0], args[1], args[2]];
+            pmt(
+                rate,
+                nper,
+                pv,
+                optional(args, 3, 0.0),
+                optional(args, 4, 0.0) != 0.0,
+            )
+            .map_err(domain("pmt"))
+        }),
+        numeric("nper", Arity::Between(3, 5), |args| {
+            let [rate, pmt, pv] = [args[0], args[1], args[2]];
+            nper(
+                rate,
+                pmt,
+                pv,
+                optional(args, 3, 0.0),
+                optional(args, 4, 0.0) != 0.0,
+            )
+            .map_err(domain("nper"))
+        }),
+        numeric("rate", Arity::Between(3, 6), |args| {
+            let [nper, pmt, pv] = [args[0], args[1], args[2]];
+            let due = optional(args, 4, 0.0) != 0.0;
+            rate(
+                nper,
+                pmt,
+                pv,
+                optional(args, 3, 0.0),
+                due,
+                optional(args, 5, GUESS),
+            )
+        }),
+        numeric("npv", Arity::AtLeast(2), |args| {
+            Ok(npv(args[0], &args[1..]))
+        }),
+        numeric("irr", Arity::AtLeast(1), irr),
+        ValueFunction::new("xnpv", Arity::AtLeast(2), |args| {
+            let rate = number("xnpv", &args[0])?;
+            Ok(Value::Number(xnpv(rate, &dated_flows("xnpv", &args[1..])?)))
+        }),
+        ValueFunction::new("xirr", Arity::AtLeast(2), |args| {
+            xirr(&dated_flows("xirr", args)?).map(Value::Number)
+        }),
+    ]
+}
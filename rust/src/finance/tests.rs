@@ -0,0 +1,146 @@
+use crate::calculator::Calculator;
+
+use super::*;
+
+/// Asserts `x` agrees with a spreadsheet's result to the digits it shows.
+fn assert_close(x: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (x - expected).abs() <= tolerance,
+        "{} is not {} within {}",
+        x,
+        expected,
+        tolerance
+    );
+}
+
+fn eval(expression: &str) -> Result<f64, CalcError> {
+    Calculator::new()
+        .eval(expression)
+        .map(|value| value.as_number().unwrap())
+}
+
+#[test]
+fn annuities() {
+    assert_close(
+        pv(0.08 / 12.0, 240.0, 500.0, 0.0, false),
+        -59777.145851,
+        1e-6,
+    );
+    assert_close(
+        fv(0.06 / 12.0, 10.0, -200.0, -500.0, true),
+        2581.403374,
+        1e-6,
+    );
+    assert_close(
+        pmt(0.05 / 12.0, 360.0, 200000.0, 0.0, false).unwrap(),
+        -1073.643246,
+        1e-6,
+    );
+    assert_close(
+        nper(0.01, -100.0, -1000.0, 10000.0, true).unwrap(),
+        59.673866,
+        1e-6,
+    );
+    assert_close(
+        rate(48.0, -200.0, 8000.0, 0.0, false, GUESS).unwrap(),
+        0.007701472,
+        1e-9,
+    );
+
+    assert_eq!(pv(0.0, 10.0, -100.0, 0.0, false), 1000.0);
+    assert_eq!(fv(0.0, 10.0, -100.0, -50.0, false), 1050.0);
+    assert_eq!(pmt(0.0, 4.0, 1000.0, 0.0, false), Ok(-250.0));
+    assert_eq!(nper(0.0, -250.0, 1000.0, 0.0, false), Ok(4.0));
+    assert!(pmt(0.05, 0.0, 1000.0, 0.0, false).is_err());
+    assert!(nper(0.1, -10.0, 1000.0, 0.0, false).is_err());
+}
+
+#[test]
+fn cash_flows() {
+    assert_close(
+        npv(0.1, &[-10000.0, 3000.0, 4200.0, 6800.0]),
+        1188.443412,
+        1e-6,
+    );
+    let flows = [-70000.0, 12000.0, 15000.0, 18000.0, 21000.0];
+    assert_close(irr(&flows).unwrap(), -0.021244848, 1e-9);
+    assert_close(
+        irr(&[&flows[..], &[26000.0]].concat()).unwrap(),
+        0.086630948,
+        1e-9,
+    );
+
+    let error = irr(&[100.0, 200.0]).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+}
+
+#[test]
+fn dated_cash_flows() {
+    let expression = |name: &str, rate: &str| {
+        format!(
+            "{}({}-10000, 2008-01-01, 2750, 2008-03-01, 4250, 2008-10-30, \
+             3250, 2009-02-15, 2750, 2009-04-01)",
+            name, rate
+        )
+    };
+    assert_close(
+        eval(&expression("xnpv", "0.09, ")).unwrap(),
+        2086.647602,
+        1e-6,
+    );
+    assert_close(eval(&expression("xirr", "")).unwrap(), 0.373362535, 1e-8);
+
+    let error = eval("xirr(-100, 2026-01-01, 110)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+    let error = eval("xirr(-100, 2026-01-01, 110, 365)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::TypeMismatch);
+}
+
+#[test]
+fn optional_arguments() {
+    assert_close(
+        eval("pmt(0.05/12, 360, 200000)").unwrap(),
+        -1073.643246,
+        1e-6,
+    );
+    assert_close(
+        eval("fv(0.06/12, 10, -200, -500, 1)").unwrap(),
+        2581.403374,
+        1e-6,
+    );
+    assert_close(
+        eval("rate(48, -200, 8000, 0, 0, 0.5)").unwrap(),
+        0.007701472,
+        1e-9,
+    );
+    assert_close(
+        eval("npv(0.1, -10000, 3000, 4200, 6800)").unwrap(),
+        1188.443412,
+        1e-6,
+    );
+
+    let error = eval("pmt(0.05, 10)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+    let error = eval("pmt(0.05, 0, 1000)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+}
+
+#[test]
+fn amortization() {
+    let schedule = amortize(1000.0, 0.01, 3.0).unwrap();
+    assert_eq!(schedule.len(), 3);
+    assert_close(schedule[0].payment, 340.022111, 1e-6);
+    assert_close(schedule[0].interest, 10.0, 1e-12);
+    assert_close(schedule[0].principal, 330.022111, 1e-6);
+    assert_eq!(schedule[2].balance, 0.0);
+    let repaid: f64 = schedule.iter().map(|row| row.principal).sum();
+    assert_close(repaid, 1000.0, 1e-9);
+
+    let interest_free = amortize(1200.0, 0.0, 12.0).unwrap();
+    assert!(interest_free.iter().all(|row| row.payment == 100.0));
+    assert!(amortize(1000.0, 0.01, 2.5).is_err());
+    assert!(amortize(1000.0, 0.01, 0.0).is_err());
+    assert_eq!(amortize(1000.0, 0.01, 1e6).unwrap().len(), 1_000_000);
+    let error = amortize(1000.0, 0.01, 1e18).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
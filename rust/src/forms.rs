@@ -0,0 +1,331 @@
+//! Special forms: calls whose arguments are expressions rather than values,
+//! such as `diff(x^2, x)`, `solve(x^2 = 2, x)`, `sum(k^2, k, 1, 10)` and
+//! `map(v, x -> x^2)`. They are resolved before any function of the same
+//! name and receive their arguments unevaluated.
+
+use crate::{
+    ast::AST,
+    derivative::{depends_on, derivative_in},
+    environment::{Environment, Scope},
+    error::{CalcError, ErrorKind},
+    functions::Arity,
+    integrate, matrix, solve, stats,
+    tokens::Token,
+    value::Value,
+};
+
+type FormFn = fn(&[&AST], &dyn Environment) -> Result<Value, CalcError>;
+
+/// The special forms, as (name, arity, implementation).
+const FORMS: &[(&str, Arity, FormFn)] = &[
+    ("diff", Arity::Between(2, 3), diff),
+    ("solve", Arity::Between(2, 4), solve),
+    ("integrate", Arity::Exact(4), integrate),
+    ("sum", Arity::AtLeast(1), sum),
+    ("prod", Arity::Exact(4), prod),
+    ("map", Arity::Exact(2), map),
+    ("filter", Arity::Exact(2), filter),
+    ("reduce", Arity::Between(2, 3), reduce),
+];
+
+/// How many terms `sum` and `prod` may evaluate.
+const MAX_TERMS: f64 = 1e7;
+
+pub fn lookup(name: &str) -> Option<(Arity, FormFn)> {
+    FORMS
+        .iter()
+        .find(|(form, _, _)| *form == name)
+        .map(|(_, arity, form)| (*arity, *form))
+}
+
+/// Whether `name` is reserved for a special form.
+pub fn is_form(name: &str) -> bool {
+    lookup(name).is_some()
+}
+
+/// The name in a variable argument, e.g. the `x` in `diff(x^2, x)`.
+pub fn variable_name<'a>(form: &str, arg: &'a AST) -> Result<&'a str, CalcError> {
+    match arg.value() {
+        Token::Variable(name) => Ok(name),
+        _ => Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!("{} expects a variable name, not {}", form, arg),
+        )),
+    }
+}
+
+/// Evaluates `tree` with `name` bound to `value` on top of `env`.
+pub fn eval_with(
+    tree: &AST,
+    name: &str,
+    value: Value,
+    env: &dyn Environment,
+) -> Result<Value, CalcError> {
+    tree.eval(&Scope::new(env, vec![(name.to_owned(), value)]))
+}
+
+/// `diff(expr, x)` is the derivative of `expr` with respect to `x` at the
+/// current value of `x`; `diff(expr, x, a)` evaluates it at `x = a`.
+fn diff(args: &[&AST], env: &dyn Environment) -> Result<Value, CalcError> {
+    let var = variable_name("diff", args[1])?;
+    let derivative = derivative_in(args[0], var, env.angle_mode())?;
+    match args.get(2) {
+        Some(point) => eval_with(&derivative, var, point.eval(env)?, env),
+        None => derivative.eval(env),
+    }
+}
+
+/// `solve(equation, x)` is a root of `equation` found by Newton's method from
+/// the current value of `x`; `solve(equation, x, a, b)` is the list of every
+/// root between `a` and `b`, in increasing order. `solve(A, b)`, where `A` is
+/// a matrix, solves the linear system `A x = b` instead.
+fn solve(args: &[&AST], env: &dyn Environment) -> Result<Value, CalcError> {
+    if let [system, rhs] = args {
+        if system.value() != &Token::Equals {
+            match system.eval(env) {
+                Ok(system @ Value::List(_)) => return matrix::solve(&system, &rhs.eval(env)?),
+                // A name second is the unknown of an expression equal to zero.
+                Err(error) if !matches!(rhs.value(), Token::Variable(_)) => return Err(error),
+                _ => {}
+            }
+        }
+    }
+    let var = variable_name("solve", args[1])?;
+    let interval = match args[2..] {
+        [] => None,
+        [a, b] => Some((number("solve", a, env)?, number("solve", b, env)?)),
+        _ => {
+            return Err(CalcError::new(
+                ErrorKind::InvalidArgument,
+                "solve needs both ends of the interval, as in solve(x^2 = 2, x, 0, 5)",
+            ))
+        }
+    };
+    let roots = solve::solve(args[0], var, interval, env)?;
+    match interval {
+        Some(_) => Ok(Value::List(roots.into_iter().map(Value::Number).collect())),
+        None => Ok(Value::Number(roots[0])),
+    }
+}
+
+/// `integrate(expr, x, a, b)` is the integral of `expr` for `x` from `a` to
+/// `b`. Either bound may be infinite.
+fn integrate(args: &[&AST], env: &dyn Environment) -> Result<Value, CalcError> {
+    let var = variable_name("integrate", args[1])?;
+    let (a, b) = (
+        number("integrate", args[2], env)?,
+        number("integrate", args[3], env)?,
+    );
+    let integrand = |x: f64| number_at("integrate", args[0], var, x, env);
+    Ok(Value::Number(integrate::integrate(&integrand, a, b)?.value))
+}
+
+/// `sum(expr, k, a, b)` adds up `expr` for each whole `k` from `a` to `b`.
+/// Any other arguments, as in `sum(2, 3, 5)`, are added up themselves: four
+/// of them are a series only when the second is a name that has no value or
+/// that the first uses, so `sum(a, b, 1, 3)` adds up `a` and `b` when they
+/// are defined. In decimal mode decimals and integers add up exactly.
+fn sum(args: &[&AST], env: &dyn Environment) -> Result<Value, CalcError> {
+    // Compensated summation, so many small terms do not lose precision.
+    let mut total = stats::Sum::default();
+    match args {
+        [term, index, _, _] if is_index(term, index, env) => {
+            series("sum", args, env, |term| total.add(term))?
+        }
+        _ => {
+            let values = args
+                .iter()
+                .map(|arg| arg.eval(env))
+                .collect::<Result<Vec<Value>, CalcError>>()?;
+            if let Some(exact) = env
+                .decimal()
+                .and_then(|mode| stats::exact_sum(&values, mode))
+            {
+                return Ok(Value::Decimal(Box::new(exact)));
+            }
+            stats::numbers("sum", &values)?
+                .into_iter()
+                .for_each(|x| total.add(x));
+        }
+    }
+    Ok(Value::Number(total.total()))
+}
+
+/// Whether `index` is the index of a series of `term`: a name with no value,
+/// or one that `term` uses.
+fn is_index(term: &AST, index: &AST, env: &dyn Environment) -> bool {
+    match index.value() {
+        Token::Variable(name) => env.variable(name).is_none() || depends_on(term, name),
+        _ => false,
+    }
+}
+
+/// `prod(expr, k, a, b)` multiplies `expr` for each whole `k` from `a` to `b`.
+fn prod(args: &[&AST], env: &dyn Environment) -> Result<Value, CalcError> {
+    let mut product = 1.0;
+    series("prod", args, env, |term| product *= term)?;
+    Ok(Value::Number(product))
+}
+
+/// `map(list, f)` is the list of `f` of each element, where `f` is a lambda
+/// such as `x -> x^2` or the name of a function such as `sqrt`.
+fn map(args: &[&AST], env: &dyn Environment) -> Result<Value, CalcError> {
+    let items = list_argument("map", args[0], env)?;
+    let mapped = items
+        .into_iter()
+        .map(|item| apply("map", args[1], vec![item], env));
+    mapped.collect::<Result<_, _>>().map(Value::List)
+}
+
+/// `filter(list, f)` keeps the elements for which `f` is not zero.
+fn filter(args: &[&AST], env: &dyn Environment) -> Result<Value, CalcError> {
+    let mut kept = vec![];
+    for item in list_argument("filter", args[0], env)? {
+        let keep = apply("filter", args[1], vec![item.clone()], env)?;
+        match keep.as_number() {
+            Some(x) if x != 0.0 => kept.push(item),
+            Some(_) => {}
+            None => {
+                return Err(CalcError::new(
+                    ErrorKind::TypeMismatch,
+                    &format!(
+                        "filter expects its function to give numbers but got {}",
+                        keep.described()
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(Value::List(kept))
+}
+
+/// `reduce(list, f)` combines the elements with `f` of two arguments from
+/// the first on, as in `reduce(v, (acc, x) -> acc + x)`; `reduce(list, f,
+/// initial)` starts from `initial` instead.
+fn reduce(args: &[&AST], env: &dyn Environment) -> Result<Value, CalcError> {
+    let mut items = list_argument("reduce", args[0], env)?.into_iter();
+    let mut total = match args.get(2) {
+        Some(initial) => initial.eval(env)?,
+        None => items.next().ok_or_else(|| {
+            CalcError::new(
+                ErrorKind::InvalidArgument,
+                "reduce needs an initial value for an empty list",
+            )
+        })?,
+    };
+    for item in items {
+        total = apply("reduce", args[1], vec![total, item], env)?;
+    }
+    Ok(total)
+}
+
+fn list_argument(form: &str, arg: &AST, env: &dyn Environment) -> Result<Vec<Value>, CalcError> {
+    match arg.eval(env)? {
+        Value::List(items) => Ok(items),
+        value => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("{} expects a list but got {}", form, value.described()),
+        )),
+    }
+}
+
+/// Calls the function argument of `form`: a lambda, or a function by name.
+fn apply(
+    form: &str,
+    function: &AST,
+    args: Vec<Value>,
+    env: &dyn Environment,
+) -> Result<Value, CalcError> {
+    match (function.value(), function.left()) {
+        (Token::Lambda(params), Some(body)) => {
+            if params.len() != args.len() {
+                return Err(CalcError::new(
+                    ErrorKind::ArityMismatch,
+                    &format!(
+                        "{} calls its lambda with {} argument(s) but it takes {}",
+                        form,
+                        args.len(),
+                        params.len()
+                    ),
+                ));
+            }
+            let bindings = params.iter().cloned().zip(args).collect();
+            body.eval(&Scope::new(env, bindings))
+        }
+        (Token::Variable(name), _) => match env.function(name) {
+            Some(callee) => {
+                callee.arity().check(args.len())?;
+                callee.call(&args, env)
+            }
+            None => Err(CalcError::new(
+                ErrorKind::UnknownFunction,
+                &format!("Unknown Function: {}", name),
+            )),
+        },
+        _ => Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!(
+                "{} expects a lambda such as x -> x^2 or the name of a function",
+                form
+            ),
+        )),
+    }
+}
+
+/// Evaluates the body of `sum` or `prod` with the index bound to each whole
+/// number in its range, which is empty when the upper bound is the smaller.
+fn series(
+    form: &str,
+    args: &[&AST],
+    env: &dyn Environment,
+    mut add: impl FnMut(f64),
+) -> Result<(), CalcError> {
+    let var = variable_name(form, args[1])?;
+    let (first, last) = (number(form, args[2], env)?, number(form, args[3], env)?);
+    for bound in [first, last] {
+        if !bound.is_finite() || bound != bound.trunc() {
+            return Err(CalcError::new(
+                ErrorKind::InvalidArgument,
+                &format!("{} expects whole numbers as bounds, not {}", form, bound),
+            ));
+        }
+    }
+    if last - first >= MAX_TERMS {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!("{} is limited to {} terms", form, MAX_TERMS),
+        ));
+    }
+    let mut k = first;
+    while k <= last {
+        add(number_at(form, args[0], var, k, env)?);
+        k += 1.0;
+    }
+    Ok(())
+}
+
+/// Evaluates `tree` with `var` bound to `x` as the number `form` expects.
+pub fn number_at(
+    form: &str,
+    tree: &AST,
+    var: &str,
+    x: f64,
+    env: &dyn Environment,
+) -> Result<f64, CalcError> {
+    number(
+        form,
+        tree,
+        &Scope::new(env, vec![(var.to_owned(), x.into())]),
+    )
+}
+
+/// Evaluates a numeric argument such as an interval bound.
+fn number(form: &str, arg: &AST, env: &dyn Environment) -> Result<f64, CalcError> {
+    let value = arg.eval(env)?;
+    value.as_number().ok_or_else(|| {
+        CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("{} expects a number but got {}", form, value.described()),
+        )
+    })
+}
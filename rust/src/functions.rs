@@ -0,0 +1,356 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Debug;
+
+use crate::{
+    ast::AST,
+    environment::{Environment, Scope},
+    error::{CalcError, ErrorKind},
+    list,
+    settings::AngleMode,
+    value::Value,
+};
+
+/// How deep user-defined functions may call each other before evaluation gives up.
+const MAX_CALL_DEPTH: usize = 256;
+
+/// How many arguments a function accepts.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    /// From the first to the second number of arguments, inclusive.
+    Between(usize, usize),
+}
+
+impl Arity {
+    /// Checks a call with `count` arguments against the arity.
+    pub fn check(&self, count: usize) -> Result<(), CalcError> {
+        let (accepted, expected) = match self {
+            Arity::Exact(n) => (count == *n, format!("{}", n)),
+            Arity::AtLeast(n) => (count >= *n, format!("at least {}", n)),
+            Arity::Between(min, max) => (
+                (*min..=*max).contains(&count),
+                format!("{} to {}", min, max),
+            ),
+        };
+        if accepted {
+            Ok(())
+        } else {
+            Err(CalcError::new(
+                ErrorKind::ArityMismatch,
+                &format!("Expected {} argument(s) but got {}", expected, count),
+            ))
+        }
+    }
+}
+
+/// Anything that can be called from an expression as `name(args...)`.
+///
+/// The evaluator checks the number of arguments against `arity` before `call`
+/// is invoked.
+pub trait Callable: Debug {
+    fn arity(&self) -> Arity;
+
+    fn call(&self, args: &[Value], env: &dyn Environment) -> Result<Value, CalcError>;
+
+    /// Whether the result depends only on the arguments and the settings, so
+    /// that a call with constant arguments can be folded when simplifying.
+    fn is_pure(&self) -> bool {
+        false
+    }
+}
+
+type NativeFn = dyn Fn(&[f64]) -> Result<f64, String>;
+
+/// Which side of a native function deals in angles, which are converted
+/// between the calculator's angle mode and the radians the function uses.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Angles {
+    Neither,
+    /// The arguments are angles, as for `sin`.
+    Arguments,
+    /// The result is an angle, as for `asin`.
+    Result,
+}
+
+/// A function implemented in Rust that takes and returns numbers.
+pub struct NativeFunction {
+    name: String,
+    arity: Arity,
+    angles: Angles,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: Arity, function: F) -> Self
+    where
+        F: Fn(&[f64]) -> f64 + 'static,
+    {
+        Self::fallible(name, arity, move |args| Ok(function(args)))
+    }
+
+    /// A native function that can reject its arguments, e.g. `sqrt` of a
+    /// negative number. The message becomes a `Domain` error.
+    pub fn fallible<F>(name: &str, arity: Arity, function: F) -> Self
+    where
+        F: Fn(&[f64]) -> Result<f64, String> + 'static,
+    {
+        NativeFunction {
+            name: name.to_owned(),
+            arity,
+            angles: Angles::Neither,
+            function: Box::new(function),
+        }
+    }
+
+    /// Marks the arguments or the result as angles in the active angle mode.
+    pub fn with_angles(mut self, angles: Angles) -> Self {
+        self.angles = angles;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .field("angles", &self.angles)
+            .finish()
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    /// A function of any number of arguments, such as `max`, also takes
+    /// their values as a list.
+    fn call(&self, args: &[Value], env: &dyn Environment) -> Result<Value, CalcError> {
+        let mode = env.angle_mode();
+        let args = match self.arity {
+            Arity::AtLeast(_) => list::spread(args),
+            _ => args.to_vec(),
+        };
+        let mut numbers = args
+            .iter()
+            .map(|arg| {
+                arg.as_number().ok_or_else(|| {
+                    CalcError::new(
+                        ErrorKind::TypeMismatch,
+                        &format!("{} expects numbers but got {}", self.name, arg.described()),
+                    )
+                })
+            })
+            .collect::<Result<Vec<f64>, CalcError>>()?;
+        if self.angles == Angles::Arguments {
+            for number in numbers.iter_mut() {
+                *number = mode.to_radians(*number);
+            }
+        }
+        let result = (self.function)(&numbers)
+            .map_err(|e| CalcError::new(ErrorKind::Domain, &format!("{}: {}", self.name, e)))?;
+        Ok(Value::Number(match (self.angles, mode) {
+            (Angles::Neither, _) | (_, AngleMode::Radians) => result,
+            (Angles::Arguments, _) => tidy(result, 0.5),
+            (Angles::Result, _) => tidy(mode.from_radians(result), 1.0),
+        }))
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+/// Snaps `x` to the nearest multiple of `step` when it is off by no more than
+/// converting between angle units loses, so that `sin(30°)` is 0.5 and
+/// `asin(0.5)` in degrees is 30.
+fn tidy(x: f64, step: f64) -> f64 {
+    let nearest = (x / step).round() * step;
+    if (x - nearest).abs() <= 4.0 * f64::EPSILON * step.max(x.abs()) {
+        nearest
+    } else {
+        x
+    }
+}
+
+type ValueFn = dyn Fn(&[Value], &dyn Environment) -> Result<Value, CalcError>;
+
+/// A function implemented in Rust that works on values other than numbers,
+/// e.g. `weekday(date)`.
+pub struct ValueFunction {
+    name: String,
+    arity: Arity,
+    function: Box<ValueFn>,
+}
+
+impl ValueFunction {
+    pub fn new<F>(name: &str, arity: Arity, function: F) -> Self
+    where
+        F: Fn(&[Value]) -> Result<Value, CalcError> + 'static,
+    {
+        Self::with_env(name, arity, move |args, _| function(args))
+    }
+
+    /// Like `new`, for a function that depends on the settings, such as a
+    /// total kept exact in decimal mode.
+    pub fn with_env<F>(name: &str, arity: Arity, function: F) -> Self
+    where
+        F: Fn(&[Value], &dyn Environment) -> Result<Value, CalcError> + 'static,
+    {
+        ValueFunction {
+            name: name.to_owned(),
+            arity,
+            function: Box::new(function),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Debug for ValueFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValueFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+impl Callable for ValueFunction {
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn call(&self, args: &[Value], env: &dyn Environment) -> Result<Value, CalcError> {
+        (self.function)(args, env)
+    }
+
+    /// Only `today` and `now`, which take no arguments, read anything else.
+    fn is_pure(&self) -> bool {
+        self.arity != Arity::Exact(0)
+    }
+}
+
+/// A function defined by an expression over its parameters, e.g. `f(x, y) = x * y`.
+#[derive(Debug)]
+pub struct UserFunction {
+    params: Vec<String>,
+    body: Box<AST>,
+}
+
+impl UserFunction {
+    pub fn new(params: Vec<String>, body: Box<AST>) -> Self {
+        UserFunction { params, body }
+    }
+
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    pub fn body(&self) -> &AST {
+        &self.body
+    }
+}
+
+impl Callable for UserFunction {
+    fn arity(&self) -> Arity {
+        Arity::Exact(self.params.len())
+    }
+
+    /// Evaluates the body with `args` bound to the parameters. The body only
+    /// sees its parameters and the globals of `env`, never the caller's locals.
+    fn call(&self, args: &[Value], env: &dyn Environment) -> Result<Value, CalcError> {
+        if env.depth() >= MAX_CALL_DEPTH {
+            return Err(CalcError::new(
+                ErrorKind::RecursionLimit,
+                "Too many nested function calls",
+            ));
+        }
+
+        let bindings = self.params.iter().cloned().zip(args.iter().cloned());
+        let scope = Scope::new(env.globals(), bindings.collect()).nested(env.depth() + 1);
+        self.body.eval(&scope)
+    }
+}
+
+fn non_negative(name: &'static str, f: fn(f64) -> f64) -> NativeFunction {
+    NativeFunction::fallible(name, Arity::Exact(1), move |args| {
+        if args[0] < 0.0 {
+            Err("argument must not be negative".to_owned())
+        } else {
+            Ok(f(args[0]))
+        }
+    })
+}
+
+fn positive(name: &'static str, f: fn(f64) -> f64) -> NativeFunction {
+    NativeFunction::fallible(name, Arity::Exact(1), move |args| {
+        if args[0] <= 0.0 {
+            Err("argument must be positive".to_owned())
+        } else {
+            Ok(f(args[0]))
+        }
+    })
+}
+
+fn unit_interval(name: &'static str, f: fn(f64) -> f64) -> NativeFunction {
+    NativeFunction::fallible(name, Arity::Exact(1), move |args| {
+        if !(-1.0..=1.0).contains(&args[0]) {
+            Err("argument must be between -1 and 1".to_owned())
+        } else {
+            Ok(f(args[0]))
+        }
+    })
+}
+
+fn unary(name: &'static str, f: fn(f64) -> f64) -> NativeFunction {
+    NativeFunction::new(name, Arity::Exact(1), move |args| f(args[0]))
+}
+
+/// The functions every calculator starts with.
+pub fn builtins() -> Vec<NativeFunction> {
+    vec![
+        non_negative("sqrt", f64::sqrt),
+        unary("cbrt", f64::cbrt),
+        unary("abs", f64::abs),
+        unary("sign", |x| if x == 0.0 { 0.0 } else { x.signum() }),
+        unary("floor", f64::floor),
+        unary("ceil", f64::ceil),
+        unary("round", f64::round),
+        unary("trunc", f64::trunc),
+        unary("exp", f64::exp),
+        positive("ln", f64::ln),
+        positive("log", f64::log10),
+        positive("log2", f64::log2),
+        unary("sin", f64::sin).with_angles(Angles::Arguments),
+        unary("cos", f64::cos).with_angles(Angles::Arguments),
+        unary("tan", f64::tan).with_angles(Angles::Arguments),
+        unit_interval("asin", f64::asin).with_angles(Angles::Result),
+        unit_interval("acos", f64::acos).with_angles(Angles::Result),
+        unary("atan", f64::atan).with_angles(Angles::Result),
+        NativeFunction::new("atan2", Arity::Exact(2), |args| args[0].atan2(args[1]))
+            .with_angles(Angles::Result),
+        unary("deg", f64::to_degrees),
+        unary("rad", f64::to_radians),
+        unary("sinh", f64::sinh),
+        unary("cosh", f64::cosh),
+        unary("tanh", f64::tanh),
+        NativeFunction::new("hypot", Arity::Exact(2), |args| args[0].hypot(args[1])),
+        NativeFunction::new("min", Arity::AtLeast(1), |args| {
+            args.iter().copied().fold(f64::INFINITY, f64::min)
+        }),
+        NativeFunction::new("max", Arity::AtLeast(1), |args| {
+            args.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+        }),
+    ]
+}
@@ -0,0 +1,101 @@
+use std::{collections::HashMap, f64::consts::PI};
+
+use super::*;
+
+fn call(name: &str, args: &[f64]) -> Result<Value, CalcError> {
+    let function = builtins()
+        .into_iter()
+        .find(|function| function.name() == name)
+        .expect("should be a builtin");
+    function.arity().check(args.len())?;
+    let args: Vec<Value> = args.iter().map(|x| Value::Number(*x)).collect();
+    function.call(&args, &HashMap::<String, f64>::new())
+}
+
+#[test]
+fn arity_checks() {
+    assert_eq!(Arity::Exact(2).check(2), Ok(()));
+    assert_eq!(Arity::AtLeast(1).check(4), Ok(()));
+
+    let error = Arity::Exact(2).check(1).expect_err("too few arguments");
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+    assert_eq!(error.message, "Expected 2 argument(s) but got 1");
+
+    assert_eq!(Arity::Between(2, 3).check(3), Ok(()));
+    let error = Arity::Between(2, 3)
+        .check(4)
+        .expect_err("too many arguments");
+    assert_eq!(error.message, "Expected 2 to 3 argument(s) but got 4");
+
+    let error = Arity::AtLeast(1).check(0).expect_err("no arguments");
+    assert_eq!(error.message, "Expected at least 1 argument(s) but got 0");
+}
+
+#[test]
+fn builtin_math() {
+    assert_eq!(call("sqrt", &[9.0]), Ok(Value::Number(3.0)));
+    assert_eq!(call("abs", &[-2.5]), Ok(Value::Number(2.5)));
+    assert_eq!(call("log", &[1000.0]), Ok(Value::Number(3.0)));
+    assert_eq!(call("sign", &[0.0]), Ok(Value::Number(0.0)));
+    assert_eq!(call("hypot", &[3.0, 4.0]), Ok(Value::Number(5.0)));
+    assert_eq!(call("min", &[4.0, -1.0, 2.0]), Ok(Value::Number(-1.0)));
+    assert_eq!(call("max", &[4.0]), Ok(Value::Number(4.0)));
+}
+
+#[test]
+fn domain_errors() {
+    let error = call("sqrt", &[-1.0]).expect_err("negative square root");
+    assert_eq!(error.kind, ErrorKind::Domain);
+    assert_eq!(error.message, "sqrt: argument must not be negative");
+
+    assert_eq!(call("ln", &[0.0]).unwrap_err().kind, ErrorKind::Domain);
+    assert_eq!(call("asin", &[2.0]).unwrap_err().kind, ErrorKind::Domain);
+}
+
+#[test]
+fn native_functions_are_named_in_errors() {
+    let clamp = NativeFunction::fallible("clamp", Arity::Exact(1), |args| {
+        if args[0].is_nan() {
+            Err("argument is not a number".to_owned())
+        } else {
+            Ok(args[0].clamp(0.0, 1.0))
+        }
+    });
+    let env = HashMap::<String, f64>::new();
+    assert_eq!(clamp.name(), "clamp");
+    assert_eq!(
+        clamp.call(&[Value::Number(3.0)], &env),
+        Ok(Value::Number(1.0))
+    );
+    let error = clamp
+        .call(&[Value::Number(f64::NAN)], &env)
+        .expect_err("NaN is rejected");
+    assert_eq!(error.message, "clamp: argument is not a number");
+}
+
+#[test]
+fn angle_modes() {
+    let mut calculator = crate::calculator::Calculator::new();
+    let mut eval = |expression: &str| calculator.eval(expression).unwrap().as_number().unwrap();
+    assert_eq!(eval("deg(pi)"), 180.0);
+    assert_eq!(eval("rad(90) * 2"), PI);
+    assert_eq!(eval("cos(180°)"), -1.0);
+    assert_eq!(eval("asin(1)"), PI / 2.0);
+
+    calculator.settings_mut().angle = AngleMode::Degrees;
+    let mut eval = |expression: &str| calculator.eval(expression).unwrap().as_number().unwrap();
+    assert_eq!(eval("sin(30)"), 0.5);
+    assert_eq!(eval("cos(90)"), 0.0);
+    assert_eq!(eval("tan(45)"), 1.0);
+    assert_eq!(eval("acos(0.5)"), 60.0);
+    assert_eq!(eval("atan2(1, 1)"), 45.0);
+    assert_eq!(eval("sin(30°) + 30°"), 30.5);
+    assert_eq!(eval("sinh(1)"), 1f64.sinh());
+    assert_eq!(eval("deg(pi)"), 180.0);
+
+    calculator.settings_mut().angle = AngleMode::Gradians;
+    let mut eval = |expression: &str| calculator.eval(expression).unwrap().as_number().unwrap();
+    assert_eq!(eval("sin(100)"), 1.0);
+    assert_eq!(eval("90°"), 100.0);
+    assert_eq!(eval("asin(1)"), 100.0);
+}
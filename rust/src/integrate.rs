@@ -0,0 +1,161 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    error::{CalcError, ErrorKind},
+    format_value,
+};
+
+/// A function of the integration variable.
+pub type Integrand<'a> = dyn Fn(f64) -> Result<f64, CalcError> + 'a;
+
+/// Integration stops once the estimated error is below this, or below
+/// `RELATIVE_TOLERANCE` times the integral, whichever is larger.
+const ABSOLUTE_TOLERANCE: f64 = 1e-12;
+
+const RELATIVE_TOLERANCE: f64 = 1e-10;
+
+/// How many pieces the interval may be cut into before giving up.
+const MAX_PIECES: usize = 2000;
+
+/// Nodes of the 15-point Kronrod rule on [-1, 1], from the outside in. The
+/// odd ones are the nodes of the embedded 7-point Gauss rule.
+const KRONROD_NODES: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.0,
+];
+
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+/// A definite integral and an estimate of its absolute error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Integral {
+    pub value: f64,
+    pub error: f64,
+}
+
+/// The integral of `f` from `a` to `b` by adaptive Gauss–Kronrod quadrature:
+/// the piece with the largest error estimate is halved until the estimates
+/// add up to less than the tolerance.
+///
+/// Either bound may be infinite, in which case the interval is mapped onto a
+/// finite one first. Bounds in decreasing order negate the integral.
+pub fn integrate(f: &Integrand, a: f64, b: f64) -> Result<Integral, CalcError> {
+    if a.is_nan() || b.is_nan() {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            "integrate needs numbers as bounds",
+        ));
+    }
+    if a == b {
+        return Ok(Integral {
+            value: 0.0,
+            error: 0.0,
+        });
+    }
+    if a > b {
+        let integral = integrate(f, b, a)?;
+        return Ok(Integral {
+            value: -integral.value,
+            ..integral
+        });
+    }
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => adaptive(f, a, b),
+        // x = a + t / (1 - t) for t in [0, 1)
+        (true, false) => adaptive(
+            &|t| Ok(f(a + t / (1.0 - t))? / ((1.0 - t) * (1.0 - t))),
+            0.0,
+            1.0,
+        ),
+        // x = b - (1 - t) / t for t in (0, 1]
+        (false, true) => adaptive(&|t| Ok(f(b - (1.0 - t) / t)? / (t * t)), 0.0, 1.0),
+        // x = t / (1 - t^2) for t in (-1, 1)
+        (false, false) => adaptive(
+            &|t| {
+                let s = 1.0 - t * t;
+                Ok(f(t / s)? * (1.0 + t * t) / (s * s))
+            },
+            -1.0,
+            1.0,
+        ),
+    }
+}
+
+fn adaptive(f: &Integrand, a: f64, b: f64) -> Result<Integral, CalcError> {
+    let mut pieces = vec![(a, b, kronrod(f, a, b)?)];
+    loop {
+        let value: f64 = pieces.iter().map(|(_, _, piece)| piece.value).sum();
+        let error: f64 = pieces.iter().map(|(_, _, piece)| piece.error).sum();
+        if !value.is_finite() || error.is_nan() {
+            return Err(CalcError::new(
+                ErrorKind::NoConvergence,
+                "integrate found the integral to be infinite or undefined",
+            ));
+        }
+        if error <= ABSOLUTE_TOLERANCE.max(RELATIVE_TOLERANCE * value.abs()) {
+            return Ok(Integral { value, error });
+        }
+
+        let worst = (0..pieces.len())
+            .max_by(|i, j| pieces[*i].2.error.total_cmp(&pieces[*j].2.error))
+            .expect("there is always a piece");
+        let (start, end, _) = pieces[worst];
+        let middle = 0.5 * (start + end);
+        if pieces.len() >= MAX_PIECES || middle <= start || middle >= end {
+            return Err(CalcError::new(
+                ErrorKind::NoConvergence,
+                &format!(
+                    "integrate did not converge; the estimated error is {}",
+                    format_value(error)
+                ),
+            ));
+        }
+        pieces.swap_remove(worst);
+        pieces.push((start, middle, kronrod(f, start, middle)?));
+        pieces.push((middle, end, kronrod(f, middle, end)?));
+    }
+}
+
+/// The 15-point Kronrod estimate over [a, b], with its difference from the
+/// 7-point Gauss estimate as the error.
+fn kronrod(f: &Integrand, a: f64, b: f64) -> Result<Integral, CalcError> {
+    let center = 0.5 * (a + b);
+    let half = 0.5 * (b - a);
+    let middle = f(center)?;
+    let mut kronrod = middle * KRONROD_WEIGHTS[7];
+    let mut gauss = middle * GAUSS_WEIGHTS[3];
+    for (i, node) in KRONROD_NODES[..7].iter().enumerate() {
+        let pair = f(center - half * node)? + f(center + half * node)?;
+        kronrod += KRONROD_WEIGHTS[i] * pair;
+        if i % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[i / 2] * pair;
+        }
+    }
+    Ok(Integral {
+        value: kronrod * half,
+        error: ((kronrod - gauss) * half).abs(),
+    })
+}
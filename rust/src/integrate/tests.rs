@@ -0,0 +1,90 @@
+use std::f64::consts::PI;
+
+use crate::calculator::Calculator;
+
+use super::*;
+
+fn close(integral: Integral, expected: f64) -> bool {
+    (integral.value - expected).abs() <= 1e-9 * (1.0 + expected.abs())
+}
+
+#[test]
+fn polynomials_are_exact() {
+    let integral = integrate(&|x| Ok(x * x * x - 2.0 * x), 0.0, 2.0).unwrap();
+    assert!((integral.value - 0.0).abs() < 1e-15);
+    assert!(integral.error < 1e-15);
+    let integral = integrate(&|x| Ok(x * x), 3.0, 0.0).unwrap();
+    assert!((integral.value + 9.0).abs() < 1e-13);
+    assert_eq!(integrate(&|x| Ok(x), 1.0, 1.0).unwrap().value, 0.0);
+}
+
+#[test]
+fn error_estimates_bound_the_error() {
+    for (f, a, b, expected) in [
+        (f64::sin as fn(f64) -> f64, 0.0, PI, 2.0),
+        (f64::exp, -1.0, 1.0, 1f64.exp() - (-1f64).exp()),
+        (|x: f64| 1.0 / x.sqrt(), 0.0, 1.0, 2.0),
+        (
+            |x: f64| (10.0 * x).sin().abs(),
+            0.0,
+            3.0,
+            (19.0 + 30f64.cos()) / 10.0,
+        ),
+    ] {
+        let integral = integrate(&|x| Ok(f(x)), a, b).unwrap();
+        let actual = (integral.value - expected).abs();
+        assert!(actual <= integral.error.max(1e-9), "{:?}", integral);
+    }
+}
+
+#[test]
+fn infinite_bounds() {
+    let gaussian = |x: f64| Ok((-x * x).exp());
+    let integral = integrate(&gaussian, f64::NEG_INFINITY, f64::INFINITY).unwrap();
+    assert!(close(integral, PI.sqrt()));
+    let integral = integrate(&gaussian, 0.0, f64::INFINITY).unwrap();
+    assert!(close(integral, PI.sqrt() / 2.0));
+    let integral = integrate(&|x| Ok(x.exp()), f64::NEG_INFINITY, 0.0).unwrap();
+    assert!(close(integral, 1.0));
+}
+
+#[test]
+fn divergent_integrals_are_errors() {
+    let error = integrate(&|x| Ok(1.0 / x), 0.0, 1.0).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::NoConvergence);
+    let error = integrate(&|x| Ok(x), f64::NAN, 1.0).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn series_and_integrals_in_expressions() {
+    let mut calculator = Calculator::new();
+    let mut eval = |expression: &str| calculator.eval(expression).unwrap().as_number().unwrap();
+    assert_eq!(eval("sum(k^2, k, 1, 10)"), 385.0);
+    assert_eq!(eval("prod(k, k, 1, 5)"), 120.0);
+    assert_eq!(eval("sum(k, k, 5, 1)"), 0.0);
+    assert_eq!(eval("prod(k, k, 5, 1)"), 1.0);
+    assert_eq!(eval("sum(sum(i * j, j, 1, 3), i, 1, 2)"), 18.0);
+    assert!((eval("integrate(x^2, x, 0, 3)") - 9.0).abs() < 1e-12);
+    assert!((eval("integrate(integrate(x * y, y, 0, 1), x, 0, 2)") - 1.0).abs() < 1e-12);
+
+    // The index is only bound inside the body.
+    calculator.set_var("k", 100.0).unwrap();
+    assert_eq!(
+        calculator.eval("sum(k, k, 1, 3) + k").unwrap().as_number(),
+        Some(106.0)
+    );
+
+    let error = calculator.eval("sum(k, k, 1, 2.5)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    assert_eq!(
+        error.message,
+        "sum expects whole numbers as bounds, not 2.5"
+    );
+    let error = calculator.eval("prod(k, 2, 1, 3)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = calculator.eval("sum(k, k, 1, 100000000)").unwrap_err();
+    assert_eq!(error.message, "sum is limited to 10000000 terms");
+    let error = calculator.eval("integrate(x, x, 0)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+}
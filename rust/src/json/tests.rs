@@ -0,0 +1,38 @@
+use super::*;
+
+#[test]
+fn scalars() {
+    assert_eq!(Json::Null.to_string(), "null");
+    assert_eq!(Json::Bool(true).to_string(), "true");
+    assert_eq!(Json::Number(12.0).to_string(), "12");
+    assert_eq!(Json::Number(-0.25).to_string(), "-0.25");
+    assert_eq!(Json::Number(f64::INFINITY).to_string(), "\"inf\"");
+    assert_eq!(Json::Number(f64::NAN).to_string(), "\"NaN\"");
+    assert_eq!(
+        Json::Integer(354224848179261915075).to_string(),
+        "354224848179261915075"
+    );
+}
+
+#[test]
+fn string_escapes() {
+    let s = Json::string("say \"hi\"\n\\\t\u{1}");
+    assert_eq!(s.to_string(), r#""say \"hi\"\n\\\t\u0001""#);
+}
+
+#[test]
+fn nested() {
+    let value = Json::object(vec![
+        ("input", Json::string("x0 + 1")),
+        (
+            "span",
+            Json::Array(vec![Json::Number(0.0), Json::Number(2.0)]),
+        ),
+        ("error", Json::object(vec![("kind", Json::Null)])),
+        ("empty", Json::Array(vec![])),
+    ]);
+    assert_eq!(
+        value.to_string(),
+        r#"{"input": "x0 + 1", "span": [0, 2], "error": {"kind": null}, "empty": []}"#
+    );
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests;
 
-use crate::tokens::Token;
+use crate::{error::Span, tokens::Token, units};
 use std::{error::Error, fmt::Display};
 
 #[derive(Debug, PartialEq, Eq)]
@@ -48,11 +48,37 @@ enum State {
     FloatStart,
     OpenParen,
     CloseParen,
+    OpenBracket,
+    CloseBracket,
     Minus,
     Plus,
     Multiply,
     Divide,
+    Power,
+    Comma,
+    Equals,
+    /// `<`, `>` or `!`, which may be followed by `=` as in `<=`. A lone `!`
+    /// is not a token.
+    Less,
+    Greater,
+    Bang,
+    /// A comparison of two characters, as in `<=` or `==`.
+    Comparison,
+    Degree,
     Float,
+    /// Letters straight after a number, which scale it if they are a
+    /// prefix as in `4.7k` and are a name otherwise, as in `5km`.
+    Suffix,
+    /// A number followed by `-`, which starts a date as in `2026-10-18` if
+    /// the digits that follow fit one and is a subtraction otherwise.
+    DateMonth,
+    DateDay,
+    /// A number followed by `:`, a time of day as in `14:30`.
+    Clock,
+    /// `..`, or a number followed by it as in the `1..` of `1..10`, of which
+    /// only the number is taken.
+    Range,
+    Arrow,
     Eol,
     Whitespace,
     Variable,
@@ -74,7 +100,7 @@ pub struct TokenParser {
 
 impl TokenParser {
     pub fn new(expression: &str) -> Result<TokenParser, NotAsciiError> {
-        if !expression.is_ascii() {
+        if !expression.chars().all(Self::is_supported) {
             Err(NotAsciiError)
         } else {
             Ok(TokenParser {
@@ -86,7 +112,14 @@ impl TokenParser {
     }
 
     pub fn get_tokens(&mut self) -> Result<Vec<Token>, InvalidTokenError> {
-        let mut token_vec: Vec<Token> = vec![];
+        let tokens = self.get_spanned_tokens()?;
+        Ok(tokens.into_iter().map(|(token, _)| token).collect())
+    }
+
+    /// Like `get_tokens`, but pairs each token with the span of input it was read from.
+    pub fn get_spanned_tokens(&mut self) -> Result<Vec<(Token, Span)>, InvalidTokenError> {
+        let mut token_vec: Vec<(Token, Span)> = vec![];
+        let mut start = self.position;
         while let Some(next) = self.next_token() {
             #[cfg(test)]
             println!("resolved token: {:?}", next);
@@ -97,9 +130,10 @@ impl TokenParser {
                         value: s,
                     })?
                 } else {
-                    token_vec.push(next.clone());
+                    token_vec.push((next.clone(), (start, self.position)));
                 }
             }
+            start = self.position;
         }
         Ok(token_vec)
     }
@@ -111,14 +145,26 @@ impl TokenParser {
         let mut exp = self.expression.clone();
         exp.push('\n');
 
-        for next_char in exp[start..].chars() {
+        // Positions count characters, which differ from bytes after a `°`.
+        for next_char in exp.chars().skip(start) {
             let next_state = Self::transition(&self.current_state, &next_char);
             match next_state {
                 None => {
                     #[cfg(test)]
                     println!("transition failed, resolving");
-                    let potential_token =
-                        Self::token_from_state(&self.current_state, &exp[start..self.position]);
+                    let value: String = exp
+                        .chars()
+                        .skip(start)
+                        .take(self.position - start)
+                        .collect();
+                    let potential_token = match self.current_state {
+                        State::Suffix => self.scaled_number(start, &value),
+                        State::DateMonth | State::DateDay | State::Clock => {
+                            self.date(start, &value)
+                        }
+                        State::Range => self.range(start, &value),
+                        _ => Self::token_from_state(&self.current_state, &value),
+                    };
                     resolved_token = if let Token::InvalidToken(_) = potential_token {
                         Some(Token::InvalidToken(next_char.to_string()))
                     } else {
@@ -137,6 +183,60 @@ impl TokenParser {
         resolved_token
     }
 
+    /// The number a prefix scales, as in `4.7k`. When the letters are not a
+    /// prefix, or name a unit as the `m` of `300m` does, only the digits are
+    /// taken and the letters lexed again as a name.
+    fn scaled_number(&mut self, start: usize, value: &str) -> Token {
+        let digits = value
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .count();
+        let (number, suffix) = value.split_at(digits);
+        let scaled = if units::is_unit(suffix) {
+            None
+        } else {
+            Token::new_scaled(number, suffix)
+        };
+        scaled.unwrap_or_else(|| {
+            self.position = start + digits;
+            Token::new_number(number)
+        })
+    }
+
+    /// A date as in `2026-10-18` or a time as in `14:30:05`. Anything else
+    /// takes only the leading number, so that `10-3` is a subtraction.
+    fn date(&mut self, start: usize, value: &str) -> Token {
+        let lengths = |separator: char| -> Vec<usize> {
+            value.split(separator).map(|part| part.len()).collect()
+        };
+        let all_digits = |separator: char| {
+            value
+                .split(separator)
+                .all(|part| part.chars().all(|c| c.is_ascii_digit()))
+        };
+        let is_date = all_digits('-') && lengths('-') == [4, 2, 2];
+        let is_clock =
+            all_digits(':') && matches!(lengths(':').as_slice(), [1 | 2, 2] | [1 | 2, 2, 2]);
+        if is_date || is_clock {
+            return Token::Date(value.to_owned());
+        }
+        let digits = value.chars().take_while(|c| c.is_ascii_digit()).count();
+        self.position = start + digits;
+        Token::new_number(&value[..digits])
+    }
+
+    /// The `..` of a range, or the number before it as in `1..10`.
+    fn range(&mut self, start: usize, value: &str) -> Token {
+        match value.strip_suffix("..") {
+            Some("") => Token::Range,
+            Some(number) => {
+                self.position = start + number.len();
+                Token::new_number(number)
+            }
+            None => Token::InvalidToken(value.to_owned()),
+        }
+    }
+
     fn token_from_state(state: &State, value: &str) -> Token {
         #[cfg(test)]
         println!("state: {:?}, value: {}", state, value);
@@ -147,12 +247,23 @@ impl TokenParser {
             State::Variable => Token::new_variable(value),
             State::Integer => Token::new_number(value),
             State::Float => Token::new_number(value),
+            State::Suffix => Token::new_number(value),
+            State::DateMonth | State::DateDay | State::Clock => Token::Date(value.to_owned()),
             State::OpenParen => Token::new_paren(value),
             State::CloseParen => Token::new_paren(value),
+            State::OpenBracket | State::CloseBracket => Token::new_bracket(value),
+            State::Range => Token::Range,
+            State::Arrow => Token::Arrow,
             State::Minus => Token::new_op(value),
             State::Plus => Token::new_op(value),
             State::Multiply => Token::new_op(value),
             State::Divide => Token::new_op(value),
+            State::Power => Token::new_op(value),
+            State::Comma => Token::Comma,
+            State::Equals => Token::Equals,
+            State::Less | State::Greater | State::Comparison => Token::new_comparison(value),
+            State::Bang => Token::InvalidToken(value.to_owned()),
+            State::Degree => Token::Degree,
             State::Whitespace => Token::Whitespace,
         }
     }
@@ -164,10 +275,19 @@ impl TokenParser {
             (State::Initial, CharType::Other('.')) => Some(State::FloatStart),
             (State::Initial, CharType::Other('(')) => Some(State::OpenParen),
             (State::Initial, CharType::Other(')')) => Some(State::CloseParen),
+            (State::Initial, CharType::Other('[')) => Some(State::OpenBracket),
+            (State::Initial, CharType::Other(']')) => Some(State::CloseBracket),
             (State::Initial, CharType::Other('-')) => Some(State::Minus),
             (State::Initial, CharType::Other('+')) => Some(State::Plus),
             (State::Initial, CharType::Other('*')) => Some(State::Multiply),
             (State::Initial, CharType::Other('/')) => Some(State::Divide),
+            (State::Initial, CharType::Other('^')) => Some(State::Power),
+            (State::Initial, CharType::Other(',')) => Some(State::Comma),
+            (State::Initial, CharType::Other('=')) => Some(State::Equals),
+            (State::Initial, CharType::Other('<')) => Some(State::Less),
+            (State::Initial, CharType::Other('>')) => Some(State::Greater),
+            (State::Initial, CharType::Other('!')) => Some(State::Bang),
+            (State::Initial, CharType::Other('°')) => Some(State::Degree),
             (State::Initial, CharType::Other('\n')) => Some(State::Eol),
             (State::Initial, CharType::Whitespace) => Some(State::Whitespace),
             (State::Initial, CharType::Letter) => Some(State::Variable),
@@ -175,10 +295,30 @@ impl TokenParser {
             //
             (State::Integer, CharType::Digit) => Some(State::Integer),
             (State::Integer, CharType::Other('.')) => Some(State::FloatStart),
+            (State::Integer, CharType::Letter) => Some(State::Suffix),
+            (State::Integer, CharType::Other('-')) => Some(State::DateMonth),
+            (State::Integer, CharType::Other(':')) => Some(State::Clock),
+            //
+            (State::DateMonth, CharType::Digit) => Some(State::DateMonth),
+            (State::DateMonth, CharType::Other('-')) => Some(State::DateDay),
+            (State::DateDay, CharType::Digit) => Some(State::DateDay),
+            (State::Clock, CharType::Digit | CharType::Other(':')) => Some(State::Clock),
             //
             (State::FloatStart, CharType::Digit) => Some(State::Float),
+            (State::FloatStart, CharType::Other('.')) => Some(State::Range),
+            //
+            (State::Minus, CharType::Other('>')) => Some(State::Arrow),
+            //
+            (State::Less | State::Greater | State::Equals | State::Bang, CharType::Other('=')) => {
+                Some(State::Comparison)
+            }
             //
             (State::Float, CharType::Digit) => Some(State::Float),
+            (State::Float, CharType::Letter) => Some(State::Suffix),
+            //
+            (State::Suffix, CharType::Letter | CharType::Digit | CharType::Other('_')) => {
+                Some(State::Suffix)
+            }
             //
             (State::Whitespace, CharType::Whitespace) => Some(State::Whitespace),
             //
@@ -190,7 +330,7 @@ impl TokenParser {
     }
 
     fn get_char_type(c: char) -> CharType {
-        if c.is_ascii_alphabetic() {
+        if c.is_ascii_alphabetic() || c == 'µ' {
             CharType::Letter
         } else if c.is_ascii_digit() {
             CharType::Digit
@@ -201,6 +341,12 @@ impl TokenParser {
         }
     }
 
+    /// ASCII, plus the degree sign of angle literals like `30°` and the
+    /// micro sign of `3.3µ`.
+    pub fn is_supported(c: char) -> bool {
+        c.is_ascii() || c == '°' || c == 'µ'
+    }
+
     fn is_whitespace(c: char) -> bool {
         c == ' ' || c == '\t'
     }
@@ -1,4 +1,5 @@
 use super::*;
+use crate::tokens::Num;
 
 #[test]
 fn parse_all_tokens() {
@@ -51,14 +52,14 @@ fn parse_failed() {
 
 #[test]
 fn invalid_char() {
-    let expression = String::from("15 ===  &@3.4 + ^%$#12 \n");
+    let expression = String::from("15 ==  &@3.4 + ^%$#12 \n");
     let mut parser = TokenParser::new(&expression).unwrap();
     let result = parser
         .get_tokens()
         .expect_err("should return InvalidTokenError");
     let expected = InvalidTokenError {
-        position: 4,
-        value: "=".to_owned(),
+        position: 8,
+        value: "&".to_owned(),
     };
     assert_eq!(result, expected);
 }
@@ -128,3 +129,161 @@ fn multiple_decimals() {
     println!("{:?}", t);
     assert_eq!(t, expected);
 }
+
+#[test]
+fn token_spans() {
+    let expression = String::from(" x0 +\t12.5*(y)\n");
+    let mut parser = TokenParser::new(&expression).unwrap();
+    let t = parser
+        .get_spanned_tokens()
+        .expect("should parse successfully");
+    let expected = vec![
+        (Token::new_variable("x0"), (1, 3)),
+        (Token::new_op("+"), (4, 5)),
+        (Token::new_number("12.5"), (6, 10)),
+        (Token::new_op("*"), (10, 11)),
+        (Token::new_paren("("), (11, 12)),
+        (Token::new_variable("y"), (12, 13)),
+        (Token::new_paren(")"), (13, 14)),
+        (Token::EOL, (14, 15)),
+    ];
+    assert_eq!(t, expected);
+}
+
+#[test]
+fn degree_sign() {
+    let expression = String::from("30° + x°");
+    let mut parser = TokenParser::new(&expression).unwrap();
+    let t = parser
+        .get_spanned_tokens()
+        .expect("the degree sign is the one non-ASCII character allowed");
+    let expected = vec![
+        (Token::new_number("30"), (0, 2)),
+        (Token::Degree, (2, 3)),
+        (Token::new_op("+"), (4, 5)),
+        (Token::new_variable("x"), (6, 7)),
+        (Token::Degree, (7, 8)),
+    ];
+    assert_eq!(t, expected);
+}
+
+#[test]
+fn prefixed_numbers() {
+    let expression = String::from("4.7k 10M 3.3µ 2Ki 16Gi 5n");
+    let mut parser = TokenParser::new(&expression).unwrap();
+    let t = parser.get_tokens().expect("should parse successfully");
+    let expected = vec![
+        Token::Number(Num::Float(4700.0)),
+        Token::Number(Num::Integer(10_000_000)),
+        Token::Number(Num::Float(3.3e-6)),
+        Token::Number(Num::Integer(2048)),
+        Token::Number(Num::Integer(16 << 30)),
+        Token::Number(Num::Float(5e-9)),
+    ];
+    assert_eq!(t, expected);
+}
+
+#[test]
+fn letters_that_are_not_a_prefix() {
+    // Only a whole prefix scales: `5km` is the number 5 and the name `km`,
+    // and `mm` is not milli-milli. Letters that name a unit are never a
+    // prefix, so the `m` of `300m` is metres, as in `300mm` and `10ms`, and
+    // only prefixes that are not also units, such as `k` or `n`, scale.
+    let expression = String::from("5km 2mm 1.5x_1 300m");
+    let mut parser = TokenParser::new(&expression).unwrap();
+    let t = parser
+        .get_spanned_tokens()
+        .expect("should parse successfully");
+    let expected = vec![
+        (Token::new_number("5"), (0, 1)),
+        (Token::new_variable("km"), (1, 3)),
+        (Token::new_number("2"), (4, 5)),
+        (Token::new_variable("mm"), (5, 7)),
+        (Token::new_number("1.5"), (8, 11)),
+        (Token::new_variable("x_1"), (11, 14)),
+        (Token::new_number("300"), (15, 18)),
+        (Token::new_variable("m"), (18, 19)),
+    ];
+    assert_eq!(t, expected);
+}
+
+#[test]
+fn dates_and_times() {
+    let expression = String::from("2026-10-18 14:30 9:05:30 10-3 2026-1-5");
+    let mut parser = TokenParser::new(&expression).unwrap();
+    let t = parser
+        .get_spanned_tokens()
+        .expect("should parse successfully");
+    let expected = vec![
+        (Token::Date("2026-10-18".to_owned()), (0, 10)),
+        (Token::Date("14:30".to_owned()), (11, 16)),
+        (Token::Date("9:05:30".to_owned()), (17, 24)),
+        // Digits that do not make a date are a subtraction.
+        (Token::new_number("10"), (25, 27)),
+        (Token::new_op("-"), (27, 28)),
+        (Token::new_number("3"), (28, 29)),
+        (Token::new_number("2026"), (30, 34)),
+        (Token::new_op("-"), (34, 35)),
+        (Token::new_number("1"), (35, 36)),
+        (Token::new_op("-"), (36, 37)),
+        (Token::new_number("5"), (37, 38)),
+    ];
+    assert_eq!(t, expected);
+}
+
+#[test]
+fn lists_ranges_and_lambdas() {
+    let expression = String::from("[1, 2.5][0] 1..10 .. x -> -1");
+    let mut parser = TokenParser::new(&expression).unwrap();
+    let t = parser
+        .get_spanned_tokens()
+        .expect("should parse successfully");
+    let expected = vec![
+        (Token::new_bracket("["), (0, 1)),
+        (Token::new_number("1"), (1, 2)),
+        (Token::Comma, (2, 3)),
+        (Token::new_number("2.5"), (4, 7)),
+        (Token::new_bracket("]"), (7, 8)),
+        (Token::new_bracket("["), (8, 9)),
+        (Token::new_number("0"), (9, 10)),
+        (Token::new_bracket("]"), (10, 11)),
+        // The number before `..` is whole, not `1.`.
+        (Token::new_number("1"), (12, 13)),
+        (Token::Range, (13, 15)),
+        (Token::new_number("10"), (15, 17)),
+        (Token::Range, (18, 20)),
+        (Token::new_variable("x"), (21, 22)),
+        (Token::Arrow, (23, 25)),
+        (Token::new_op("-"), (26, 27)),
+        (Token::new_number("1"), (27, 28)),
+    ];
+    assert_eq!(t, expected);
+}
+
+#[test]
+fn comparisons() {
+    let expression = String::from("a<b <= c>d>=e==f != g = h");
+    let mut parser = TokenParser::new(&expression).unwrap();
+    let t = parser.get_tokens().expect("should parse successfully");
+    let expected = vec![
+        Token::new_variable("a"),
+        Token::new_comparison("<"),
+        Token::new_variable("b"),
+        Token::new_comparison("<="),
+        Token::new_variable("c"),
+        Token::new_comparison(">"),
+        Token::new_variable("d"),
+        Token::new_comparison(">="),
+        Token::new_variable("e"),
+        Token::new_comparison("=="),
+        Token::new_variable("f"),
+        Token::new_comparison("!="),
+        Token::new_variable("g"),
+        Token::Equals,
+        Token::new_variable("h"),
+    ];
+    assert_eq!(t, expected);
+
+    // A `!` is only a token before `=`.
+    assert!(TokenParser::new("3 ! 4").unwrap().get_tokens().is_err());
+}
@@ -1,40 +1,171 @@
 pub mod ast;
+pub mod calculator;
+pub mod cli;
+pub mod commands;
+pub mod compiled;
+pub mod constants;
+pub mod datetime;
+pub mod decimal;
+pub mod derivative;
+pub mod environment;
+pub mod error;
+pub mod finance;
+pub mod forms;
+pub mod functions;
+pub mod integrate;
+pub mod json;
 pub mod lexical_analyzer;
+pub mod list;
+pub mod matrix;
+pub mod money;
+pub mod number_theory;
+pub mod printer;
+pub mod render;
+pub mod settings;
+pub mod simplify;
+pub mod solve;
+pub mod stats;
 pub mod tokens;
+pub mod units;
+pub mod value;
+pub mod vm;
 
 use std::{
     collections::HashMap,
-    f64::consts::PI,
-    io::{self, Write},
+    env,
+    io::{self, IsTerminal, Write},
 };
 
 use ast::AST;
+use calculator::Calculator;
+use cli::Options;
+use decimal::Decimal;
+use error::{CalcError, ErrorKind, Span};
+use json::Json;
 use lexical_analyzer::TokenParser;
+use money::Rates;
+use settings::{NumberFormat, Settings};
 use tokens::Token;
+use value::Value;
 
-pub fn main_loop() {
-    let mut env: HashMap<String, f64> = HashMap::new();
-    env.insert("pi".to_owned(), PI);
-    let mut result_index = 0;
+/// Runs the read-evaluate-print loop over stdin.
+///
+/// When stdin is a terminal a `> ` prompt is shown and an empty line quits.
+/// When it is not (a pipe or redirected file) prompts are suppressed, blank
+/// lines are skipped, errors are written to stderr and the loop runs until
+/// end of input. With `--json` every result and error is written to stdout
+/// as one JSON object per line. Lines starting with `:` are REPL commands,
+/// see [`commands`].
+///
+/// Returns `false` if any expression failed to evaluate.
+pub fn main_loop(options: &Options) -> bool {
+    let interactive = io::stdin().is_terminal();
+    let prompt = if interactive && !options.quiet {
+        "> "
+    } else {
+        ""
+    };
+    let color = use_color(options);
+    let mut calculator = Calculator::with_settings(options.settings());
+    if let Err(e) = configure(&mut calculator, options) {
+        eprintln!("{}", paint_error(&e, color));
+        return false;
+    }
+
+    let mut all_succeeded = true;
     loop {
-        if let Ok(expression) = read_line("> ") {
-            let exp = expression.trim();
-            if exp.is_empty() || exp == "q" {
+        let expression = match read_line(prompt) {
+            Ok(expression) if expression.is_empty() => break,
+            Ok(expression) => expression,
+            Err(e) => {
+                eprintln!("{}", e);
+                all_succeeded = false;
                 break;
             }
+        };
 
-            let result = evaluate_string_expression(&expression, &mut env, result_index);
-            match result {
-                Ok(value) => {
-                    println!("{}", value);
-                    result_index += 1;
-                }
-                Err(e) => {
-                    println!("{}", e);
+        let exp = expression.trim();
+        if exp == "q" || (exp.is_empty() && interactive) {
+            break;
+        } else if exp.is_empty() {
+            continue;
+        }
+
+        if commands::is_command(exp) {
+            let result = commands::run(&mut calculator, exp);
+            match &result {
+                _ if options.json => {
+                    let fields = result
+                        .as_ref()
+                        .map(|out| vec![("output", Json::string(out))]);
+                    println!("{}", input_json(exp, fields));
+                    all_succeeded &= interactive || result.is_ok();
                 }
+                Ok(output) => println!("{}", output),
+                Err(e) => all_succeeded &= report_error(exp, e, interactive, color),
+            }
+            continue;
+        }
+
+        if calculator.settings().echo && !options.json {
+            if let Ok(formatted) = calculator.format(exp) {
+                println!("{}", formatted);
+            }
+        }
+
+        let index = calculator.history().len();
+        let result = calculator.eval(&expression);
+        match &result {
+            _ if options.json => {
+                println!("{}", result_json(exp, index, &result));
+                all_succeeded &= interactive || result.is_ok();
+            }
+            Ok(value) if !options.quiet => {
+                let settings = calculator.settings();
+                println!("{}", format_output(exp, index, value, settings, options))
             }
+            Ok(_) => (),
+            Err(e) => all_succeeded &= report_error(&expression, e, interactive, color),
         }
     }
+    all_succeeded
+}
+
+/// Prints an error from the REPL: to stdout when interactive, where it is just
+/// feedback, otherwise to stderr. Returns whether the session can still succeed.
+fn report_error(expression: &str, error: &CalcError, interactive: bool, color: bool) -> bool {
+    let message = paint_error(&format_error(expression, error), color);
+    if interactive {
+        println!("{}", message);
+        true
+    } else {
+        eprintln!("{}", message);
+        false
+    }
+}
+
+/// Loads the exchange rates and defines the variables given on the command line.
+pub fn configure(calculator: &mut Calculator, options: &Options) -> Result<(), String> {
+    if let Some(path) = &options.rates {
+        let rates = Rates::load(path).map_err(|e| e.to_string())?;
+        calculator.set_rates(rates);
+    }
+    define_variables(calculator, &options.defines)
+}
+
+/// Evaluates each `name=expression` pair in order and stores the result as a variable.
+pub fn define_variables(
+    calculator: &mut Calculator,
+    defines: &[(String, String)],
+) -> Result<(), String> {
+    for (name, expression) in defines {
+        calculator
+            .compile(expression)
+            .and_then(|expr| expr.eval(calculator))
+            .and_then(|value| calculator.set_var(name, value))
+            .map_err(|e| format!("{}: {}", name, format_error(expression, &e)))?;
+    }
+    Ok(())
 }
 
 pub fn evaluate_string_expression(
@@ -42,39 +173,364 @@ pub fn evaluate_string_expression(
     env: &mut HashMap<String, f64>,
     index: i32,
 ) -> Result<String, String> {
-    let parsed_tokens: Vec<Token> = match parse_tokens(expression) {
-        Ok(parsed) => parsed,
-        Err(e) => return Err(e),
-    };
+    evaluate_expression(expression, env, index)
+        .map(|value| format_result(index, value))
+        .map_err(|e| format_error(expression, &e))
+}
+
+/// Evaluates `expression` and stores the result in `env` as `x{index}`.
+pub fn evaluate_expression(
+    expression: &str,
+    env: &mut HashMap<String, f64>,
+    index: i32,
+) -> Result<f64, CalcError> {
+    let result = AST::evaluate(parse_expression(expression)?, env)?;
+    env.insert(format!("x{}", index), result);
+    Ok(result)
+}
 
-    let tree: Box<AST> = match AST::build_tree(&parsed_tokens) {
-        Ok(parsed) => parsed,
-        Err(e) => return Err(e),
+/// Formats a result the way the calculator reports it, e.g. `x0 = 15.0`.
+pub fn format_result(index: i32, value: f64) -> String {
+    format!("x{} = {}", index, format_value(value))
+}
+
+/// Formats a bare value, always showing a fractional part for whole numbers.
+pub fn format_value(value: f64) -> String {
+    format_number(value, NumberFormat::Auto, None)
+}
+
+/// Formats a result as requested on the command line: `x0 = 15.0` by default,
+/// only the value with `--raw`, or `expression = value` with `--latex` or
+/// `--mathml`. Numbers are written as the session `settings` say, which start
+/// out as given on the command line but may be changed by commands such as
+/// `:decimal`.
+pub fn format_output(
+    expression: &str,
+    index: usize,
+    value: &Value,
+    settings: &Settings,
+    options: &Options,
+) -> String {
+    let prefixed;
+    let (number, unit) = match value {
+        Value::Number(x) => (format_number(*x, settings.format, settings.precision), None),
+        Value::Integer(n) => (format_integer(*n, settings), None),
+        Value::Decimal(x) => (format_decimal(x, settings), None),
+        Value::Quantity(quantity) if settings.format == NumberFormat::Engineering => {
+            // The prefix joins the unit where it can, as in `4.7 kV`.
+            let (mantissa, power) = engineering(quantity.value, settings.precision);
+            match si_prefix(power).and_then(|prefix| quantity.unit.prefixed(prefix)) {
+                Some(unit) => {
+                    prefixed = unit;
+                    (mantissa, Some(&prefixed))
+                }
+                None if power == 0 => (mantissa, Some(&quantity.unit)),
+                None => (format!("{}e{}", mantissa, power), Some(&quantity.unit)),
+            }
+        }
+        Value::Quantity(quantity) => (
+            format_number(quantity.value, settings.format, settings.precision),
+            Some(&quantity.unit),
+        ),
+        // Amounts are always shown to the currency's minor unit.
+        Value::Money(money) => {
+            let amount = money.rounded();
+            return match (options.markup, parse_expression(expression)) {
+                (Some(markup), Ok(tree)) => {
+                    render::render_money(&tree, &amount, &money.currency, markup)
+                }
+                _ if options.raw => money.to_string(),
+                _ => format!("{} = {}", Calculator::result_name(index), money),
+            };
+        }
+        Value::Date(_) | Value::Duration(_) | Value::List(_) | Value::Factors(_) => {
+            let text = format_element(value, settings);
+            return match (options.markup, parse_expression(expression)) {
+                (Some(markup), Ok(tree)) => render::render_text(&tree, &text, markup),
+                _ if options.raw => text,
+                _ => format!("{} = {}", Calculator::result_name(index), text),
+            };
+        }
+    };
+    let formatted = match unit {
+        Some(unit) => format!("{} {}", number, unit),
+        None => number.clone(),
     };
+    match (options.markup, parse_expression(expression)) {
+        (Some(markup), Ok(tree)) => render::render_result(&tree, &number, unit, markup),
+        _ if options.raw => formatted,
+        _ => format!("{} = {}", Calculator::result_name(index), formatted),
+    }
+}
+
+/// A value inside a list, with numbers in the requested notation.
+fn format_element(value: &Value, settings: &Settings) -> String {
+    match value {
+        Value::Number(x) => format_number(*x, settings.format, settings.precision),
+        Value::Integer(n) => format_integer(*n, settings),
+        Value::Decimal(x) => format_decimal(x, settings),
+        Value::List(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| format_element(item, settings))
+                .collect();
+            format!("[{}]", items.join(", "))
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Formats an exact integer as a float would be while a float holds it
+/// exactly, so `10` is `10.0`, and with all its digits past that. In decimal
+/// mode it is a decimal.
+fn format_integer(n: i128, settings: &Settings) -> String {
+    match number_theory::exact_float(n) {
+        Some(x) if settings.decimal.is_none() => {
+            format_number(x, settings.format, settings.precision)
+        }
+        _ => format_decimal(&Decimal::new(n, 0), settings),
+    }
+}
 
-    match AST::evaluate(tree, env) {
-        Ok(result) => {
-            env.insert(format!("x{}", index), result);
-            if result == result.floor() {
-                Ok(format!("x{} = {}.0", index, result))
+/// Formats an exact decimal with all its digits, or to the requested places
+/// with the rounding of the decimal mode. Scientific notations go through a
+/// float.
+fn format_decimal(value: &Decimal, settings: &Settings) -> String {
+    let rounding = settings.decimal.unwrap_or_default().rounding;
+    match (settings.format, settings.precision) {
+        (NumberFormat::Auto, None) => value.to_string(),
+        (NumberFormat::Auto | NumberFormat::Fixed, Some(places)) => {
+            value.to_fixed(places as u32, rounding)
+        }
+        (NumberFormat::Fixed, None) => value.to_fixed(6, rounding),
+        (format, precision) => format_number(value.to_f64(), format, precision),
+    }
+}
+
+/// Formats `value` in the given notation. `precision` is the number of digits
+/// after the decimal point; without it `Auto` prints the shortest exact
+/// representation and `Fixed` uses six digits.
+pub fn format_number(value: f64, format: NumberFormat, precision: Option<usize>) -> String {
+    match (format, precision) {
+        _ if !value.is_finite() => format!("{}", value),
+        (NumberFormat::Auto, None) => {
+            if value == value.floor() {
+                format!("{}.0", value)
             } else {
-                Ok(format!("x{} = {}", index, result))
+                format!("{}", value)
             }
         }
-        Err(e) => Err(e),
+        (NumberFormat::Auto, Some(digits)) => {
+            let fixed = format!("{:.*}", digits, value);
+            if fixed.contains('.') {
+                let trimmed = fixed.trim_end_matches('0');
+                match trimmed.strip_suffix('.') {
+                    Some(whole) => format!("{}.0", whole),
+                    None => trimmed.to_owned(),
+                }
+            } else {
+                format!("{}.0", fixed)
+            }
+        }
+        (NumberFormat::Fixed, digits) => format!("{:.*}", digits.unwrap_or(6), value),
+        (NumberFormat::Scientific, None) => format!("{:e}", value),
+        (NumberFormat::Scientific, Some(digits)) => format!("{:.*e}", digits, value),
+        (NumberFormat::Engineering, digits) => {
+            let (mantissa, power) = engineering(value, digits);
+            match si_prefix(power) {
+                Some("") => mantissa,
+                Some(prefix) => format!("{} {}", mantissa, prefix),
+                None => format!("{}e{}", mantissa, power),
+            }
+        }
+    }
+}
+
+/// Splits `value` into a mantissa from 1 up to 1000 and a power of ten that
+/// is a multiple of three: 4700 is `4.7` and 3.
+fn engineering(value: f64, precision: Option<usize>) -> (String, i32) {
+    let scientific = format!("{:e}", value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let power = exponent.div_euclid(3) * 3;
+    // Moving the decimal point in the digits keeps them exact.
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", mantissa),
+    };
+    let mut digits = mantissa.replace('.', "");
+    let point = (exponent - power) as usize + 1;
+    while digits.len() < point {
+        digits.push('0');
+    }
+    let (whole, fraction) = digits.split_at(point);
+    let fraction = if fraction.is_empty() { "0" } else { fraction };
+    let text = format!("{}{}.{}", sign, whole, fraction);
+    match precision {
+        None => (text, power),
+        Some(digits) => {
+            let mantissa: f64 = text.parse().unwrap_or(value);
+            let rounded = format!("{:.*}", digits, mantissa);
+            // Rounding 999.96 to one digit gives 1000.0, the next prefix up.
+            if rounded
+                .trim_start_matches('-')
+                .parse::<f64>()
+                .unwrap_or(0.0)
+                >= 1000.0
+            {
+                (format!("{:.*}", digits, mantissa / 1000.0), power + 3)
+            } else {
+                (rounded, power)
+            }
+        }
+    }
+}
+
+/// The SI prefix for `10^power`, empty for 1.
+fn si_prefix(power: i32) -> Option<&'static str> {
+    let prefix = match power {
+        18 => "E",
+        15 => "P",
+        12 => "T",
+        9 => "G",
+        6 => "M",
+        3 => "k",
+        0 => "",
+        -3 => "m",
+        -6 => "µ",
+        -9 => "n",
+        -12 => "p",
+        -15 => "f",
+        -18 => "a",
+        _ => return None,
+    };
+    Some(prefix)
+}
+
+/// Formats an error for display. Invalid characters are pointed out with an
+/// arrow under the first line of the expression.
+pub fn format_error(expression: &str, error: &CalcError) -> String {
+    match (error.kind, error.span) {
+        (ErrorKind::InvalidCharacter, Some((start, _))) => {
+            let first_line = expression.split('\n').next().unwrap_or("");
+            format!("{}\n{}\n{}^", error, first_line, " ".repeat(start))
+        }
+        _ => error.to_string(),
     }
 }
 
-fn parse_tokens(expression: &str) -> Result<Vec<Token>, String> {
-    let mut parser =
-        TokenParser::new(expression).map_err(|_| "Expression contains non-ascii characters.")?;
+/// Describes the outcome of evaluating `input` as a JSON object, e.g.
+/// `{"input": "10 + 2", "name": "x0", "value": 12, "type": "integer"}`.
+pub fn result_json(input: &str, index: usize, result: &Result<Value, CalcError>) -> Json {
+    input_json(
+        input,
+        result.as_ref().map(|value| value_fields(index, value)),
+    )
+}
 
-    parser.get_tokens().map_err(|e| {
-        let end = expression
+/// The `name`, `value` and `type` fields describing the result stored as
+/// `x{index}`, and the `unit` of a quantity. Dates are ISO strings,
+/// durations are seconds, lists are arrays and factorizations are strings
+/// such as `"2^3 * 5"`.
+pub fn value_fields(index: usize, value: &Value) -> Vec<(&'static str, Json)> {
+    let mut fields = vec![
+        ("name", Json::String(Calculator::result_name(index))),
+        ("value", value_json(value)),
+        ("type", Json::string(value.type_name())),
+    ];
+    match value {
+        Value::Quantity(quantity) => fields.push(("unit", Json::String(quantity.unit.to_string()))),
+        Value::Money(money) => fields.push(("currency", Json::string(&money.currency))),
+        Value::Duration(_) => fields.push(("unit", Json::string("s"))),
+        Value::Number(_)
+        | Value::Integer(_)
+        | Value::Decimal(_)
+        | Value::Date(_)
+        | Value::List(_)
+        | Value::Factors(_) => {}
+    }
+    fields
+}
+
+fn value_json(value: &Value) -> Json {
+    match value {
+        Value::Number(x) => Json::Number(*x),
+        Value::Integer(n) => Json::Integer(*n),
+        Value::Decimal(x) => Json::Number(x.to_f64()),
+        Value::Quantity(quantity) => Json::Number(quantity.value),
+        Value::Money(money) => Json::Number(money.amount.to_f64()),
+        Value::Date(date) => Json::String(date.to_string()),
+        Value::Duration(duration) => Json::Number(duration.seconds),
+        Value::List(items) => Json::Array(items.iter().map(value_json).collect()),
+        Value::Factors(_) => Json::String(value.to_string()),
+    }
+}
+
+/// Builds `{"input": ..., <fields>}` on success or `{"input": ..., "error": {...}}`.
+pub fn input_json(input: &str, fields: Result<Vec<(&str, Json)>, &CalcError>) -> Json {
+    let mut object = vec![("input", Json::string(input))];
+    match fields {
+        Ok(fields) => object.extend(fields),
+        Err(e) => object.push(("error", error_json(e))),
+    }
+    Json::object(object)
+}
+
+pub fn error_json(error: &CalcError) -> Json {
+    let span = match error.span {
+        Some((start, end)) => {
+            Json::Array(vec![Json::Number(start as f64), Json::Number(end as f64)])
+        }
+        None => Json::Null,
+    };
+    Json::object(vec![
+        ("kind", Json::string(error.kind.name())),
+        ("message", Json::string(&error.message)),
+        ("span", span),
+    ])
+}
+
+/// Whether errors should be colored: not disabled with `--no-color` or
+/// `NO_COLOR`, and stdout is a terminal.
+pub fn use_color(options: &Options) -> bool {
+    options.color && env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal()
+}
+
+pub fn paint_error(message: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[0;31m{}\x1b[0m", message)
+    } else {
+        message.to_owned()
+    }
+}
+
+/// Lexes and parses `expression` without evaluating it.
+pub fn parse_expression(expression: &str) -> Result<Box<AST>, CalcError> {
+    AST::build_tree_spanned(&tokenize(expression)?)
+}
+
+pub fn parse_tokens(expression: &str) -> Result<Vec<Token>, CalcError> {
+    let tokens = tokenize(expression)?;
+    Ok(tokens.into_iter().map(|(token, _)| token).collect())
+}
+
+/// Lexes `expression`, keeping the span each token was read from.
+pub fn tokenize(expression: &str) -> Result<Vec<(Token, Span)>, CalcError> {
+    let mut parser = TokenParser::new(expression).map_err(|_| {
+        let position = expression
             .chars()
-            .position(|c| c == '\n')
-            .unwrap_or(expression.len());
-        format!("{}\n{}\n{}", e, &expression[..end], e.arrow())
+            .position(|c| !TokenParser::is_supported(c))
+            .unwrap_or(0);
+        CalcError::new(
+            ErrorKind::NonAscii,
+            "Expression contains non-ascii characters.",
+        )
+        .with_span((position, position + 1))
+    })?;
+
+    parser.get_spanned_tokens().map_err(|e| {
+        CalcError::new(ErrorKind::InvalidCharacter, &e.to_string())
+            .with_span((e.position - 1, e.position))
     })
 }
 
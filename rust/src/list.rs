@@ -0,0 +1,154 @@
+//! Lists of values, as in `[1, 2, 3]` or `1..10`.
+//!
+//! Arithmetic with a list applies element by element: `[1, 2] + [10, 20]` is
+//! `[11, 22]` and `[1, 2] * 3` is `[3, 6]`. `map`, `filter` and `reduce`
+//! are special forms in `forms`, as their function argument is not a value.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    ast::AST,
+    environment::Environment,
+    error::{CalcError, ErrorKind},
+    tokens::Op,
+    value::Value,
+};
+
+/// How many elements a range may have.
+pub(crate) const MAX_LENGTH: f64 = 1e6;
+
+/// `left oper right` where either side is a list: pairs of elements of two
+/// lists of the same shape, or each element of one list with the other
+/// value. A matrix, a list of lists, does not combine with a flat list, as
+/// whether that should go by rows or by columns is a guess.
+pub fn broadcast(
+    oper: &Op,
+    left: Value,
+    right: Value,
+    env: &dyn Environment,
+) -> Result<Value, CalcError> {
+    let items: Result<Vec<Value>, CalcError> = match (left, right) {
+        (Value::List(left), Value::List(right)) => {
+            if is_matrix(&left) != is_matrix(&right) {
+                let (matrix, flat) = if is_matrix(&left) {
+                    (&left, &right)
+                } else {
+                    (&right, &left)
+                };
+                return Err(CalcError::new(
+                    ErrorKind::DimensionMismatch,
+                    &format!(
+                        "Cannot combine a matrix of {} rows with a list of {} elements",
+                        matrix.len(),
+                        flat.len()
+                    ),
+                ));
+            }
+            if left.len() != right.len() {
+                return Err(CalcError::new(
+                    ErrorKind::LengthMismatch,
+                    &format!(
+                        "Cannot combine lists of {} and {} elements",
+                        left.len(),
+                        right.len()
+                    ),
+                ));
+            }
+            left.into_iter()
+                .zip(right)
+                .map(|(x, y)| AST::evaluate_operator(oper, x, y, env))
+                .collect()
+        }
+        (Value::List(left), right) => left
+            .into_iter()
+            .map(|x| AST::evaluate_operator(oper, x, right.clone(), env))
+            .collect(),
+        (left, Value::List(right)) => right
+            .into_iter()
+            .map(|y| AST::evaluate_operator(oper, left.clone(), y, env))
+            .collect(),
+        (left, right) => return AST::evaluate_operator(oper, left, right, env),
+    };
+    items.map(Value::List)
+}
+
+/// Whether `items` are the rows of a matrix rather than a flat list.
+fn is_matrix(items: &[Value]) -> bool {
+    items.iter().any(|item| matches!(item, Value::List(_)))
+}
+
+/// The element of `list` at `index`, counting from 0, or from the end when
+/// negative, so that `v[-1]` is the last.
+pub fn index(list: Value, index: Value) -> Result<Value, CalcError> {
+    let Value::List(items) = list else {
+        return Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("Only a list can be indexed, not {}", list.described()),
+        ));
+    };
+    let position = whole("An index", &index)?;
+    let length = items.len() as f64;
+    let from_start = if position < 0.0 {
+        position + length
+    } else {
+        position
+    };
+    if !(0.0..length).contains(&from_start) {
+        return Err(CalcError::new(
+            ErrorKind::IndexOutOfRange,
+            &format!(
+                "Index {} is out of range for a list of {} elements",
+                position, length
+            ),
+        ));
+    }
+    Ok(items
+        .into_iter()
+        .nth(from_start as usize)
+        .expect("index is in range"))
+}
+
+/// The whole numbers from `from` to `to`, both included, counting down when
+/// `to` is the smaller.
+pub fn range(from: Value, to: Value) -> Result<Value, CalcError> {
+    let (from, to) = (whole("A range", &from)?, whole("A range", &to)?);
+    if (to - from).abs() >= MAX_LENGTH {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!("A range is limited to {} elements", MAX_LENGTH),
+        ));
+    }
+    let step = if to < from { -1 } else { 1 };
+    let length = (to - from).abs() as usize + 1;
+    let items = (0..length).map(|i| Value::Integer(from as i128 + step * i as i128));
+    Ok(Value::List(items.collect()))
+}
+
+/// `value` as a whole number, for what `what` describes.
+fn whole(what: &str, value: &Value) -> Result<f64, CalcError> {
+    match value.as_number() {
+        Some(x) if x.is_finite() && x == x.trunc() => Ok(x),
+        Some(x) => Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!("{} needs whole numbers, not {}", what, x),
+        )),
+        None => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("{} needs numbers, not {}", what, value.described()),
+        )),
+    }
+}
+
+/// The values with each list replaced by its elements, so that functions of
+/// any number of arguments take a list too, as in `max([3, 1, 2])`.
+pub fn spread(values: &[Value]) -> Vec<Value> {
+    let mut spread = Vec::with_capacity(values.len());
+    for value in values {
+        match value {
+            Value::List(items) => spread.extend(self::spread(items)),
+            value => spread.push(value.clone()),
+        }
+    }
+    spread
+}
@@ -0,0 +1,148 @@
+use crate::calculator::Calculator;
+
+use super::*;
+
+fn eval(expression: &str) -> Result<Value, CalcError> {
+    Calculator::new().eval(expression)
+}
+
+fn numbers(xs: &[f64]) -> Value {
+    Value::List(xs.iter().map(|x| Value::Number(*x)).collect())
+}
+
+fn integers(ns: &[i128]) -> Value {
+    Value::List(ns.iter().map(|n| Value::Integer(*n)).collect())
+}
+
+fn shown(expression: &str) -> String {
+    eval(expression).unwrap().to_string()
+}
+
+#[test]
+fn literals_and_ranges() {
+    assert_eq!(eval("[1, 2, 3]"), Ok(integers(&[1, 2, 3])));
+    assert_eq!(eval("[]"), Ok(Value::List(vec![])));
+    assert_eq!(eval("[1 + 1, 2^3]"), Ok(integers(&[2, 8])));
+    assert_eq!(eval("1..4"), Ok(integers(&[1, 2, 3, 4])));
+    assert_eq!(eval("3..1"), Ok(integers(&[3, 2, 1])));
+    assert_eq!(eval("-1..1"), Ok(integers(&[-1, 0, 1])));
+    assert_eq!(shown("[1, [2, 3], 1..2]"), "[1.0, [2.0, 3.0], [1.0, 2.0]]");
+
+    let error = eval("1..2.5").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = eval("1..10000000").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = eval("[1, 2").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::MissingParen);
+}
+
+#[test]
+fn indexing() {
+    assert_eq!(eval("[10, 20, 30][0]"), Ok(Value::Integer(10)));
+    assert_eq!(eval("[10, 20, 30][-1]"), Ok(Value::Integer(30)));
+    assert_eq!(eval("(1..10)[4]"), Ok(Value::Integer(5)));
+    assert_eq!(eval("[[1, 2], [3, 4]][1][0]"), Ok(Value::Integer(3)));
+    assert_eq!(eval("[1, 2, 3][1 + 1] * 2"), Ok(Value::Integer(6)));
+
+    let error = eval("[1, 2, 3][3]").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::IndexOutOfRange);
+    let error = eval("[1, 2, 3][-4]").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::IndexOutOfRange);
+    let error = eval("[1, 2][0.5]").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn broadcasting() {
+    assert_eq!(eval("[1, 2] + [10, 20]"), Ok(integers(&[11, 22])));
+    assert_eq!(eval("[1, 2] * 3"), Ok(integers(&[3, 6])));
+    assert_eq!(eval("10 - [1, 2]"), Ok(integers(&[9, 8])));
+    assert_eq!(eval("(1..3)^2"), Ok(integers(&[1, 4, 9])));
+    assert_eq!(eval("-[1, 2]"), Ok(integers(&[-1, -2])));
+    assert_eq!(shown("[[1, 2], [3, 4]] * 2"), "[[2.0, 4.0], [6.0, 8.0]]");
+    assert_eq!(shown("[1 km, 2 km] + 500 m"), "[1.5 km, 2.5 km]");
+
+    let error = eval("[1, 2] + [1, 2, 3]").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::LengthMismatch);
+    assert_eq!(
+        shown("[[1, 2], [3, 4]] + [[10, 20], [30, 40]]"),
+        "[[11.0, 22.0], [33.0, 44.0]]"
+    );
+    for expression in ["[[1, 2], [3, 4]] * [1, 1]", "[1, 2, 3] - [[1, 2], [3, 4]]"] {
+        let error = eval(expression).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::DimensionMismatch, "{}", expression);
+    }
+
+    // Comparisons broadcast too.
+    assert_eq!(eval("[1, 2, 3] >= 2"), Ok(integers(&[0, 1, 1])));
+}
+
+#[test]
+fn map_filter_reduce() {
+    assert_eq!(eval("map([1, 2, 3], x -> x^2)"), Ok(integers(&[1, 4, 9])));
+    assert_eq!(
+        eval("map(1..3, sqrt)"),
+        Ok(numbers(&[1.0, 2f64.sqrt(), 3f64.sqrt()]))
+    );
+    assert_eq!(
+        eval("filter(1..5, n -> n - 3)"),
+        Ok(integers(&[1, 2, 4, 5]))
+    );
+    assert_eq!(
+        eval("filter(1..10, x -> x > 5)"),
+        Ok(integers(&[6, 7, 8, 9, 10]))
+    );
+    assert_eq!(
+        eval("filter([1, 2, 3, 2], x -> x != 2)"),
+        Ok(integers(&[1, 3]))
+    );
+    assert_eq!(
+        eval("reduce(1..5, (a, b) -> a * b)"),
+        Ok(Value::Integer(120))
+    );
+    assert_eq!(
+        eval("reduce([], (a, b) -> a + b, 7)"),
+        Ok(Value::Integer(7))
+    );
+    assert_eq!(
+        eval("reduce([1, 2], (a, b) -> a - b, 10)"),
+        Ok(Value::Integer(7))
+    );
+
+    // Parameters shadow variables only inside the lambda.
+    let mut calculator = Calculator::new();
+    calculator.set_var("x", 100.0).unwrap();
+    assert_eq!(
+        calculator.eval("map([1, 2], x -> x + 1)"),
+        Ok(integers(&[2, 3]))
+    );
+    assert_eq!(calculator.eval("x"), Ok(Value::Number(100.0)));
+
+    let error = eval("map([1, 2], (a, b) -> a)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+    let error = eval("reduce([], (a, b) -> a + b)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = eval("map(3, x -> x)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::TypeMismatch);
+    let error = eval("map([1], nosuch)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnknownFunction);
+}
+
+#[test]
+fn functions_take_lists() {
+    assert_eq!(eval("max([3, 1, 2])"), Ok(Value::Number(3.0)));
+    assert_eq!(eval("min(5, [3, 4])"), Ok(Value::Number(3.0)));
+    assert_eq!(eval("mean(1..4)"), Ok(Value::Number(2.5)));
+    assert_eq!(eval("var([1, 2, 3, 4])"), Ok(Value::Number(5.0 / 3.0)));
+    assert_eq!(eval("count([1, [2, 3]])"), Ok(Value::Number(3.0)));
+    assert_eq!(eval("sum([1, 2, 3])"), Ok(Value::Number(6.0)));
+}
+
+#[test]
+fn spreading() {
+    let values = [Value::Integer(1), integers(&[2, 3])];
+    assert_eq!(
+        spread(&values),
+        vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)]
+    );
+}
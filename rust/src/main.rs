@@ -1,29 +1,197 @@
-use std::{collections::HashMap, env};
-
-use cli_calculator::{evaluate_string_expression, main_loop};
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() == 1 {
-        main_loop();
-    } else if args.len() == 3 && args.get(1).unwrap_or(&"".to_owned()) == "--expression" {
-        let default = "".to_owned();
-        let e = args.get(2).unwrap_or(&default);
-        let mut env = HashMap::new();
-        let mut output = String::new();
-        let mut index = 0;
-        for expression in e.split(';') {
-            match evaluate_string_expression(expression, &mut env, index) {
-                Ok(value) => {
-                    output.push_str(&value.to_string());
-                    index += 1;
-                }
-                Err(e) => output.push_str(&e.to_string()),
-            };
-            output.push('\n');
+use std::{
+    env, fs,
+    io::{self, BufRead, IsTerminal},
+    process::ExitCode,
+};
+
+use cli_calculator::{
+    calculator::Calculator,
+    cli::{self, Action, Command, Options},
+    configure,
+    error::{CalcError, Span},
+    format_error, format_output, input_json,
+    json::Json,
+    main_loop, paint_error, parse_expression,
+    printer::infix,
+    tokenize,
+    tokens::Token,
+    use_color, value_fields,
+};
+
+/// The text and JSON renderings of one expression's outcome.
+type Report = Result<(String, Vec<(&'static str, Json)>), CalcError>;
+
+fn main() -> ExitCode {
+    let options = match cli::parse_args(env::args().skip(1)) {
+        Ok(Action::Run(options)) => options,
+        Ok(Action::Help) => {
+            print!("{}", cli::help_text());
+            return ExitCode::SUCCESS;
         }
-        print!("{}", output);
+        Ok(Action::Version) => {
+            println!("{}", cli::version_text());
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\nFor more information, try '--help'.", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let succeeded = match options.command() {
+        Command::Repl => main_loop(&options),
+        Command::Eval if options.expressions.is_empty() && options.file.is_none() => {
+            main_loop(&options)
+        }
+        command => match collect_expressions(&options) {
+            Ok(expressions) => run(command, &expressions, &options),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                false
+            }
+        },
+    };
+
+    if succeeded {
+        ExitCode::SUCCESS
     } else {
-        println!("invalid arguments");
+        ExitCode::FAILURE
+    }
+}
+
+/// Gathers expressions from `--expression`/positional arguments (split on
+/// `;`) and `--file`, falling back to stdin lines when neither was given.
+fn collect_expressions(options: &Options) -> Result<Vec<String>, String> {
+    let mut expressions: Vec<String> = options
+        .expressions
+        .iter()
+        .flat_map(|e| e.split(';'))
+        .map(|e| e.to_owned())
+        .collect();
+
+    if let Some(path) = &options.file {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        expressions.extend(
+            contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.to_owned()),
+        );
     }
+
+    if options.expressions.is_empty() && options.file.is_none() && !io::stdin().is_terminal() {
+        for line in io::stdin().lock().lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            if !line.trim().is_empty() {
+                expressions.push(line);
+            }
+        }
+    }
+    Ok(expressions)
+}
+
+fn run(command: Command, expressions: &[String], options: &Options) -> bool {
+    let color = use_color(options);
+    let mut calculator = Calculator::with_settings(options.settings());
+    if let Err(e) = configure(&mut calculator, options) {
+        eprintln!("{}", paint_error(&e, color));
+        return false;
+    }
+
+    let mut output = String::new();
+    let mut all_succeeded = true;
+    for expression in expressions {
+        let result: Report = match command {
+            Command::Eval => {
+                let index = calculator.history().len();
+                calculator.eval(expression).map(|value| {
+                    (
+                        format_output(expression, index, &value, calculator.settings(), options),
+                        value_fields(index, &value),
+                    )
+                })
+            }
+            Command::Check => parse_expression(expression)
+                .map(|_| ("ok".to_owned(), vec![("ok", Json::Bool(true))])),
+            Command::Tokens => tokenize(expression).map(|tokens| {
+                let text = tokens
+                    .iter()
+                    .map(|(token, _)| format!("{:?}", token))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                let json = tokens
+                    .iter()
+                    .map(|(token, span)| token_json(expression, token, *span))
+                    .collect();
+                (text, vec![("tokens", Json::Array(json))])
+            }),
+            Command::Ast => parse_expression(expression).map(|tree| {
+                let text = tree.to_string();
+                let json = vec![("ast", Json::string(&text))];
+                (text, json)
+            }),
+            Command::Format => parse_expression(expression).map(|tree| {
+                let text = infix(&tree);
+                let json = vec![("formatted", Json::string(&text))];
+                (text, json)
+            }),
+            Command::Repl => unreachable!("the repl does not take a list of expressions"),
+        };
+
+        let line = match (&result, options.json) {
+            (Ok((_, fields)), true) => Some(input_json(expression, Ok(fields.clone())).to_string()),
+            (Err(e), true) => Some(input_json(expression, Err(e)).to_string()),
+            (Ok((text, _)), false) if !options.quiet => Some(text.to_owned()),
+            (Ok(_), false) => None,
+            (Err(e), false) => Some(paint_error(&format_error(expression, e), color)),
+        };
+        if let Some(line) = line {
+            output.push_str(&line);
+            output.push('\n');
+        }
+        all_succeeded &= result.is_ok();
+    }
+    print!("{}", output);
+    all_succeeded
+}
+
+fn token_json(expression: &str, token: &Token, (start, end): Span) -> Json {
+    let kind = match token {
+        Token::Paren(_) => "Paren",
+        Token::Number(_) => "Number",
+        Token::Operator(_) => "Operator",
+        Token::Comparison(_) => "Comparison",
+        Token::Variable(_) => "Variable",
+        Token::Function(_) => "Function",
+        Token::Comma => "Comma",
+        Token::Equals => "Equals",
+        Token::Degree => "Degree",
+        Token::Unit(_) => "Unit",
+        Token::Conversion(_) => "Conversion",
+        Token::Currency(_) => "Currency",
+        Token::Exchange(_) => "Exchange",
+        Token::Date(_) => "Date",
+        Token::Duration => "Duration",
+        Token::Bracket(_) => "Bracket",
+        Token::Range => "Range",
+        Token::Arrow => "Arrow",
+        Token::List => "List",
+        Token::Index => "Index",
+        Token::Lambda(_) => "Lambda",
+        Token::Whitespace => "Whitespace",
+        Token::EOL => "EOL",
+        Token::InvalidToken(_) => "InvalidToken",
+    };
+    Json::object(vec![
+        ("token", Json::string(kind)),
+        (
+            "text",
+            // Spans count characters, not bytes.
+            Json::String(expression.chars().skip(start).take(end - start).collect()),
+        ),
+        (
+            "span",
+            Json::Array(vec![Json::Number(start as f64), Json::Number(end as f64)]),
+        ),
+    ])
 }
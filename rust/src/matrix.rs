@@ -0,0 +1,408 @@
+//! Matrices, written as lists of rows as in `[[1, 2], [3, 4]]`.
+//!
+//! Element-wise arithmetic is that of lists; this module adds the linear
+//! algebra: `matmul`, `transpose`, `det`, `inv`, `rank`, `eig` and
+//! `identity`, and the linear systems `solve(A, b)` hands over. A list of
+//! numbers is a vector, a row on the left of `matmul` and a column anywhere
+//! else.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    error::{CalcError, ErrorKind},
+    functions::{Arity, ValueFunction},
+    value::Value,
+};
+
+/// How many sweeps of rotations `eig` makes before giving up.
+const MAX_SWEEPS: usize = 100;
+
+/// A dense matrix of numbers, stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn new(rows: usize, cols: usize, data: Vec<f64>) -> Self {
+        assert_eq!(
+            data.len(),
+            rows * cols,
+            "matrix data does not fit its shape"
+        );
+        Matrix { rows, cols, data }
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let mut identity = Matrix::new(n, n, vec![0.0; n * n]);
+        for i in 0..n {
+            identity.set(i, i, 1.0);
+        }
+        identity
+    }
+
+    pub fn at(&self, row: usize, col: usize) -> f64 {
+        self.data[row * self.cols + col]
+    }
+
+    fn set(&mut self, row: usize, col: usize, value: f64) {
+        self.data[row * self.cols + col] = value;
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        for col in 0..self.cols {
+            self.data.swap(a * self.cols + col, b * self.cols + col);
+        }
+    }
+
+    /// The shape as it appears in messages, e.g. `2×3`.
+    pub fn shape(&self) -> String {
+        format!("{}×{}", self.rows, self.cols)
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let mut data = Vec::with_capacity(self.data.len());
+        for col in 0..self.cols {
+            data.extend((0..self.rows).map(|row| self.at(row, col)));
+        }
+        Matrix::new(self.cols, self.rows, data)
+    }
+
+    pub fn multiply(&self, other: &Matrix) -> Result<Matrix, CalcError> {
+        if self.cols != other.rows {
+            return Err(CalcError::new(
+                ErrorKind::DimensionMismatch,
+                &format!(
+                    "Cannot multiply a {} matrix by a {} matrix",
+                    self.shape(),
+                    other.shape()
+                ),
+            ));
+        }
+        let mut data = Vec::with_capacity(self.rows * other.cols);
+        for row in 0..self.rows {
+            for col in 0..other.cols {
+                data.push(
+                    (0..self.cols)
+                        .map(|k| self.at(row, k) * other.at(k, col))
+                        .sum(),
+                );
+            }
+        }
+        Ok(Matrix::new(self.rows, other.cols, data))
+    }
+
+    /// The size of a square matrix, or an error naming `name` otherwise.
+    fn square(&self, name: &str) -> Result<usize, CalcError> {
+        if self.rows != self.cols {
+            return Err(CalcError::new(
+                ErrorKind::DimensionMismatch,
+                &format!("{} needs a square matrix, not {}", name, self.shape()),
+            ));
+        }
+        Ok(self.rows)
+    }
+
+    /// Entries no larger than this count as zero in elimination: rounding
+    /// leaves them where exact arithmetic would cancel.
+    fn tolerance(&self) -> f64 {
+        let largest = self.data.iter().fold(0.0, |max: f64, x| max.max(x.abs()));
+        self.rows.max(self.cols) as f64 * f64::EPSILON * largest
+    }
+
+    /// Reduces the matrix to row echelon form by Gaussian elimination with
+    /// partial pivoting, doing the same row operations on `other`. Returns
+    /// the columns that hold pivots and whether the rows were swapped an odd
+    /// number of times.
+    fn eliminate(&mut self, mut other: Option<&mut Matrix>) -> (Vec<usize>, bool) {
+        let tolerance = self.tolerance();
+        let mut pivots = vec![];
+        let mut odd = false;
+        for col in 0..self.cols {
+            let row = pivots.len();
+            if row == self.rows {
+                break;
+            }
+            let best = (row..self.rows)
+                .max_by(|a, b| self.at(*a, col).abs().total_cmp(&self.at(*b, col).abs()))
+                .expect("rows remain");
+            if self.at(best, col).abs() <= tolerance {
+                continue;
+            }
+            if best != row {
+                self.swap_rows(best, row);
+                if let Some(other) = other.as_deref_mut() {
+                    other.swap_rows(best, row);
+                }
+                odd = !odd;
+            }
+            for below in row + 1..self.rows {
+                let factor = self.at(below, col) / self.at(row, col);
+                if factor == 0.0 {
+                    continue;
+                }
+                for c in col..self.cols {
+                    self.set(below, c, self.at(below, c) - factor * self.at(row, c));
+                }
+                if let Some(other) = other.as_deref_mut() {
+                    for c in 0..other.cols {
+                        other.set(below, c, other.at(below, c) - factor * other.at(row, c));
+                    }
+                }
+            }
+            pivots.push(col);
+        }
+        (pivots, odd)
+    }
+
+    pub fn determinant(&self) -> Result<f64, CalcError> {
+        let n = self.square("det")?;
+        let mut reduced = self.clone();
+        let (pivots, odd) = reduced.eliminate(None);
+        if pivots.len() < n {
+            return Ok(0.0);
+        }
+        let product: f64 = (0..n).map(|i| reduced.at(i, i)).product();
+        Ok(if odd { -product } else { product })
+    }
+
+    pub fn rank(&self) -> usize {
+        self.clone().eliminate(None).0.len()
+    }
+
+    /// The `x` with `self * x = b`, for each column of `b`.
+    pub fn solve(&self, b: &Matrix) -> Result<Matrix, CalcError> {
+        let n = self.square("solve")?;
+        if b.rows != n {
+            return Err(CalcError::new(
+                ErrorKind::DimensionMismatch,
+                &format!(
+                    "solve needs {} values on the right for a {} matrix, not {}",
+                    n,
+                    self.shape(),
+                    b.rows
+                ),
+            ));
+        }
+        let mut reduced = self.clone();
+        let mut x = b.clone();
+        let (pivots, _) = reduced.eliminate(Some(&mut x));
+        if pivots.len() < n {
+            return Err(singular());
+        }
+        for col in 0..x.cols {
+            for row in (0..n).rev() {
+                let known: f64 = (row + 1..n)
+                    .map(|k| reduced.at(row, k) * x.at(k, col))
+                    .sum();
+                x.set(row, col, (x.at(row, col) - known) / reduced.at(row, row));
+            }
+        }
+        Ok(x)
+    }
+
+    pub fn inverse(&self) -> Result<Matrix, CalcError> {
+        let n = self.square("inv")?;
+        self.solve(&Matrix::identity(n))
+    }
+
+    /// The eigenvalues of a symmetric matrix in increasing order, by cyclic
+    /// Jacobi rotations.
+    pub fn eigenvalues(&self) -> Result<Vec<f64>, CalcError> {
+        let n = self.square("eig")?;
+        let scale = self.data.iter().fold(1.0, |max: f64, x| max.max(x.abs()));
+        let symmetric =
+            (0..n).all(|i| (0..i).all(|j| (self.at(i, j) - self.at(j, i)).abs() <= 1e-10 * scale));
+        if !symmetric {
+            return Err(CalcError::new(
+                ErrorKind::Domain,
+                "eig needs a symmetric matrix",
+            ));
+        }
+        let mut a = self.clone();
+        for _ in 0..MAX_SWEEPS {
+            let off: f64 = (0..n)
+                .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+                .map(|(i, j)| a.at(i, j).powi(2))
+                .sum();
+            if off.sqrt() <= f64::EPSILON * scale {
+                let mut values: Vec<f64> = (0..n).map(|i| a.at(i, i)).collect();
+                values.sort_by(f64::total_cmp);
+                return Ok(values);
+            }
+            for p in 0..n {
+                for q in p + 1..n {
+                    a.rotate(p, q);
+                }
+            }
+        }
+        Err(CalcError::new(
+            ErrorKind::NoConvergence,
+            "eig did not converge",
+        ))
+    }
+
+    /// Applies the Jacobi rotation that zeroes the entries at `(p, q)` and
+    /// `(q, p)` of a symmetric matrix.
+    fn rotate(&mut self, p: usize, q: usize) {
+        let apq = self.at(p, q);
+        if apq == 0.0 {
+            return;
+        }
+        let theta = (self.at(q, q) - self.at(p, p)) / (2.0 * apq);
+        let t = theta.signum() / (theta.abs() + theta.hypot(1.0));
+        let c = 1.0 / t.hypot(1.0);
+        let s = t * c;
+        for k in 0..self.rows {
+            let (kp, kq) = (self.at(k, p), self.at(k, q));
+            self.set(k, p, c * kp - s * kq);
+            self.set(k, q, s * kp + c * kq);
+        }
+        for k in 0..self.cols {
+            let (pk, qk) = (self.at(p, k), self.at(q, k));
+            self.set(p, k, c * pk - s * qk);
+            self.set(q, k, s * pk + c * qk);
+        }
+    }
+}
+
+fn singular() -> CalcError {
+    CalcError::new(ErrorKind::Domain, "The matrix is singular")
+}
+
+/// A matrix argument of `name`, and whether it was written as a vector, a
+/// plain list of numbers, which is read as a single row.
+pub fn matrix(name: &str, value: &Value) -> Result<(Matrix, bool), CalcError> {
+    let Value::List(rows) = value else {
+        return Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("{} expects a matrix but got {}", name, value.described()),
+        ));
+    };
+    let entry = |value: &Value| {
+        value.as_number().ok_or_else(|| {
+            CalcError::new(
+                ErrorKind::TypeMismatch,
+                &format!("{} expects numbers but got {}", name, value.described()),
+            )
+        })
+    };
+    if rows.is_empty() {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!("{} expects a matrix but got an empty list", name),
+        ));
+    }
+    if !rows.iter().any(|row| matches!(row, Value::List(_))) {
+        let data = rows
+            .iter()
+            .map(entry)
+            .collect::<Result<Vec<f64>, CalcError>>()?;
+        return Ok((Matrix::new(1, data.len(), data), true));
+    }
+    let mut data = vec![];
+    let mut cols = None;
+    for row in rows {
+        let Value::List(row) = row else {
+            return Err(CalcError::new(
+                ErrorKind::TypeMismatch,
+                &format!(
+                    "{} expects a matrix of rows but got {}",
+                    name,
+                    row.described()
+                ),
+            ));
+        };
+        if *cols.get_or_insert(row.len()) != row.len() || row.is_empty() {
+            return Err(CalcError::new(
+                ErrorKind::DimensionMismatch,
+                &format!("{} expects rows of the same, nonzero length", name),
+            ));
+        }
+        for value in row {
+            data.push(entry(value)?);
+        }
+    }
+    let cols = cols.expect("at least one row");
+    Ok((Matrix::new(rows.len(), cols, data), false))
+}
+
+impl From<Matrix> for Value {
+    fn from(matrix: Matrix) -> Self {
+        let rows = matrix
+            .data
+            .chunks(matrix.cols.max(1))
+            .map(|row| Value::List(row.iter().map(|x| Value::Number(*x)).collect()))
+            .collect();
+        Value::List(rows)
+    }
+}
+
+/// The entries of `matrix` as a plain list, for a vector result.
+fn vector(matrix: Matrix) -> Value {
+    Value::List(matrix.data.into_iter().map(Value::Number).collect())
+}
+
+/// `A x = b` for the matrix `A` and the vector or matrix `b`, which
+/// `solve(A, b)` evaluates to.
+pub fn solve(system: &Value, rhs: &Value) -> Result<Value, CalcError> {
+    let (a, _) = matrix("solve", system)?;
+    let (b, is_vector) = matrix("solve", rhs)?;
+    if is_vector {
+        Ok(vector(a.solve(&b.transpose())?))
+    } else {
+        Ok(a.solve(&b)?.into())
+    }
+}
+
+/// `matmul(a, b)`, with a vector on the left as a row and on the right as a
+/// column. Two vectors make their dot product.
+fn matmul(args: &[Value]) -> Result<Value, CalcError> {
+    let (a, a_vector) = matrix("matmul", &args[0])?;
+    let (b, b_vector) = matrix("matmul", &args[1])?;
+    let b = if b_vector { b.transpose() } else { b };
+    let product = a.multiply(&b)?;
+    Ok(match (a_vector, b_vector) {
+        (true, true) => Value::Number(product.at(0, 0)),
+        (false, false) => product.into(),
+        _ => vector(product),
+    })
+}
+
+/// A function of one matrix argument.
+fn unary<F>(name: &'static str, function: F) -> ValueFunction
+where
+    F: Fn(Matrix) -> Result<Value, CalcError> + 'static,
+{
+    ValueFunction::new(name, Arity::Exact(1), move |args| {
+        function(matrix(name, &args[0])?.0)
+    })
+}
+
+pub fn functions() -> Vec<ValueFunction> {
+    vec![
+        ValueFunction::new("matmul", Arity::Exact(2), matmul),
+        unary("transpose", |m| Ok(m.transpose().into())),
+        unary("det", |m| m.determinant().map(Value::Number)),
+        unary("inv", |m| m.inverse().map(Value::from)),
+        unary("rank", |m| Ok(Value::Number(m.rank() as f64))),
+        unary("eig", |m| {
+            let values = m.eigenvalues()?;
+            Ok(Value::List(values.into_iter().map(Value::Number).collect()))
+        }),
+        ValueFunction::new("identity", Arity::Exact(1), |args| {
+            match args[0].as_number() {
+                Some(n) if n >= 1.0 && n == n.trunc() && n <= 1000.0 => {
+                    Ok(Matrix::identity(n as usize).into())
+                }
+                _ => Err(CalcError::new(
+                    ErrorKind::InvalidArgument,
+                    &format!("identity needs a size from 1 to 1000, not {}", args[0]),
+                )),
+            }
+        }),
+    ]
+}
@@ -0,0 +1,149 @@
+use crate::calculator::Calculator;
+
+use super::*;
+
+fn eval(expression: &str) -> Result<Value, CalcError> {
+    Calculator::new().eval(expression)
+}
+
+fn number(expression: &str) -> f64 {
+    eval(expression).unwrap().as_number().unwrap()
+}
+
+fn numbers(value: Value) -> Vec<f64> {
+    match value {
+        Value::List(items) => items.iter().map(|x| x.as_number().unwrap()).collect(),
+        value => panic!("{} is not a list", value),
+    }
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(
+        actual.len(),
+        expected.len(),
+        "{:?} != {:?}",
+        actual,
+        expected
+    );
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn products_and_transposes() {
+    let a = Matrix::new(2, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    assert_eq!(
+        a.transpose(),
+        Matrix::new(3, 2, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0])
+    );
+    assert_eq!(
+        a.multiply(&a.transpose()),
+        Ok(Matrix::new(2, 2, vec![14.0, 32.0, 32.0, 77.0]))
+    );
+    let error = a.multiply(&a).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DimensionMismatch);
+    assert_eq!(
+        error.message,
+        "Cannot multiply a 2×3 matrix by a 2×3 matrix"
+    );
+
+    assert_eq!(
+        eval("matmul([[1, 2], [3, 4]], [[5, 6], [7, 8]])")
+            .unwrap()
+            .to_string(),
+        "[[19.0, 22.0], [43.0, 50.0]]"
+    );
+    assert_close(
+        &numbers(eval("matmul([[1, 2], [3, 4]], [1, 1])").unwrap()),
+        &[3.0, 7.0],
+    );
+    assert_close(
+        &numbers(eval("matmul([1, 1], [[1, 2], [3, 4]])").unwrap()),
+        &[4.0, 6.0],
+    );
+    assert_eq!(number("matmul([1, 2, 3], [4, 5, 6])"), 32.0);
+    assert_eq!(
+        eval("transpose([[1, 2, 3]])").unwrap().to_string(),
+        "[[1.0], [2.0], [3.0]]"
+    );
+    assert_eq!(
+        eval("identity(2) * 3").unwrap().to_string(),
+        "[[3.0, 0.0], [0.0, 3.0]]"
+    );
+}
+
+#[test]
+fn determinants_and_ranks() {
+    assert_eq!(number("det([[1, 2], [3, 4]])"), -2.0);
+    assert!((number("det([[2, 0, 1], [1, 3, 2], [1, 1, 2]])") - 6.0).abs() < 1e-12);
+    assert_eq!(number("det([[0, 1], [1, 0]])"), -1.0);
+    assert_eq!(number("det([[1, 2], [2, 4]])"), 0.0);
+    assert_eq!(number("det([[5]])"), 5.0);
+
+    assert_eq!(number("rank([[1, 2], [2, 4]])"), 1.0);
+    assert_eq!(number("rank([[1, 2, 3], [4, 5, 6], [7, 8, 9]])"), 2.0);
+    assert_eq!(number("rank(identity(4))"), 4.0);
+    assert_eq!(number("rank([[0, 0], [0, 0]])"), 0.0);
+    // Rounding leaves tiny remainders where the rows cancel exactly.
+    assert_eq!(number("rank([[0.1, 0.2], [0.3, 0.6]])"), 1.0);
+
+    let error = eval("det([[1, 2, 3], [4, 5, 6]])").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DimensionMismatch);
+    assert_eq!(error.message, "det needs a square matrix, not 2×3");
+}
+
+#[test]
+fn inverses_and_systems() {
+    let inverse = eval("inv([[4, 7], [2, 6]])").unwrap();
+    let Value::List(rows) = inverse else {
+        panic!("not a matrix")
+    };
+    assert_close(&numbers(rows[0].clone()), &[0.6, -0.7]);
+    assert_close(&numbers(rows[1].clone()), &[-0.2, 0.4]);
+
+    // A 3×3 system, as from a circuit's mesh currents.
+    let x = eval("solve([[2, 1, -1], [-3, -1, 2], [-2, 1, 2]], [8, -11, -3])").unwrap();
+    assert_close(&numbers(x), &[2.0, 3.0, -1.0]);
+    let mut calculator = Calculator::new();
+    calculator
+        .set_var("A", eval("[[1, 1], [1, -1]]").unwrap())
+        .unwrap();
+    calculator.set_var("b", eval("[3, 1]").unwrap()).unwrap();
+    assert_close(
+        &numbers(calculator.eval("solve(A, b)").unwrap()),
+        &[2.0, 1.0],
+    );
+    let x = calculator.eval("solve(A, [[3, 0], [1, 2]])").unwrap();
+    assert_eq!(x.to_string(), "[[2.0, 1.0], [1.0, -1.0]]");
+    // Equations still solve for a name.
+    assert_eq!(calculator.eval("solve(x^2 = 4, x)"), Ok(Value::Number(2.0)));
+
+    let error = eval("inv([[1, 2], [2, 4]])").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+    let error = eval("solve([[1, 2], [3, 4]], [1, 2, 3])").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DimensionMismatch);
+    let error = eval("inv([[1, 2], [3]])").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DimensionMismatch);
+    let error = eval("inv(3)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::TypeMismatch);
+}
+
+#[test]
+fn eigenvalues() {
+    assert_close(
+        &numbers(eval("eig([[2, 1], [1, 2]])").unwrap()),
+        &[1.0, 3.0],
+    );
+    assert_close(
+        &numbers(eval("eig([[2, -1, 0], [-1, 2, -1], [0, -1, 2]])").unwrap()),
+        &[2.0 - 2f64.sqrt(), 2.0, 2.0 + 2f64.sqrt()],
+    );
+    assert_close(
+        &numbers(eval("eig([[3, 0], [0, -1]])").unwrap()),
+        &[-1.0, 3.0],
+    );
+
+    let error = eval("eig([[1, 2], [3, 4]])").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+}
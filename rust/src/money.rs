@@ -0,0 +1,544 @@
+//! Amounts of money and conversion between currencies at exchange rates
+//! loaded from a local file.
+//!
+//! A currency code after a number or a name, as in `120 USD` or `price EUR`,
+//! makes an amount of money, and `to EUR` converts one. Amounts are exact
+//! decimals, so `0.1 USD + 0.2 USD` is `0.30 USD`, and only amounts in the
+//! same currency can be added or compared.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Display;
+
+use crate::{
+    decimal::{Decimal, Rounding},
+    environment::Environment,
+    error::{CalcError, ErrorKind},
+    tokens::Op,
+    value::Value,
+};
+
+/// Currencies whose minor unit is not a hundredth, with the number of
+/// digits after the decimal point they are shown with.
+const MINOR_UNITS: &[(&str, u32)] = &[
+    ("BHD", 3),
+    ("BIF", 0),
+    ("CLP", 0),
+    ("DJF", 0),
+    ("GNF", 0),
+    ("IQD", 3),
+    ("ISK", 0),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KMF", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("LYD", 3),
+    ("OMR", 3),
+    ("PYG", 0),
+    ("RWF", 0),
+    ("TND", 3),
+    ("UGX", 0),
+    ("VND", 0),
+    ("VUV", 0),
+    ("XAF", 0),
+    ("XOF", 0),
+    ("XPF", 0),
+];
+
+/// Whether `name` is a currency code: three capital letters, as in `EUR`.
+pub fn is_currency(name: &str) -> bool {
+    name.len() == 3 && name.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Digits after the decimal point in amounts of `currency`: 2 for most,
+/// 0 for `JPY`, 3 for `KWD`.
+pub fn minor_units(currency: &str) -> u32 {
+    MINOR_UNITS
+        .iter()
+        .find(|(code, _)| *code == currency)
+        .map_or(2, |(_, digits)| *digits)
+}
+
+/// An amount in a currency, as in `120 USD`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Money {
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: &str) -> Self {
+        Money {
+            amount,
+            currency: currency.to_owned(),
+        }
+    }
+
+    /// The amount rounded to the currency's minor unit, ties away from zero.
+    pub fn rounded(&self) -> String {
+        self.amount
+            .to_fixed(minor_units(&self.currency), Rounding::HalfUp)
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.rounded(), self.currency)
+    }
+}
+
+/// Exchange rates, as the amount of each currency one unit of a common base
+/// currency buys.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rates {
+    rates: Vec<(String, Decimal)>,
+}
+
+impl Rates {
+    /// Reads rates from a JSON object, either flat as in
+    /// `{"USD": 1, "EUR": 0.92}` or as `{"base": "USD", "rates": {...}}`, or
+    /// from CSV lines `EUR,0.92` with an optional header.
+    pub fn parse(text: &str) -> Result<Rates, CalcError> {
+        let rates = if text.trim_start().starts_with('{') {
+            Self::parse_json(text)?
+        } else {
+            Self::parse_csv(text)?
+        };
+        let mut sorted: Vec<(String, Decimal)> = vec![];
+        for (code, rate) in rates {
+            if !is_currency(&code) {
+                return Err(invalid_rates(&format!("{:?} is not a currency code", code)));
+            }
+            if rate.is_zero() || rate.is_negative() {
+                return Err(invalid_rates(&format!(
+                    "the rate of {} must be positive",
+                    code
+                )));
+            }
+            sorted.retain(|(known, _)| *known != code);
+            sorted.push((code, rate));
+        }
+        sorted.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(Rates { rates: sorted })
+    }
+
+    fn parse_json(text: &str) -> Result<Vec<(String, Decimal)>, CalcError> {
+        let fields = match JsonReader::new(text).document()? {
+            Item::Object(fields) => fields,
+            _ => return Err(invalid_rates("expected an object")),
+        };
+        let nested = fields.iter().find_map(|(key, item)| match item {
+            Item::Object(rates) if key == "rates" => Some(rates),
+            _ => None,
+        });
+        let mut rates = vec![];
+        if nested.is_some() {
+            let base = fields.iter().find_map(|(key, item)| match item {
+                Item::String(base) if key == "base" => Some(base),
+                _ => None,
+            });
+            if let Some(base) = base {
+                rates.push((base.clone(), Decimal::new(1, 0)));
+            }
+        }
+        for (code, item) in nested.unwrap_or(&fields) {
+            match item {
+                Item::Number(rate) => rates.push((code.clone(), *rate)),
+                _ if nested.is_none() => {}
+                _ => {
+                    return Err(invalid_rates(&format!(
+                        "the rate of {} is not a number",
+                        code
+                    )))
+                }
+            }
+        }
+        Ok(rates)
+    }
+
+    fn parse_csv(text: &str) -> Result<Vec<(String, Decimal)>, CalcError> {
+        let mut rates = vec![];
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            match fields.as_slice() {
+                [code, rate] => match Decimal::parse(rate) {
+                    Some(rate) => rates.push((code.to_string(), rate)),
+                    // A header such as `currency,rate`.
+                    None if rates.is_empty() => {}
+                    None => {
+                        return Err(invalid_rates(&format!(
+                            "line {}: {:?} is not a number",
+                            index + 1,
+                            rate
+                        )))
+                    }
+                },
+                _ => {
+                    return Err(invalid_rates(&format!(
+                        "line {}: expected CODE,RATE",
+                        index + 1
+                    )))
+                }
+            }
+        }
+        Ok(rates)
+    }
+
+    /// Reads rates from the file at `path`.
+    pub fn load(path: &str) -> Result<Rates, CalcError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            CalcError::new(
+                ErrorKind::InvalidArgument,
+                &format!("Cannot read {}: {}", path, e),
+            )
+        })?;
+        Self::parse(&text)
+    }
+
+    pub fn rate(&self, currency: &str) -> Option<Decimal> {
+        self.rates
+            .iter()
+            .find(|(code, _)| code == currency)
+            .map(|(_, rate)| *rate)
+    }
+
+    /// The currencies and their rates, sorted by code.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Decimal)> {
+        self.rates.iter().map(|(code, rate)| (code.as_str(), *rate))
+    }
+
+    pub fn len(&self) -> usize {
+        self.rates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+
+    /// `money` in `currency`, exact up to the digits division keeps.
+    pub fn convert(&self, money: &Money, currency: &str) -> Result<Money, CalcError> {
+        if money.currency == currency {
+            return Ok(money.clone());
+        }
+        let rate = |code: &str| {
+            self.rate(code).ok_or_else(|| {
+                CalcError::new(
+                    ErrorKind::UnknownCurrency,
+                    &format!("No exchange rate for {}; load rates with :rates FILE", code),
+                )
+            })
+        };
+        let (from, to) = (rate(&money.currency)?, rate(currency)?);
+        let amount = money
+            .amount
+            .checked_mul(&to)
+            .and_then(|amount| amount.checked_div(&from))
+            .ok_or_else(too_large)?;
+        Ok(Money::new(amount, currency))
+    }
+}
+
+fn invalid_rates(message: &str) -> CalcError {
+    CalcError::new(
+        ErrorKind::InvalidArgument,
+        &format!("Invalid rates file: {}", message),
+    )
+}
+
+fn too_large() -> CalcError {
+    CalcError::new(ErrorKind::Domain, "Amount of money too large")
+}
+
+/// The parts of a JSON document a rates file uses. Numbers keep their
+/// digits; arrays, booleans and null are read but not kept.
+enum Item {
+    Object(Vec<(String, Item)>),
+    String(String),
+    Number(Decimal),
+    Other,
+}
+
+struct JsonReader {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl JsonReader {
+    fn new(text: &str) -> Self {
+        JsonReader {
+            chars: text.chars().collect(),
+            position: 0,
+        }
+    }
+
+    fn document(&mut self) -> Result<Item, CalcError> {
+        let item = self.item()?;
+        self.skip_whitespace();
+        match self.chars.get(self.position) {
+            None => Ok(item),
+            Some(c) => Err(self.unexpected(*c)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.position += 1;
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        let c = self.chars.get(self.position).copied();
+        self.position += 1;
+        c
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.position).copied()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), CalcError> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.unexpected(c)),
+            None => Err(invalid_rates("unexpected end of file")),
+        }
+    }
+
+    fn unexpected(&self, c: char) -> CalcError {
+        invalid_rates(&format!("unexpected {:?} in JSON", c))
+    }
+
+    fn item(&mut self) -> Result<Item, CalcError> {
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => {
+                self.position += 1;
+                if self.peek() != Some(']') {
+                    self.item()?;
+                    while self.peek() == Some(',') {
+                        self.position += 1;
+                        self.item()?;
+                    }
+                }
+                self.expect(']')?;
+                Ok(Item::Other)
+            }
+            Some('"') => Ok(Item::String(self.string()?)),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.position;
+                while self
+                    .chars
+                    .get(self.position)
+                    .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(*c))
+                {
+                    self.position += 1;
+                }
+                let text: String = self.chars[start..self.position].iter().collect();
+                Decimal::parse(&text)
+                    .map(Item::Number)
+                    .ok_or_else(|| invalid_rates(&format!("{} is out of range", text)))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.position;
+                while self
+                    .chars
+                    .get(self.position)
+                    .is_some_and(|c| c.is_ascii_alphabetic())
+                {
+                    self.position += 1;
+                }
+                let word: String = self.chars[start..self.position].iter().collect();
+                match word.as_str() {
+                    "true" | "false" | "null" => Ok(Item::Other),
+                    _ => Err(invalid_rates(&format!("unexpected {:?} in JSON", word))),
+                }
+            }
+            Some(c) => Err(self.unexpected(c)),
+            None => Err(invalid_rates("unexpected end of file")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Item, CalcError> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Item::Object(fields));
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.item()?));
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Item::Object(fields)),
+                Some(c) => return Err(self.unexpected(c)),
+                None => return Err(invalid_rates("unexpected end of file")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, CalcError> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            let c = self.chars.get(self.position).copied();
+            self.position += 1;
+            match c {
+                Some('"') => return Ok(text),
+                Some('\\') => {
+                    let escaped = self.chars.get(self.position).copied();
+                    self.position += 1;
+                    match escaped {
+                        Some('n') => text.push('\n'),
+                        Some('t') => text.push('\t'),
+                        Some(c) => text.push(c),
+                        None => return Err(invalid_rates("unexpected end of file")),
+                    }
+                }
+                Some(c) => text.push(c),
+                None => return Err(invalid_rates("unexpected end of file")),
+            }
+        }
+    }
+}
+
+/// `value` as an amount of `currency`: a number becomes one, and money in
+/// another currency is converted at the rates of `env`.
+pub fn in_currency(
+    value: Value,
+    currency: &str,
+    env: &dyn Environment,
+) -> Result<Value, CalcError> {
+    match value {
+        Value::Integer(n) => Ok(Value::Money(Box::new(Money::new(
+            Decimal::new(n, 0),
+            currency,
+        )))),
+        Value::Decimal(amount) => Ok(Value::Money(Box::new(Money::new(*amount, currency)))),
+        Value::Number(x) => {
+            let amount = Decimal::from_f64(x).ok_or_else(|| {
+                CalcError::new(
+                    ErrorKind::Domain,
+                    &format!("{} is not an amount of money", x),
+                )
+            })?;
+            Ok(Value::Money(Box::new(Money::new(amount, currency))))
+        }
+        Value::Money(money) => {
+            let converted = match env.rates() {
+                Some(rates) => rates.convert(&money, currency)?,
+                None => Rates::default().convert(&money, currency)?,
+            };
+            Ok(Value::Money(Box::new(converted)))
+        }
+        value => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("Cannot give {} a currency", value.described()),
+        )),
+    }
+}
+
+/// What `value` is called in errors: its currency, or its type.
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Money(money) => money.currency.clone(),
+        Value::Number(_) | Value::Integer(_) | Value::Decimal(_) => "a number".to_owned(),
+        Value::Quantity(quantity) => quantity.unit.to_string(),
+        value => value.described(),
+    }
+}
+
+/// `left oper right` where at least one side is money. Amounts add only to
+/// amounts in the same currency, and scale by plain numbers.
+pub fn operate(oper: &Op, left: Value, right: Value) -> Result<Value, CalcError> {
+    let mismatch = |verb: &str| {
+        let kind = match (&left, &right) {
+            (
+                Value::Money(_) | Value::Number(_) | Value::Decimal(_),
+                Value::Money(_) | Value::Number(_) | Value::Decimal(_),
+            ) => ErrorKind::CurrencyMismatch,
+            _ => ErrorKind::TypeMismatch,
+        };
+        Err(CalcError::new(
+            kind,
+            &format!(
+                "Cannot {} {} and {}",
+                verb,
+                describe(&left),
+                describe(&right)
+            ),
+        ))
+    };
+    let amount = |value: &Value| match value {
+        Value::Money(money) => Some(money.amount),
+        Value::Number(x) => Decimal::from_f64(*x),
+        Value::Decimal(x) => Some(**x),
+        _ => None,
+    };
+    let money = |amount: Option<Decimal>, currency: &str| {
+        amount
+            .map(|amount| Value::Money(Box::new(Money::new(amount, currency))))
+            .ok_or_else(too_large)
+    };
+    match (oper, &left, &right) {
+        (Op::Add | Op::Sub, Value::Money(a), Value::Money(b)) if a.currency == b.currency => {
+            let sum = match oper {
+                Op::Add => a.amount.checked_add(&b.amount),
+                _ => a.amount.checked_sub(&b.amount),
+            };
+            money(sum, &a.currency)
+        }
+        (Op::Add, _, _) => mismatch("add"),
+        (Op::Sub, _, _) => mismatch("subtract"),
+        (Op::Mult, Value::Money(a), Value::Number(_) | Value::Decimal(_))
+        | (Op::Mult, Value::Number(_) | Value::Decimal(_), Value::Money(a)) => {
+            let (x, y) = (amount(&left), amount(&right));
+            let product = x.zip(y).and_then(|(x, y)| x.checked_mul(&y));
+            money(product, &a.currency)
+        }
+        (Op::Div, Value::Money(a), Value::Money(_) | Value::Number(_) | Value::Decimal(_)) => {
+            let divisor = amount(&right).ok_or_else(too_large)?;
+            if divisor.is_zero() {
+                return Err(CalcError::new(ErrorKind::DivideByZero, "Divide by Zero"));
+            }
+            let quotient = a.amount.checked_div(&divisor);
+            match &right {
+                // How many times one amount goes into another is a number.
+                Value::Money(b) if b.currency == a.currency => {
+                    Ok(Value::Number(quotient.ok_or_else(too_large)?.to_f64()))
+                }
+                Value::Money(_) => mismatch("divide"),
+                _ => money(quotient, &a.currency),
+            }
+        }
+        (Op::Mult, _, _) => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!(
+                "Cannot multiply {} by {}",
+                describe(&left),
+                describe(&right)
+            ),
+        )),
+        (Op::Div, _, _) => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("Cannot divide {} by {}", describe(&left), describe(&right)),
+        )),
+        (Op::Pow, _, _) => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            "Money cannot be raised to a power",
+        )),
+    }
+}
@@ -0,0 +1,148 @@
+use crate::calculator::Calculator;
+
+use super::*;
+
+const RATES: &str = r#"{
+    "base": "USD",
+    "date": "2026-10-16",
+    "rates": {"EUR": 0.92, "GBP": 0.79, "JPY": 151.2, "KWD": 0.3071}
+}"#;
+
+fn calculator() -> Calculator {
+    let mut calculator = Calculator::new();
+    calculator.set_rates(Rates::parse(RATES).expect("the rates should parse"));
+    calculator
+}
+
+fn eval(calculator: &mut Calculator, expression: &str) -> Result<String, CalcError> {
+    calculator.eval(expression).map(|value| value.to_string())
+}
+
+#[test]
+fn currency_codes() {
+    assert!(is_currency("EUR"));
+    assert!(!is_currency("Eur"));
+    assert!(!is_currency("EURO"));
+    assert_eq!(minor_units("USD"), 2);
+    assert_eq!(minor_units("JPY"), 0);
+    assert_eq!(minor_units("KWD"), 3);
+}
+
+#[test]
+fn rates_files() {
+    let rates = Rates::parse(RATES).unwrap();
+    let codes: Vec<&str> = rates.iter().map(|(code, _)| code).collect();
+    assert_eq!(codes, ["EUR", "GBP", "JPY", "KWD", "USD"]);
+    assert_eq!(rates.rate("USD"), Decimal::parse("1"));
+
+    let flat = Rates::parse(r#"{"USD": 1, "EUR": 0.92}"#).unwrap();
+    assert_eq!(flat.rate("EUR"), Decimal::parse("0.92"));
+    let csv = Rates::parse("currency,rate\nUSD,1\n# euro\nEUR, 0.92\n").unwrap();
+    assert_eq!(csv, flat);
+
+    for text in [
+        "USD,1\nEUR,lots",
+        "USD;1",
+        r#"{"rates": {"EUR": "0.92"}}"#,
+        r#"{"usd": 1}"#,
+        r#"{"EUR": -1}"#,
+        r#"{"EUR": 1"#,
+    ] {
+        let error = Rates::parse(text).expect_err(text);
+        assert_eq!(error.kind, ErrorKind::InvalidArgument, "{}", text);
+        assert!(error.message.starts_with("Invalid rates file"), "{}", text);
+    }
+}
+
+#[test]
+fn amounts_and_conversions() {
+    let mut calculator = calculator();
+    calculator.set_var("price", 19.99).unwrap();
+    assert_eq!(
+        eval(&mut calculator, "120 USD to EUR"),
+        Ok("110.40 EUR".to_owned())
+    );
+    assert_eq!(
+        eval(&mut calculator, "price EUR * 3"),
+        Ok("59.97 EUR".to_owned())
+    );
+    assert_eq!(
+        eval(&mut calculator, "0.1 USD + 0.2 USD"),
+        Ok("0.30 USD".to_owned())
+    );
+    assert_eq!(
+        eval(&mut calculator, "-(2 + 3) GBP"),
+        Ok("-5.00 GBP".to_owned())
+    );
+    assert_eq!(
+        eval(&mut calculator, "1000 JPY in USD"),
+        Ok("6.61 USD".to_owned())
+    );
+    assert_eq!(
+        eval(&mut calculator, "100 EUR to JPY"),
+        Ok("16435 JPY".to_owned())
+    );
+    assert_eq!(
+        eval(&mut calculator, "10 USD to KWD"),
+        Ok("3.071 KWD".to_owned())
+    );
+    // Amounts are kept exact and only rounded when shown.
+    assert_eq!(
+        eval(&mut calculator, "10 USD / 3"),
+        Ok("3.33 USD".to_owned())
+    );
+    assert_eq!(eval(&mut calculator, "x7 * 3"), Ok("10.00 USD".to_owned()));
+    assert_eq!(
+        eval(&mut calculator, "0.125 USD"),
+        Ok("0.13 USD".to_owned())
+    );
+    assert_eq!(calculator.eval("30 EUR / 12 EUR"), Ok(Value::Number(2.5)));
+}
+
+#[test]
+fn currencies_must_match() {
+    let mut calculator = calculator();
+    for (expression, kind, message) in [
+        (
+            "1 USD + 1 EUR",
+            ErrorKind::CurrencyMismatch,
+            "Cannot add USD and EUR",
+        ),
+        (
+            "1 USD - 1",
+            ErrorKind::CurrencyMismatch,
+            "Cannot subtract USD and a number",
+        ),
+        (
+            "1 USD / 1 EUR",
+            ErrorKind::CurrencyMismatch,
+            "Cannot divide USD and EUR",
+        ),
+        (
+            "3 m + 2 USD",
+            ErrorKind::TypeMismatch,
+            "Cannot add m and USD",
+        ),
+        (
+            "2 USD * 2 USD",
+            ErrorKind::TypeMismatch,
+            "Cannot multiply USD by USD",
+        ),
+        (
+            "2 USD ^ 2",
+            ErrorKind::TypeMismatch,
+            "Money cannot be raised to a power",
+        ),
+        ("1 USD / 0", ErrorKind::DivideByZero, "Divide by Zero"),
+        (
+            "5 CHF to USD",
+            ErrorKind::UnknownCurrency,
+            "No exchange rate for CHF; load rates with :rates FILE",
+        ),
+    ] {
+        let error = calculator.eval(expression).expect_err(expression);
+        assert_eq!((error.kind, error.message.as_str()), (kind, message));
+    }
+    let error = Calculator::new().eval("1 USD to EUR").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnknownCurrency);
+}
@@ -0,0 +1,452 @@
+//! Combinatorics and number theory on exact integers: `gcd`, `lcm`, `nCr`
+//! (or `choose`), `nPr`, `isprime`, `factor`, `nextprime`, `modpow`,
+//! `modinv`, `totient` and `fib`.
+//!
+//! Integer literals and their sums, differences, products and whole powers
+//! are exact integers, so `isprime(2^61 - 1)` sees the Mersenne prime rather
+//! than the nearest float. These functions can be passed by name too, as in
+//! `map(1..10, isprime)`, and give exact integers, which keep every digit
+//! past 2^53. Primality is decided by Miller–Rabin with
+//! bases that make it deterministic for 64-bit numbers, and factorization
+//! splits off factors with Pollard's rho in Brent's variant.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    error::{CalcError, ErrorKind},
+    functions::{Arity, ValueFunction},
+    tokens::Op,
+    value::Value,
+};
+
+/// A number theory function of its arguments as exact integers.
+type IntegerFn = fn(&[i128]) -> Result<Value, CalcError>;
+
+/// The number theory functions, as (name, arity, implementation).
+const FUNCTIONS: [(&str, Arity, IntegerFn); 12] = [
+    ("gcd", Arity::AtLeast(2), gcd_function),
+    ("lcm", Arity::AtLeast(2), lcm_function),
+    ("nCr", Arity::Exact(2), choose_function),
+    ("choose", Arity::Exact(2), choose_function),
+    ("nPr", Arity::Exact(2), permutations_function),
+    ("isprime", Arity::Exact(1), is_prime_function),
+    ("factor", Arity::Exact(1), factor_function),
+    ("nextprime", Arity::Exact(1), next_prime_function),
+    ("modpow", Arity::Exact(3), mod_pow_function),
+    ("modinv", Arity::Exact(2), mod_inverse_function),
+    ("totient", Arity::Exact(1), totient_function),
+    ("fib", Arity::Exact(1), fib_function),
+];
+
+/// The functions, which take exact integers.
+pub fn functions() -> Vec<ValueFunction> {
+    FUNCTIONS
+        .iter()
+        .map(|&(name, arity, function)| {
+            ValueFunction::new(name, arity, move |args| {
+                let args = args
+                    .iter()
+                    .map(|arg| whole(name, arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                function(&args)
+            })
+        })
+        .collect()
+}
+
+/// Witnesses that make Miller–Rabin exact below 2^64.
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// How many steps of Pollard's rho share one gcd.
+const BATCH: usize = 128;
+
+/// Largest integer a float holds exactly.
+const MAX_EXACT_FLOAT: i128 = 1 << 53;
+
+/// `n` as a float, if a float holds it exactly.
+pub fn exact_float(n: i128) -> Option<f64> {
+    (n.abs() <= MAX_EXACT_FLOAT).then_some(n as f64)
+}
+
+pub fn gcd(a: u128, b: u128) -> u128 {
+    let (mut a, mut b) = (a, b);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+pub fn lcm(a: u128, b: u128) -> Option<u128> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// The number of ways to choose `k` of `n` things, or `None` on overflow.
+pub fn choose(n: u128, k: u128) -> Option<u128> {
+    if k > n {
+        return Some(0);
+    }
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+    for i in 0..k {
+        // result * (n - i) / (i + 1) is whole; dividing out the common factor
+        // first keeps the product small.
+        let divisor = i + 1;
+        let common = gcd(result, divisor);
+        result = (result / common).checked_mul((n - i) / (divisor / common))?;
+    }
+    Some(result)
+}
+
+/// The number of ordered arrangements of `k` of `n` things.
+pub fn permutations(n: u128, k: u128) -> Option<u128> {
+    if k > n {
+        return Some(0);
+    }
+    (n - k + 1..=n).try_fold(1u128, |product, x| product.checked_mul(x))
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+pub fn pow_mod(base: u64, exponent: u64, m: u64) -> u64 {
+    let (mut base, mut exponent, mut result) = (base % m, exponent, 1 % m);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// The inverse of `a` modulo `m`, if they are coprime.
+pub fn mod_inverse(a: u64, m: u64) -> Option<u64> {
+    // The extended Euclidean algorithm, tracking the coefficient of `a`.
+    let (mut r, mut next_r) = (m as i128, (a % m) as i128);
+    let (mut t, mut next_t) = (0i128, 1i128);
+    while next_r != 0 {
+        let q = r / next_r;
+        (r, next_r) = (next_r, r - q * next_r);
+        (t, next_t) = (next_t, t - q * next_t);
+    }
+    (r == 1).then(|| t.rem_euclid(m as i128) as u64)
+}
+
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let shift = (n - 1).trailing_zeros();
+    let odd = (n - 1) >> shift;
+    'witness: for a in WITNESSES {
+        let mut x = pow_mod(a, odd, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..shift {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// The smallest prime above `n`, if there is one below 2^64.
+pub fn next_prime(n: u64) -> Option<u64> {
+    (n.checked_add(1)?..=u64::MAX).find(|candidate| is_prime(*candidate))
+}
+
+/// A factor of the odd composite `n` other than 1 and `n`, by Brent's
+/// variant of Pollard's rho with the polynomial `x^2 + c`.
+fn rho(n: u64, c: u64) -> Option<u64> {
+    let f = |x: u64| ((x as u128 * x as u128 + c as u128) % n as u128) as u64;
+    let (mut x, mut y, mut saved) = (2, 2, 2);
+    let (mut product, mut divisor, mut length) = (1, 1, 1);
+    while divisor == 1 {
+        x = y;
+        for _ in 0..length {
+            y = f(y);
+        }
+        let mut done = 0;
+        while done < length && divisor == 1 {
+            saved = y;
+            for _ in 0..BATCH.min(length - done) {
+                y = f(y);
+                product = mul_mod(product, x.abs_diff(y), n);
+            }
+            divisor = gcd(product as u128, n as u128) as u64;
+            done += BATCH;
+        }
+        length *= 2;
+    }
+    if divisor == n {
+        // The batch overshot: retrace it one step at a time.
+        loop {
+            saved = f(saved);
+            divisor = gcd(x.abs_diff(saved) as u128, n as u128) as u64;
+            if divisor > 1 {
+                break;
+            }
+        }
+    }
+    (divisor != n).then_some(divisor)
+}
+
+/// The prime factors of `n` with their multiplicities, in increasing order.
+pub fn factorize(n: u64) -> Vec<(u64, u32)> {
+    let mut primes = vec![];
+    let mut rest = n;
+    for p in WITNESSES {
+        while rest.is_multiple_of(p) {
+            primes.push(p);
+            rest /= p;
+        }
+    }
+    split(rest, &mut primes);
+    primes.sort_unstable();
+    let mut factors: Vec<(u64, u32)> = vec![];
+    for p in primes {
+        match factors.last_mut() {
+            Some((last, count)) if *last == p => *count += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    factors
+}
+
+/// Adds the prime factors of `n`, which has no factors below 41, to
+/// `primes`.
+fn split(n: u64, primes: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime(n) {
+        primes.push(n);
+        return;
+    }
+    let divisor = (1..)
+        .find_map(|c| rho(n, c))
+        .expect("a composite has a factor");
+    split(divisor, primes);
+    split(n / divisor, primes);
+}
+
+pub fn totient(n: u64) -> u64 {
+    factorize(n)
+        .into_iter()
+        .map(|(p, count)| (p - 1) * p.pow(count - 1))
+        .product()
+}
+
+/// The `n`th Fibonacci number and the next, by fast doubling, or `None`
+/// once they overflow.
+pub fn fib(n: u32) -> Option<(i128, i128)> {
+    if n == 0 {
+        return Some((0, 1));
+    }
+    let (a, b) = fib(n / 2)?;
+    // F(2k) = F(k) (2 F(k+1) - F(k)) and F(2k+1) = F(k)^2 + F(k+1)^2.
+    let even = a.checked_mul(b.checked_mul(2)?.checked_sub(a)?)?;
+    let odd = a.checked_mul(a)?.checked_add(b.checked_mul(b)?)?;
+    if n.is_multiple_of(2) {
+        Some((even, odd))
+    } else {
+        Some((odd, even.checked_add(odd)?))
+    }
+}
+
+/// `a oper b` if it is an integer that fits in 128 bits.
+pub fn operate(oper: &Op, a: i128, b: i128) -> Option<i128> {
+    match oper {
+        Op::Add => a.checked_add(b),
+        Op::Sub => a.checked_sub(b),
+        Op::Mult => a.checked_mul(b),
+        Op::Div => (b != 0 && a % b == 0).then(|| a / b),
+        Op::Pow => a.checked_pow(u32::try_from(b).ok()?),
+    }
+}
+
+/// `value` as an exact integer, for the arguments of `name`.
+pub fn whole(name: &str, value: &Value) -> Result<i128, CalcError> {
+    let not_whole = || {
+        CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!("{} needs whole numbers, not {}", name, value),
+        )
+    };
+    match value {
+        Value::Integer(n) => Ok(*n),
+        Value::Decimal(x) => x.to_integer().ok_or_else(not_whole),
+        Value::Factors(factors) => Ok(product(factors) as i128),
+        Value::Number(x) if !x.is_finite() || *x != x.trunc() => Err(not_whole()),
+        Value::Number(x) if x.abs() > MAX_EXACT_FLOAT as f64 => Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!(
+                "{} needs exact integers, and {} is past 2^53 where floats lose them; \
+                 write it in integers as in 2^61 - 1",
+                name, value
+            ),
+        )),
+        Value::Number(x) => Ok(*x as i128),
+        value => Err(CalcError::new(
+            ErrorKind::TypeMismatch,
+            &format!("{} expects integers but got {}", name, value.described()),
+        )),
+    }
+}
+
+/// The number a factorization multiplies out to.
+pub fn product(factors: &[(u64, u32)]) -> u64 {
+    factors.iter().map(|(p, count)| p.pow(*count)).product()
+}
+
+fn too_large(name: &str) -> CalcError {
+    CalcError::new(
+        ErrorKind::InvalidArgument,
+        &format!("{} is too large to compute exactly", name),
+    )
+}
+
+/// An argument `n` of `name` that must be at least `least` and fit in 64
+/// bits.
+fn bounded(name: &str, n: i128, least: u64) -> Result<u64, CalcError> {
+    if n < least as i128 {
+        return Err(CalcError::new(
+            ErrorKind::Domain,
+            &format!("{} needs an integer of at least {}, not {}", name, least, n),
+        ));
+    }
+    u64::try_from(n).map_err(|_| beyond_64_bits(name, n))
+}
+
+fn beyond_64_bits(name: &str, n: i128) -> CalcError {
+    CalcError::new(
+        ErrorKind::InvalidArgument,
+        &format!("{} works on integers below 2^64, not {}", name, n),
+    )
+}
+
+fn gcd_function(args: &[i128]) -> Result<Value, CalcError> {
+    let divisor = args
+        .iter()
+        .fold(0, |divisor, n| gcd(divisor, n.unsigned_abs()));
+    Ok(Value::Integer(divisor as i128))
+}
+
+fn lcm_function(args: &[i128]) -> Result<Value, CalcError> {
+    let mut multiple = 1;
+    for n in args {
+        multiple = lcm(multiple, n.unsigned_abs())
+            .filter(|n| *n <= i128::MAX as u128)
+            .ok_or_else(|| too_large("lcm"))?;
+    }
+    Ok(Value::Integer(multiple as i128))
+}
+
+/// `n` and `k` for `nCr` and `nPr`, which need `n >= 0` and `k >= 0`.
+fn counts(args: &[i128]) -> Result<(u128, u128), CalcError> {
+    let (n, k) = (args[0], args[1]);
+    if n < 0 || k < 0 {
+        return Err(CalcError::new(
+            ErrorKind::Domain,
+            &format!("Cannot choose {} of {} things", k, n),
+        ));
+    }
+    Ok((n as u128, k as u128))
+}
+
+fn choose_function(args: &[i128]) -> Result<Value, CalcError> {
+    let (n, k) = counts(args)?;
+    let count = choose(n, k)
+        .filter(|count| *count <= i128::MAX as u128)
+        .ok_or_else(|| too_large("nCr"))?;
+    Ok(Value::Integer(count as i128))
+}
+
+fn permutations_function(args: &[i128]) -> Result<Value, CalcError> {
+    let (n, k) = counts(args)?;
+    let count = permutations(n, k)
+        .filter(|count| *count <= i128::MAX as u128)
+        .ok_or_else(|| too_large("nPr"))?;
+    Ok(Value::Integer(count as i128))
+}
+
+/// 1 for a prime and 0 otherwise, so that `filter(v, isprime)` keeps the
+/// primes.
+fn is_prime_function(args: &[i128]) -> Result<Value, CalcError> {
+    let n = args[0];
+    let prime = match u64::try_from(n) {
+        Ok(n) => is_prime(n),
+        Err(_) if n < 0 => false,
+        Err(_) => return Err(beyond_64_bits("isprime", n)),
+    };
+    Ok(Value::Integer(i128::from(prime)))
+}
+
+fn factor_function(args: &[i128]) -> Result<Value, CalcError> {
+    let n = bounded("factor", args[0], 1)?;
+    Ok(Value::Factors(factorize(n)))
+}
+
+fn next_prime_function(args: &[i128]) -> Result<Value, CalcError> {
+    let start = u64::try_from(args[0].max(1)).map_err(|_| too_large("nextprime"))?;
+    let prime = next_prime(start).ok_or_else(|| too_large("nextprime"))?;
+    Ok(Value::Integer(prime as i128))
+}
+
+/// `modpow(b, e, m)` is `b^e mod m`; a negative `e` raises the inverse of
+/// `b`.
+fn mod_pow_function(args: &[i128]) -> Result<Value, CalcError> {
+    let m = bounded("modpow", args[2], 1)?;
+    let base = args[0].rem_euclid(m as i128) as u64;
+    let exponent = args[1];
+    let base = if exponent < 0 {
+        mod_inverse(base, m).ok_or_else(|| no_inverse(base, m))?
+    } else {
+        base
+    };
+    let exponent = u64::try_from(exponent.unsigned_abs()).map_err(|_| too_large("modpow"))?;
+    Ok(Value::Integer(pow_mod(base, exponent, m) as i128))
+}
+
+fn mod_inverse_function(args: &[i128]) -> Result<Value, CalcError> {
+    let m = bounded("modinv", args[1], 1)?;
+    let a = args[0].rem_euclid(m as i128) as u64;
+    let inverse = mod_inverse(a, m).ok_or_else(|| no_inverse(a, m))?;
+    Ok(Value::Integer(inverse as i128))
+}
+
+fn no_inverse(a: u64, m: u64) -> CalcError {
+    CalcError::new(
+        ErrorKind::Domain,
+        &format!("{} has no inverse modulo {}", a, m),
+    )
+}
+
+fn totient_function(args: &[i128]) -> Result<Value, CalcError> {
+    let n = bounded("totient", args[0], 1)?;
+    Ok(Value::Integer(totient(n) as i128))
+}
+
+fn fib_function(args: &[i128]) -> Result<Value, CalcError> {
+    let n = bounded("fib", args[0], 0)?;
+    let (fib, _) = u32::try_from(n)
+        .ok()
+        .and_then(fib)
+        .ok_or_else(|| too_large("fib"))?;
+    Ok(Value::Integer(fib))
+}
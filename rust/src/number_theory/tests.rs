@@ -0,0 +1,205 @@
+use crate::{calculator::Calculator, settings::DecimalMode};
+
+use super::*;
+
+fn eval(expression: &str) -> Result<Value, CalcError> {
+    Calculator::new().eval(expression)
+}
+
+fn shown(expression: &str) -> String {
+    eval(expression).unwrap().to_string()
+}
+
+#[test]
+fn divisors_and_multiples() {
+    assert_eq!(gcd(12, 18), 6);
+    assert_eq!(gcd(0, 5), 5);
+    assert_eq!(lcm(4, 6), Some(12));
+    assert_eq!(lcm(u128::MAX, 2), None);
+
+    assert_eq!(eval("gcd(12, 18, 27)"), Ok(Value::Integer(3)));
+    assert_eq!(eval("gcd(-12, 18)"), Ok(Value::Integer(6)));
+    assert_eq!(eval("lcm(4, 6, 10)"), Ok(Value::Integer(60)));
+    assert_eq!(eval("lcm(0, 5)"), Ok(Value::Integer(0)));
+
+    let error = eval("gcd(2.5, 5)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = eval("gcd(12)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+}
+
+#[test]
+fn combinatorics() {
+    assert_eq!(choose(5, 2), Some(10));
+    assert_eq!(choose(2, 5), Some(0));
+    assert_eq!(permutations(5, 2), Some(20));
+    assert_eq!(permutations(5, 0), Some(1));
+
+    assert_eq!(eval("nCr(52, 5)"), Ok(Value::Integer(2598960)));
+    assert_eq!(eval("choose(10, 0)"), Ok(Value::Integer(1)));
+    assert_eq!(eval("nPr(10, 3)"), Ok(Value::Integer(720)));
+    // Past 2^53 the count is exact rather than the nearest float.
+    assert_eq!(shown("nCr(100, 50)"), "100891344545564193334812497256");
+    assert_eq!(shown("nPr(25, 25)"), "15511210043330985984000000");
+    assert_eq!(shown("nCr(50, 25)"), "126410606437752.0");
+
+    let error = eval("nCr(-1, 2)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+    let error = eval("nPr(1000, 500)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn primes() {
+    let primes: Vec<u64> = (0..50).filter(|n| is_prime(*n)).collect();
+    assert_eq!(
+        primes,
+        [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47]
+    );
+    // Strong pseudoprimes to many bases, and the largest 64-bit prime.
+    assert!(!is_prime(3_215_031_751));
+    assert!(!is_prime(3_825_123_056_546_413_051));
+    assert!(is_prime(18_446_744_073_709_551_557));
+    assert_eq!(next_prime(18_446_744_073_709_551_557), None);
+
+    assert_eq!(eval("isprime(97)"), Ok(Value::Integer(1)));
+    assert_eq!(eval("isprime(561)"), Ok(Value::Integer(0)));
+    assert_eq!(eval("isprime(-7)"), Ok(Value::Integer(0)));
+    // Computed in integers: as a float 2^61 - 1 would be 2^61.
+    assert_eq!(eval("isprime(2^61 - 1)"), Ok(Value::Integer(1)));
+    assert_eq!(eval("nextprime(100)"), Ok(Value::Integer(101)));
+    assert_eq!(eval("nextprime(-5)"), Ok(Value::Integer(2)));
+    assert_eq!(shown("nextprime(10^18)"), "1000000000000000003");
+    assert_eq!(
+        eval("filter(1..20, n -> isprime(n))"),
+        Ok(Value::List(
+            [2, 3, 5, 7, 11, 13, 17, 19].map(Value::Integer).to_vec()
+        ))
+    );
+
+    let error = eval("isprime(2^64)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = eval("isprime(2^60 * 1.5)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn factorization() {
+    assert_eq!(factorize(360), vec![(2, 3), (3, 2), (5, 1)]);
+    assert_eq!(factorize(1), vec![]);
+    assert_eq!(factorize(97), vec![(97, 1)]);
+    // Two 32-bit primes, which trial division would take long to find.
+    assert_eq!(
+        factorize(4_294_967_291 * 4_294_967_279),
+        vec![(4_294_967_279, 1), (4_294_967_291, 1)]
+    );
+    assert_eq!(factorize(1 << 63), vec![(2, 63)]);
+
+    assert_eq!(shown("factor(40)"), "2^3 * 5");
+    assert_eq!(shown("factor(1)"), "1");
+    assert_eq!(shown("factor(2^61 - 1)"), "2305843009213693951");
+    assert_eq!(shown("factor(600851475143)"), "71 * 839 * 1471 * 6857");
+    assert_eq!(shown("factor(10^18 + 1)"), "101 * 9901 * 999999000001");
+    assert_eq!(eval("factor(40)").unwrap().type_name(), "factors");
+    // A factorization is its number everywhere else.
+    assert_eq!(eval("factor(40) + 2"), Ok(Value::Number(42.0)));
+    assert_eq!(eval("sqrt(factor(36))"), Ok(Value::Number(6.0)));
+    assert_eq!(eval("gcd(factor(40), 12)"), Ok(Value::Integer(4)));
+
+    let error = eval("factor(0)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+}
+
+#[test]
+fn modular_arithmetic() {
+    assert_eq!(pow_mod(4, 13, 497), 445);
+    assert_eq!(pow_mod(5, 0, 1), 0);
+    assert_eq!(mod_inverse(3, 11), Some(4));
+    assert_eq!(mod_inverse(6, 9), None);
+
+    assert_eq!(eval("modpow(2, 10, 1000)"), Ok(Value::Integer(24)));
+    assert_eq!(eval("modpow(-2, 3, 5)"), Ok(Value::Integer(2)));
+    assert_eq!(eval("modpow(3, -1, 11)"), Ok(Value::Integer(4)));
+    assert_eq!(eval("modpow(2, 2^61 - 2, 2^61 - 1)"), Ok(Value::Integer(1)));
+    assert_eq!(eval("modinv(17, 3120)"), Ok(Value::Integer(2753)));
+    assert_eq!(eval("modinv(-3, 11)"), Ok(Value::Integer(7)));
+
+    let error = eval("modinv(6, 9)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+    assert_eq!(error.message, "6 has no inverse modulo 9");
+    let error = eval("modpow(2, 3, 0)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+}
+
+#[test]
+fn totients_and_fibonacci() {
+    assert_eq!(totient(1), 1);
+    assert_eq!(totient(36), 12);
+    assert_eq!(totient(97), 96);
+    assert_eq!(eval("totient(3120)"), Ok(Value::Integer(768)));
+
+    assert_eq!(fib(0), Some((0, 1)));
+    assert_eq!(fib(10), Some((55, 89)));
+    assert_eq!(eval("fib(50)"), Ok(Value::Integer(12586269025)));
+    assert_eq!(shown("fib(100)"), "354224848179261915075");
+    assert_eq!(eval("fib(100)").unwrap().type_name(), "integer");
+
+    let error = eval("fib(-1)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+    let error = eval("fib(1000)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn decimal_mode_keeps_integers_exact() {
+    let mut calculator = Calculator::new();
+    calculator.settings_mut().decimal = Some(DecimalMode::default());
+    assert_eq!(calculator.eval("gcd(12, 18)"), Ok(Value::Integer(6)));
+    let value = calculator.eval("gcd(12, 18) / 4").unwrap();
+    assert_eq!(value.type_name(), "decimal");
+    assert_eq!(value.to_string(), "1.5");
+    assert_eq!(
+        calculator.eval("fib(100) * 10 + 1").unwrap().to_string(),
+        "3542248481792619150751"
+    );
+}
+
+#[test]
+fn results_are_exact_integers() {
+    // Integers stay exact among themselves, and become floats otherwise.
+    assert_eq!(shown("fib(100) + fib(101)"), "927372692193078999176");
+    assert_eq!(eval("fib(10) / fib(5)"), Ok(Value::Integer(11)));
+    assert_eq!(eval("fib(10) / nCr(3, 1)"), Ok(Value::Number(55.0 / 3.0)));
+    assert_eq!(eval("nCr(5, 2) * 1.5"), Ok(Value::Number(15.0)));
+    assert_eq!(shown("(fib(10)) USD"), "55.00 USD");
+    assert_eq!(shown("[fib(10), 2.5]"), "[55.0, 2.5]");
+    assert_eq!(shown("fib(100) + 1"), "354224848179261915076");
+    assert_eq!(shown("9007199254740993"), "9007199254740993");
+
+    // Literals are exact anywhere, so a result can be tested later.
+    let mut calculator = Calculator::new();
+    calculator.eval("2^61 - 1").unwrap();
+    assert_eq!(calculator.eval("isprime(x0)"), Ok(Value::Integer(1)));
+}
+
+#[test]
+fn functions_are_values() {
+    assert_eq!(
+        eval("map(1..6, isprime)"),
+        Ok(Value::List([0, 1, 1, 0, 1, 0].map(Value::Integer).to_vec()))
+    );
+    assert_eq!(
+        eval("filter(1..20, isprime)"),
+        eval("filter(1..20, n -> isprime(n))")
+    );
+    assert_eq!(eval("reduce([12, 18, 27], gcd)"), Ok(Value::Integer(3)));
+    assert_eq!(
+        eval("map([10, 20], fib)").unwrap().to_string(),
+        "[55.0, 6765.0]"
+    );
+
+    let error = eval("map([2.5], isprime)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = eval("map([1], modpow)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::ArityMismatch);
+}
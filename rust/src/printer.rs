@@ -0,0 +1,210 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    ast::AST,
+    tokens::{Num, Op, Token},
+};
+
+/// Binding strength of the operations as the parser sees them.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub(crate) enum Precedence {
+    /// Ranges and lambdas, which only appear where a whole expression can.
+    Range,
+    /// Comparisons, which do not chain.
+    Comparison,
+    Sum,
+    Product,
+    /// Negations and negative literals.
+    Negation,
+    Power,
+    /// Literals, names and calls.
+    Atom,
+}
+
+/// Prints `tree` as canonical infix with single spaces around operators and
+/// only the parentheses needed to parse back to the same tree.
+///
+/// `+`, `-`, `*` and `/` are left-associative, so a right operand of the
+/// same precedence keeps its parentheses: `a - (b - c)` and `a + (b + c)` both
+/// stay as they are. `^` is right-associative and binds tighter than unary
+/// minus, so `-x ^ 2` needs none. `-1 * x` is printed as the `-x` it was
+/// parsed from.
+pub fn infix(tree: &AST) -> String {
+    let (first, links) = tree.chain();
+    if !links.is_empty() {
+        return chain(first, &links);
+    }
+    match (tree.value(), tree.left(), tree.right()) {
+        (Token::Operator(Op::Mult), Some(_), Some(right)) if is_negation(tree) => {
+            format!("-{}", operand(right, Precedence::Negation, false))
+        }
+        (Token::Operator(oper), Some(left), Some(right)) => {
+            let precedence = precedence(tree);
+            format!(
+                "{} {} {}",
+                operand(left, precedence, false),
+                Token::Operator(oper.clone()),
+                operand(right, precedence, true)
+            )
+        }
+        (Token::Comparison(cmp), Some(left), Some(right)) => format!(
+            "{} {} {}",
+            operand(left, Precedence::Comparison, true),
+            cmp,
+            operand(right, Precedence::Comparison, true)
+        ),
+        (Token::Equals, Some(left), Some(right)) => {
+            format!("{} = {}", infix(left), infix(right))
+        }
+        (Token::Degree, Some(operand), _) => {
+            format!("{}°", self::operand(operand, Precedence::Atom, false))
+        }
+        (Token::Unit(unit), Some(number), _) => format!("{} {}", infix(number), unit),
+        (Token::Conversion(unit), Some(operand), _) => {
+            format!("{} to {}", infix(operand), unit)
+        }
+        (Token::Currency(currency), Some(amount), _) => {
+            format!(
+                "{} {}",
+                self::operand(amount, Precedence::Negation, false),
+                currency
+            )
+        }
+        (Token::Exchange(currency), Some(operand), _) => {
+            format!("{} to {}", infix(operand), currency)
+        }
+        // The parts of `3 d 4 h` are summed, or subtracted after a negative
+        // first part, but written side by side.
+        (Token::Duration, Some(parts), _) => {
+            let mut parts = parts;
+            let mut written = vec![];
+            while let (Token::Operator(Op::Add | Op::Sub), Some(rest), Some(part)) =
+                (parts.value(), parts.left(), parts.right())
+            {
+                written.push(infix(part));
+                parts = rest;
+            }
+            written.push(infix(parts));
+            written.reverse();
+            written.join(" ")
+        }
+        (Token::Function(name), _, _) => {
+            let args: Vec<String> = tree.arguments().into_iter().map(infix).collect();
+            format!("{}({})", name, args.join(", "))
+        }
+        (Token::List, _, _) => {
+            let items: Vec<String> = tree.arguments().into_iter().map(infix).collect();
+            format!("[{}]", items.join(", "))
+        }
+        (Token::Index, Some(list), Some(index)) => {
+            format!(
+                "{}[{}]",
+                operand(list, Precedence::Atom, false),
+                infix(index)
+            )
+        }
+        (Token::Range, Some(from), Some(to)) => format!(
+            "{}..{}",
+            operand(from, Precedence::Sum, false),
+            operand(to, Precedence::Sum, false)
+        ),
+        (Token::Lambda(params), Some(body), _) => match params.as_slice() {
+            [param] => format!("{} -> {}", param, infix(body)),
+            params => format!("({}) -> {}", params.join(", "), infix(body)),
+        },
+        (Token::Number(num), _, _) => number(num),
+        (token, _, _) => token.to_string(),
+    }
+}
+
+/// A chain such as `a + b * c - d`, written from its first operand in a loop
+/// rather than by recursing once per operator.
+fn chain(first: &AST, links: &[(&AST, &Op, &AST)]) -> String {
+    let mut text = infix(first);
+    let mut left = first;
+    for (link, oper, right) in links {
+        let precedence = precedence(link);
+        if needs_parens(left, precedence, false) {
+            text = format!("({})", text);
+        }
+        text.push_str(&format!(
+            " {} {}",
+            Token::Operator((*oper).clone()),
+            operand(right, precedence, true)
+        ));
+        left = link;
+    }
+    text
+}
+
+fn operand(tree: &AST, parent: Precedence, right: bool) -> String {
+    if needs_parens(tree, parent, right) {
+        format!("({})", infix(tree))
+    } else {
+        infix(tree)
+    }
+}
+
+/// Whether `tree` must be parenthesized as the left or right operand of an
+/// operation with precedence `parent`.
+pub(crate) fn needs_parens(tree: &AST, parent: Precedence, right: bool) -> bool {
+    let precedence = precedence(tree);
+    match parent {
+        Precedence::Power if right => precedence < Precedence::Negation,
+        Precedence::Power => precedence <= Precedence::Power,
+        Precedence::Negation => precedence < Precedence::Negation || is_negation(tree),
+        _ => precedence < parent || (right && precedence == parent),
+    }
+}
+
+pub(crate) fn precedence(tree: &AST) -> Precedence {
+    match tree.value() {
+        _ if is_negation(tree) => Precedence::Negation,
+        Token::Number(Num::Integer(x)) if *x < 0 => Precedence::Negation,
+        Token::Number(Num::Float(x)) if x.is_sign_negative() => Precedence::Negation,
+        Token::Number(Num::Decimal(x)) if x.is_negative() => Precedence::Negation,
+        Token::Operator(Op::Add | Op::Sub) => Precedence::Sum,
+        // `5 km` needs parentheses wherever a product would.
+        Token::Operator(Op::Mult | Op::Div)
+        | Token::Unit(_)
+        | Token::Currency(_)
+        | Token::Duration => Precedence::Product,
+        Token::Operator(Op::Pow) => Precedence::Power,
+        Token::Comparison(_) => Precedence::Comparison,
+        Token::Range | Token::Lambda(_) => Precedence::Range,
+        _ => Precedence::Atom,
+    }
+}
+
+/// Whole floats and decimals keep a `.0` so they parse back as such rather
+/// than as integers.
+pub(crate) fn number(num: &Num) -> String {
+    match num {
+        Num::Float(x) if x.is_finite() && *x == x.trunc() => format!("{}.0", x),
+        Num::Float(x) => format!("{}", x),
+        Num::Integer(x) => format!("{}", x),
+        Num::Decimal(x) if x.scale() == 0 => format!("{}.0", x),
+        Num::Decimal(x) => format!("{}", x),
+    }
+}
+
+/// Whether `tree` is a negation the parser wrote as `-1 * x`.
+pub(crate) fn is_negation(tree: &AST) -> bool {
+    match (tree.value(), tree.left(), tree.right()) {
+        (Token::Operator(Op::Mult), Some(left), Some(right)) => {
+            left.value() == &Token::Number(Num::Integer(-1))
+                && !matches!(right.value(), Token::Number(_))
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn is_minus_one(tree: &AST) -> bool {
+    match tree.value() {
+        Token::Number(Num::Integer(x)) => *x == -1,
+        Token::Number(Num::Float(x)) => *x == -1.0,
+        Token::Number(Num::Decimal(x)) => x.to_integer() == Some(-1),
+        _ => false,
+    }
+}
@@ -0,0 +1,85 @@
+use crate::parse_expression;
+
+use super::*;
+
+fn format(expression: &str) -> String {
+    infix(&parse_expression(expression).unwrap())
+}
+
+#[test]
+fn minimal_parentheses() {
+    assert_eq!(format("((1+2))*3"), "(1 + 2) * 3");
+    assert_eq!(format("1+(2*3)"), "1 + 2 * 3");
+    assert_eq!(format("(a-b)-c"), "a - b - c");
+    assert_eq!(format("a-(b-c)"), "a - (b - c)");
+    assert_eq!(format("a/(b*c)"), "a / (b * c)");
+    assert_eq!(format("(a*b)/c"), "a * b / c");
+    assert_eq!(format("a+(b+c)"), "a + (b + c)");
+}
+
+#[test]
+fn normalizes_spacing_and_implied_multiplication() {
+    assert_eq!(format("  2(3+4)"), "2 * (3 + 4)");
+    assert_eq!(format("f( a ,b*2 )"), "f(a, b * 2)");
+    assert_eq!(format("x0(2)"), "x0(2)");
+    assert_eq!(format("(x)(y)"), "x * y");
+}
+
+#[test]
+fn negation() {
+    assert_eq!(format("-x * y"), "-x * y");
+    assert_eq!(format("-(x * y)"), "-(x * y)");
+    assert_eq!(format("-(-x)"), "-(-x)");
+    assert_eq!(format("a - -5"), "a - -5");
+    assert_eq!(format("-(5)"), "-1 * 5");
+    assert_eq!(format("-f(2)"), "-f(2)");
+    assert_eq!(format("3.0 * -1.5"), "3.0 * -1.5");
+}
+
+#[test]
+fn powers() {
+    assert_eq!(format("2^3^2"), "2 ^ 3 ^ 2");
+    assert_eq!(format("(2^3)^2"), "(2 ^ 3) ^ 2");
+    assert_eq!(format("-x^2"), "-x ^ 2");
+    assert_eq!(format("(-x)^2"), "(-x) ^ 2");
+    assert_eq!(format("(-2)^2"), "(-2) ^ 2");
+    assert_eq!(format("2^-x"), "2 ^ -x");
+    assert_eq!(format("2^(a+b)*c"), "2 ^ (a + b) * c");
+}
+
+#[test]
+fn round_trips() {
+    for expression in [
+        "1 + 2 * 3 - 4 / 5",
+        "((1 + 2) * (3 - 4)) / (5 / (6 * 7))",
+        "-(a + b) * -c - -(d / e)",
+        "f(g(1, -x), (2 + 3) * 4) / h()",
+        "2(3) + (4)5 - x(y - 1)",
+        "1 - (2 - (3 - (4 - 5)))",
+        "0.5 * 10.0 - -1 * x",
+        "19.990 + -0.125 * 2500.00 - 0.1 ^ -2",
+        "5 / -(267)",
+        "-2 ^ 2 + (-2) ^ -2 ^ -x",
+        "a ^ b ^ (c ^ d) * (a ^ b) ^ c",
+        "-30° + (-30)° * (x + 1)° ^ 2°",
+        "-5 km + (2 m/s) ^ 2 * 3 J/(mol*K) / (4.5 kg*m^2) to ft",
+        "price EUR * 3 - (2 + 3) USD / -4 USD to GBP",
+        "2026-12-25 - today() + 3 d 4 h * 2 - -1 h 30 min",
+        "(2026-10-18 14:30 + 90 min - 9:05) / (1 d 12 h) ^ 2",
+        "[1, -2 * x, []] * (1..n + 1)[-1] - (-v)[0] ^ 2",
+        "reduce(map(0..9, k -> k ^ 2), (acc, k) -> acc + k, 1)",
+        "filter(v, x -> -x + 1 >= 2 * y) * ((a < b) == (c != d))",
+    ] {
+        let tree = parse_expression(expression).unwrap();
+        let printed = infix(&tree);
+        let reparsed = parse_expression(&printed).unwrap();
+        assert!(
+            tree.equivalent(&reparsed),
+            "{} printed as {} parses as {}",
+            expression,
+            printed,
+            reparsed
+        );
+        assert_eq!(infix(&reparsed), printed);
+    }
+}
@@ -0,0 +1,620 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    ast::AST,
+    printer::{is_negation, needs_parens, number, Precedence},
+    tokens::{Cmp, Op, Token},
+    units::Unit,
+};
+
+/// Markup languages expressions can be rendered to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Markup {
+    Latex,
+    MathMl,
+}
+
+/// Renders `tree` as LaTeX math, e.g. `\frac{a}{b} + x^{2}`.
+pub fn latex(tree: &AST) -> String {
+    walk(tree, &Latex)
+}
+
+/// Renders `tree` as a presentation MathML `<math>` element.
+pub fn mathml(tree: &AST) -> String {
+    math_element(walk(tree, &MathMl))
+}
+
+pub fn render(tree: &AST, markup: Markup) -> String {
+    match markup {
+        Markup::Latex => latex(tree),
+        Markup::MathMl => mathml(tree),
+    }
+}
+
+/// Renders `tree = value`, where `value` is a formatted number such as
+/// `0.5` or `1.5e3`.
+pub fn render_equation(tree: &AST, value: &str, markup: Markup) -> String {
+    render_result(tree, value, None, markup)
+}
+
+/// Like `render_equation`, for a value that may have a unit.
+pub fn render_result(tree: &AST, value: &str, unit: Option<&Unit>, markup: Markup) -> String {
+    let value = |notation: &dyn Notation| match unit {
+        Some(unit) => notation.quantity(notation.number(value), self::unit(unit, notation)),
+        None => notation.number(value),
+    };
+    render_value(tree, &value, markup)
+}
+
+/// Like `render_result`, for an amount of money such as `120.00 USD`.
+pub fn render_money(tree: &AST, amount: &str, currency: &str, markup: Markup) -> String {
+    let value = |notation: &dyn Notation| {
+        notation.quantity(notation.number(amount), notation.unit_name(currency))
+    };
+    render_value(tree, &value, markup)
+}
+
+/// Like `render_result`, for a value shown as text such as a date.
+pub fn render_text(tree: &AST, text: &str, markup: Markup) -> String {
+    render_value(tree, &|notation: &dyn Notation| notation.text(text), markup)
+}
+
+fn render_value(tree: &AST, value: &dyn Fn(&dyn Notation) -> String, markup: Markup) -> String {
+    match markup {
+        Markup::Latex => format!("{} = {}", latex(tree), value(&Latex)),
+        Markup::MathMl => math_element(format!(
+            "<mrow>{}<mo>=</mo>{}</mrow>",
+            walk(tree, &MathMl),
+            value(&MathMl)
+        )),
+    }
+}
+
+fn math_element(content: String) -> String {
+    format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{}</math>",
+        content
+    )
+}
+
+/// The constructs a markup language needs to express a tree.
+trait Notation {
+    fn number(&self, text: &str) -> String;
+    fn name(&self, name: &str) -> String;
+    /// `+`, `-` and `*` between two rendered operands.
+    fn operator(&self, oper: &Op, left: String, right: String) -> String;
+    fn comparison(&self, cmp: &Cmp, left: String, right: String) -> String;
+    fn equation(&self, left: String, right: String) -> String;
+    fn degrees(&self, angle: String) -> String;
+    fn unit_name(&self, symbol: &str) -> String;
+    /// Text shown as written, such as the date `2026-10-18`.
+    fn text(&self, text: &str) -> String;
+    /// A unit divided by another, as in `m/s`.
+    fn per(&self, numerator: String, denominator: String) -> String;
+    fn quantity(&self, number: String, unit: String) -> String;
+    fn conversion(&self, operand: String, unit: String) -> String;
+    fn negate(&self, operand: String) -> String;
+    fn fraction(&self, numerator: String, denominator: String) -> String;
+    fn power(&self, base: String, exponent: String) -> String;
+    fn parens(&self, inner: String) -> String;
+    fn call(&self, name: &str, args: Vec<String>) -> String;
+    fn list(&self, items: Vec<String>) -> String;
+    /// The element of a list at an index, drawn as a subscript.
+    fn index(&self, list: String, index: String) -> String;
+    fn range(&self, from: String, to: String) -> String;
+    fn lambda(&self, params: &[String], body: String) -> String;
+}
+
+fn walk(tree: &AST, notation: &dyn Notation) -> String {
+    let (first, links) = tree.chain();
+    if !links.is_empty() {
+        return chain(first, &links, notation);
+    }
+    match (tree.value(), tree.left(), tree.right()) {
+        (_, _, Some(right)) if is_negation(tree) => {
+            notation.negate(operand(right, Precedence::Negation, false, notation))
+        }
+        (Token::Operator(Op::Div), Some(left), Some(right)) => {
+            notation.fraction(walk(left, notation), walk(right, notation))
+        }
+        (Token::Operator(Op::Pow), Some(left), Some(right)) => notation.power(
+            operand(left, Precedence::Power, false, notation),
+            walk(right, notation),
+        ),
+        (Token::Operator(oper), Some(left), Some(right)) => {
+            let precedence = crate::printer::precedence(tree);
+            notation.operator(
+                oper,
+                operand(left, precedence, false, notation),
+                operand(right, precedence, true, notation),
+            )
+        }
+        (Token::Comparison(cmp), Some(left), Some(right)) => notation.comparison(
+            cmp,
+            operand(left, Precedence::Comparison, true, notation),
+            operand(right, Precedence::Comparison, true, notation),
+        ),
+        (Token::Equals, Some(left), Some(right)) => {
+            notation.equation(walk(left, notation), walk(right, notation))
+        }
+        (Token::Degree, Some(angle), _) => {
+            notation.degrees(operand(angle, Precedence::Atom, false, notation))
+        }
+        (Token::Unit(unit), Some(number), _) => {
+            notation.quantity(walk(number, notation), self::unit(unit, notation))
+        }
+        (Token::Conversion(unit), Some(operand), _) => {
+            notation.conversion(walk(operand, notation), self::unit(unit, notation))
+        }
+        (Token::Currency(currency), Some(amount), _) => notation.quantity(
+            operand(amount, Precedence::Negation, false, notation),
+            notation.unit_name(currency),
+        ),
+        (Token::Exchange(currency), Some(operand), _) => {
+            notation.conversion(walk(operand, notation), notation.unit_name(currency))
+        }
+        (Token::Date(text), _, _) => notation.text(text),
+        // A duration is the sum of its parts.
+        (Token::Duration, Some(parts), _) => walk(parts, notation),
+        (Token::Function(name), _, _) => {
+            let args = tree
+                .arguments()
+                .into_iter()
+                .map(|arg| walk(arg, notation))
+                .collect();
+            notation.call(name, args)
+        }
+        (Token::List, _, _) => {
+            let items = tree
+                .arguments()
+                .into_iter()
+                .map(|item| walk(item, notation))
+                .collect();
+            notation.list(items)
+        }
+        (Token::Index, Some(list), Some(index)) => notation.index(
+            operand(list, Precedence::Atom, false, notation),
+            walk(index, notation),
+        ),
+        (Token::Range, Some(from), Some(to)) => notation.range(
+            operand(from, Precedence::Sum, false, notation),
+            operand(to, Precedence::Sum, false, notation),
+        ),
+        (Token::Lambda(params), Some(body), _) => notation.lambda(params, walk(body, notation)),
+        (Token::Number(num), _, _) => notation.number(&number(num)),
+        (Token::Variable(name), _, _) => notation.name(name),
+        (token, _, _) => notation.name(&token.to_string()),
+    }
+}
+
+/// Whether `tree` needs parentheses as an operand of `parent`. A fraction
+/// is set apart by its bar everywhere but in a base.
+fn parenthesized(tree: &AST, parent: Precedence, right: bool) -> bool {
+    match tree.value() {
+        Token::Operator(Op::Div) => parent == Precedence::Power,
+        _ => needs_parens(tree, parent, right),
+    }
+}
+
+/// A chain such as `a + b / c - d`, rendered from its first operand in a
+/// loop rather than by recursing once per operator.
+fn chain(first: &AST, links: &[(&AST, &Op, &AST)], notation: &dyn Notation) -> String {
+    let mut rendered = walk(first, notation);
+    let mut left = first;
+    for (link, oper, right) in links {
+        rendered = match oper {
+            Op::Div => notation.fraction(rendered, walk(right, notation)),
+            oper => {
+                let precedence = crate::printer::precedence(link);
+                let left = match parenthesized(left, precedence, false) {
+                    true => notation.parens(rendered),
+                    false => rendered,
+                };
+                notation.operator(oper, left, operand(right, precedence, true, notation))
+            }
+        };
+        left = link;
+    }
+    rendered
+}
+
+/// Renders the factors of `unit` with positive powers over those with
+/// negative powers, as in `kg·m/s²`.
+fn unit(unit: &Unit, notation: &dyn Notation) -> String {
+    let part = |sign: i32| {
+        let factors: Vec<String> = unit
+            .factors()
+            .iter()
+            .filter(|(_, power)| power.signum() == sign)
+            .map(|(symbol, power)| match power.abs() {
+                1 => notation.unit_name(symbol),
+                power => notation.power(
+                    notation.unit_name(symbol),
+                    notation.number(&power.to_string()),
+                ),
+            })
+            .collect();
+        let count = factors.len();
+        let product = factors
+            .into_iter()
+            .reduce(|left, right| notation.operator(&Op::Mult, left, right));
+        (product, count)
+    };
+    match (part(1), part(-1)) {
+        ((Some(above), _), (None, _)) => above,
+        ((above, _), (Some(below), count)) => {
+            let above = above.unwrap_or_else(|| notation.number("1"));
+            let below = if count > 1 {
+                notation.parens(below)
+            } else {
+                below
+            };
+            notation.per(above, below)
+        }
+        ((None, _), (None, _)) => String::new(),
+    }
+}
+
+/// Like the infix printer, except that a fraction is drawn as a block and
+/// only needs parentheses as the base of a power.
+fn operand(tree: &AST, parent: Precedence, right: bool, notation: &dyn Notation) -> String {
+    let rendered = walk(tree, notation);
+    if parenthesized(tree, parent, right) {
+        notation.parens(rendered)
+    } else {
+        rendered
+    }
+}
+
+const GREEK: &[(&str, char)] = &[
+    ("alpha", 'α'),
+    ("beta", 'β'),
+    ("gamma", 'γ'),
+    ("delta", 'δ'),
+    ("epsilon", 'ε'),
+    ("zeta", 'ζ'),
+    ("eta", 'η'),
+    ("theta", 'θ'),
+    ("kappa", 'κ'),
+    ("lambda", 'λ'),
+    ("mu", 'μ'),
+    ("nu", 'ν'),
+    ("xi", 'ξ'),
+    ("pi", 'π'),
+    ("rho", 'ρ'),
+    ("sigma", 'σ'),
+    ("tau", 'τ'),
+    ("phi", 'φ'),
+    ("chi", 'χ'),
+    ("psi", 'ψ'),
+    ("omega", 'ω'),
+    ("Gamma", 'Γ'),
+    ("Delta", 'Δ'),
+    ("Theta", 'Θ'),
+    ("Lambda", 'Λ'),
+    ("Pi", 'Π'),
+    ("Sigma", 'Σ'),
+    ("Phi", 'Φ'),
+    ("Omega", 'Ω'),
+];
+
+/// Splits a name into its base and subscript: `x0` is `x` with `0`, `k_B`
+/// is `k` with `B`.
+fn subscript(name: &str) -> Option<(&str, &str)> {
+    if let Some((base, sub)) = name.split_once('_') {
+        if !base.is_empty() && !sub.is_empty() {
+            return Some((base, sub));
+        }
+    }
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 && digits < name.len() {
+        Some(name.split_at(name.len() - digits))
+    } else {
+        None
+    }
+}
+
+/// How a scientific number like `1.5e3` is split into mantissa and exponent.
+fn scientific(text: &str) -> Option<(&str, &str)> {
+    text.split_once('e')
+}
+
+struct Latex;
+
+impl Latex {
+    fn name_part(name: &str) -> String {
+        if GREEK.iter().any(|(greek, _)| *greek == name) {
+            format!("\\{}", name)
+        } else if name.len() == 1 || name.chars().all(|c| c.is_ascii_digit()) {
+            name.to_owned()
+        } else {
+            format!("\\mathrm{{{}}}", name.replace('_', "\\_"))
+        }
+    }
+}
+
+impl Notation for Latex {
+    fn number(&self, text: &str) -> String {
+        match text {
+            "inf" => "\\infty".to_owned(),
+            "-inf" => "-\\infty".to_owned(),
+            "NaN" => "\\mathrm{NaN}".to_owned(),
+            _ => match scientific(text) {
+                Some((mantissa, exponent)) => {
+                    format!("{} \\times 10^{{{}}}", mantissa, exponent)
+                }
+                None => text.to_owned(),
+            },
+        }
+    }
+
+    fn name(&self, name: &str) -> String {
+        match subscript(name) {
+            Some((base, sub)) => {
+                format!("{}_{{{}}}", Self::name_part(base), Self::name_part(sub))
+            }
+            None => Self::name_part(name),
+        }
+    }
+
+    fn operator(&self, oper: &Op, left: String, right: String) -> String {
+        let symbol = match oper {
+            Op::Add => "+",
+            Op::Sub => "-",
+            _ => "\\cdot",
+        };
+        format!("{} {} {}", left, symbol, right)
+    }
+
+    fn comparison(&self, cmp: &Cmp, left: String, right: String) -> String {
+        let symbol = match cmp {
+            Cmp::Less => "<",
+            Cmp::LessEqual => "\\le",
+            Cmp::Greater => ">",
+            Cmp::GreaterEqual => "\\ge",
+            Cmp::Equal => "=",
+            Cmp::NotEqual => "\\ne",
+        };
+        format!("{} {} {}", left, symbol, right)
+    }
+
+    fn equation(&self, left: String, right: String) -> String {
+        format!("{} = {}", left, right)
+    }
+
+    fn degrees(&self, angle: String) -> String {
+        format!("{}^{{\\circ}}", angle)
+    }
+
+    fn unit_name(&self, symbol: &str) -> String {
+        format!("\\mathrm{{{}}}", symbol)
+    }
+
+    fn text(&self, text: &str) -> String {
+        format!("\\text{{{}}}", text)
+    }
+
+    fn per(&self, numerator: String, denominator: String) -> String {
+        format!("{}/{}", numerator, denominator)
+    }
+
+    fn quantity(&self, number: String, unit: String) -> String {
+        format!("{}\\,{}", number, unit)
+    }
+
+    fn conversion(&self, operand: String, unit: String) -> String {
+        format!("{} \\to {}", operand, unit)
+    }
+
+    fn negate(&self, operand: String) -> String {
+        format!("-{}", operand)
+    }
+
+    fn fraction(&self, numerator: String, denominator: String) -> String {
+        format!("\\frac{{{}}}{{{}}}", numerator, denominator)
+    }
+
+    fn power(&self, base: String, exponent: String) -> String {
+        format!("{}^{{{}}}", base, exponent)
+    }
+
+    fn parens(&self, inner: String) -> String {
+        format!("\\left({}\\right)", inner)
+    }
+
+    fn call(&self, name: &str, args: Vec<String>) -> String {
+        match (name, args.as_slice()) {
+            ("sqrt", [arg]) => format!("\\sqrt{{{}}}", arg),
+            ("cbrt", [arg]) => format!("\\sqrt[3]{{{}}}", arg),
+            ("abs", [arg]) => format!("\\left|{}\\right|", arg),
+            ("floor", [arg]) => format!("\\left\\lfloor {}\\right\\rfloor", arg),
+            ("ceil", [arg]) => format!("\\left\\lceil {}\\right\\rceil", arg),
+            _ => {
+                let operator = match name {
+                    "sin" | "cos" | "tan" | "sinh" | "cosh" | "tanh" | "ln" | "log" | "exp"
+                    | "min" | "max" => format!("\\{}", name),
+                    "asin" | "acos" | "atan" => format!("\\arc{}", &name[1..]),
+                    _ => format!("\\operatorname{{{}}}", name.replace('_', "\\_")),
+                };
+                format!("{}{}", operator, self.parens(args.join(", ")))
+            }
+        }
+    }
+
+    fn list(&self, items: Vec<String>) -> String {
+        format!("\\left[{}\\right]", items.join(", "))
+    }
+
+    fn index(&self, list: String, index: String) -> String {
+        format!("{{{}}}_{{{}}}", list, index)
+    }
+
+    fn range(&self, from: String, to: String) -> String {
+        format!("{} \\ldots {}", from, to)
+    }
+
+    fn lambda(&self, params: &[String], body: String) -> String {
+        let params: Vec<String> = params.iter().map(|param| self.name(param)).collect();
+        let params = match params.as_slice() {
+            [param] => param.clone(),
+            params => self.parens(params.join(", ")),
+        };
+        format!("{} \\mapsto {}", params, body)
+    }
+}
+
+struct MathMl;
+
+impl MathMl {
+    fn identifier(name: &str) -> String {
+        match GREEK.iter().find(|(greek, _)| *greek == name) {
+            Some((_, letter)) => format!("<mi>{}</mi>", letter),
+            None if name.chars().all(|c| c.is_ascii_digit()) => format!("<mn>{}</mn>", name),
+            None => format!("<mi>{}</mi>", name),
+        }
+    }
+
+    fn row(items: &[String]) -> String {
+        format!("<mrow>{}</mrow>", items.concat())
+    }
+}
+
+impl Notation for MathMl {
+    fn number(&self, text: &str) -> String {
+        if let Some(magnitude) = text.strip_prefix('-') {
+            return Self::row(&["<mo>&#x2212;</mo>".to_owned(), self.number(magnitude)]);
+        }
+        match (text, scientific(text)) {
+            ("inf", _) => "<mi>&#x221E;</mi>".to_owned(),
+            ("NaN", _) => "<mi>NaN</mi>".to_owned(),
+            (_, Some((mantissa, exponent))) => Self::row(&[
+                format!("<mn>{}</mn>", mantissa),
+                "<mo>&#x00D7;</mo>".to_owned(),
+                self.power("<mn>10</mn>".to_owned(), self.number(exponent)),
+            ]),
+            (_, None) => format!("<mn>{}</mn>", text),
+        }
+    }
+
+    fn name(&self, name: &str) -> String {
+        match subscript(name) {
+            Some((base, sub)) => format!(
+                "<msub>{}{}</msub>",
+                Self::identifier(base),
+                Self::identifier(sub)
+            ),
+            None => Self::identifier(name),
+        }
+    }
+
+    fn operator(&self, oper: &Op, left: String, right: String) -> String {
+        let symbol = match oper {
+            Op::Add => "+",
+            Op::Sub => "&#x2212;",
+            _ => "&#x22C5;",
+        };
+        Self::row(&[left, format!("<mo>{}</mo>", symbol), right])
+    }
+
+    fn comparison(&self, cmp: &Cmp, left: String, right: String) -> String {
+        let symbol = match cmp {
+            Cmp::Less => "&lt;",
+            Cmp::LessEqual => "&#x2264;",
+            Cmp::Greater => "&gt;",
+            Cmp::GreaterEqual => "&#x2265;",
+            Cmp::Equal => "=",
+            Cmp::NotEqual => "&#x2260;",
+        };
+        Self::row(&[left, format!("<mo>{}</mo>", symbol), right])
+    }
+
+    fn equation(&self, left: String, right: String) -> String {
+        Self::row(&[left, "<mo>=</mo>".to_owned(), right])
+    }
+
+    fn degrees(&self, angle: String) -> String {
+        format!("<msup>{}<mo>&#x00B0;</mo></msup>", angle)
+    }
+
+    fn unit_name(&self, symbol: &str) -> String {
+        format!("<mi mathvariant=\"normal\">{}</mi>", symbol)
+    }
+
+    fn text(&self, text: &str) -> String {
+        format!("<mtext>{}</mtext>", text)
+    }
+
+    fn per(&self, numerator: String, denominator: String) -> String {
+        Self::row(&[numerator, "<mo>/</mo>".to_owned(), denominator])
+    }
+
+    fn quantity(&self, number: String, unit: String) -> String {
+        Self::row(&[number, "<mo>&#x2062;</mo>".to_owned(), unit])
+    }
+
+    fn conversion(&self, operand: String, unit: String) -> String {
+        Self::row(&[operand, "<mo>&#x2192;</mo>".to_owned(), unit])
+    }
+
+    fn negate(&self, operand: String) -> String {
+        Self::row(&["<mo>&#x2212;</mo>".to_owned(), operand])
+    }
+
+    fn fraction(&self, numerator: String, denominator: String) -> String {
+        format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+    }
+
+    fn power(&self, base: String, exponent: String) -> String {
+        format!("<msup>{}{}</msup>", base, exponent)
+    }
+
+    fn parens(&self, inner: String) -> String {
+        Self::row(&["<mo>(</mo>".to_owned(), inner, "<mo>)</mo>".to_owned()])
+    }
+
+    fn call(&self, name: &str, args: Vec<String>) -> String {
+        match (name, args.as_slice()) {
+            ("sqrt", [arg]) => format!("<msqrt>{}</msqrt>", arg),
+            ("cbrt", [arg]) => format!("<mroot>{}<mn>3</mn></mroot>", arg),
+            ("abs", [arg]) => Self::row(&[
+                "<mo>|</mo>".to_owned(),
+                arg.clone(),
+                "<mo>|</mo>".to_owned(),
+            ]),
+            _ => {
+                let args = args.join("<mo>,</mo>");
+                Self::row(&[
+                    format!("<mi>{}</mi>", name),
+                    "<mo>&#x2061;</mo>".to_owned(),
+                    self.parens(args),
+                ])
+            }
+        }
+    }
+
+    fn list(&self, items: Vec<String>) -> String {
+        Self::row(&[
+            "<mo>[</mo>".to_owned(),
+            items.join("<mo>,</mo>"),
+            "<mo>]</mo>".to_owned(),
+        ])
+    }
+
+    fn index(&self, list: String, index: String) -> String {
+        format!("<msub>{}{}</msub>", list, index)
+    }
+
+    fn range(&self, from: String, to: String) -> String {
+        Self::row(&[from, "<mo>&#x2026;</mo>".to_owned(), to])
+    }
+
+    fn lambda(&self, params: &[String], body: String) -> String {
+        let params: Vec<String> = params.iter().map(|param| self.name(param)).collect();
+        let params = match params.as_slice() {
+            [param] => param.clone(),
+            params => self.parens(params.join("<mo>,</mo>")),
+        };
+        Self::row(&[params, "<mo>&#x21A6;</mo>".to_owned(), body])
+    }
+}
@@ -0,0 +1,64 @@
+use std::{env, fs, path::PathBuf};
+
+use crate::parse_expression;
+
+use super::*;
+
+fn golden_path(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "src", "render", "golden", name]
+        .iter()
+        .collect()
+}
+
+/// Renders every expression in `golden/expressions.txt` and compares the
+/// result line by line with `golden/<name>`. Set `UPDATE_GOLDEN=1` to rewrite
+/// the golden file instead.
+fn check_golden(name: &str, markup: Markup) {
+    let expressions = fs::read_to_string(golden_path("expressions.txt")).unwrap();
+    let rendered: String = expressions
+        .lines()
+        .map(|expression| render(&parse_expression(expression).unwrap(), markup) + "\n")
+        .collect();
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(golden_path(name), &rendered).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(golden_path(name)).unwrap();
+    for ((expression, actual), expected) in expressions
+        .lines()
+        .zip(rendered.lines())
+        .zip(expected.lines())
+    {
+        assert_eq!(actual, expected, "rendering {}", expression);
+    }
+    assert_eq!(rendered.lines().count(), expected.lines().count());
+}
+
+#[test]
+fn latex_golden() {
+    check_golden("latex.txt", Markup::Latex);
+}
+
+#[test]
+fn mathml_golden() {
+    check_golden("mathml.txt", Markup::MathMl);
+}
+
+#[test]
+fn equations() {
+    let tree = parse_expression("1 / 2").unwrap();
+    assert_eq!(
+        render_equation(&tree, "0.5", Markup::Latex),
+        "\\frac{1}{2} = 0.5"
+    );
+    let tree = parse_expression("10 ^ 20").unwrap();
+    assert_eq!(
+        render_equation(&tree, "1e20", Markup::Latex),
+        "10^{20} = 1 \\times 10^{20}"
+    );
+    assert_eq!(
+        render_equation(&tree, "-1.5e-3", Markup::MathMl),
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><msup><mn>10</mn><mn>20</mn></msup><mo>=</mo><mrow><mo>&#x2212;</mo><mrow><mn>1.5</mn><mo>&#x00D7;</mo><msup><mn>10</mn><mrow><mo>&#x2212;</mo><mn>3</mn></mrow></msup></mrow></mrow></mrow></math>"
+    );
+}
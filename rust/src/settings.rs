@@ -0,0 +1,139 @@
+use std::{f64::consts::PI, fmt::Display};
+
+use crate::decimal::{Rounding, MAX_SCALE};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NumberFormat {
+    Auto,
+    Fixed,
+    Scientific,
+    /// Powers of ten in steps of three written as SI prefixes, as in `4.7 k`.
+    Engineering,
+}
+
+/// The unit trigonometric functions take and inverse trigonometric
+/// functions return angles in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AngleMode {
+    Radians,
+    Degrees,
+    /// A right angle is 100 gradians.
+    Gradians,
+}
+
+impl AngleMode {
+    /// The short name used by `--angle` and `:angle`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AngleMode::Radians => "rad",
+            AngleMode::Degrees => "deg",
+            AngleMode::Gradians => "grad",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AngleMode> {
+        [AngleMode::Radians, AngleMode::Degrees, AngleMode::Gradians]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+    }
+
+    /// Half a turn in this unit.
+    fn half_turn(&self) -> f64 {
+        match self {
+            AngleMode::Radians => PI,
+            AngleMode::Degrees => 180.0,
+            AngleMode::Gradians => 200.0,
+        }
+    }
+
+    /// Converts an angle in this unit to radians.
+    pub fn to_radians(&self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Radians => angle,
+            _ => angle / self.half_turn() * PI,
+        }
+    }
+
+    /// Converts an angle in radians to this unit.
+    pub fn from_radians(&self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Radians => angle,
+            _ => angle / PI * self.half_turn(),
+        }
+    }
+
+    /// Converts an angle in degrees to this unit.
+    pub fn from_degrees(&self, angle: f64) -> f64 {
+        match self {
+            AngleMode::Degrees => angle,
+            _ => angle / 180.0 * self.half_turn(),
+        }
+    }
+}
+
+/// Exact decimal arithmetic on plain numbers: results keep at most `places`
+/// digits after the decimal point, rounded with `rounding`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DecimalMode {
+    pub places: u32,
+    pub rounding: Rounding,
+}
+
+impl DecimalMode {
+    pub fn new(rounding: Rounding) -> Self {
+        DecimalMode {
+            places: MAX_SCALE,
+            rounding,
+        }
+    }
+
+    /// Reads a rounding and optionally the number of places, as in
+    /// `half-up 2` for `:decimal` or `half-up:2` for `--decimal`.
+    pub fn parse(text: &str) -> Option<DecimalMode> {
+        let mut words = text
+            .split(|c: char| c == ':' || c.is_whitespace())
+            .filter(|word| !word.is_empty());
+        let mut mode = DecimalMode::new(Rounding::from_name(words.next()?)?);
+        if let Some(places) = words.next() {
+            mode.places = places.parse().ok().filter(|places| *places <= MAX_SCALE)?;
+        }
+        words.next().is_none().then_some(mode)
+    }
+}
+
+impl Default for DecimalMode {
+    fn default() -> Self {
+        Self::new(Rounding::HalfEven)
+    }
+}
+
+impl Display for DecimalMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.rounding.name(), self.places)
+    }
+}
+
+/// Session-wide preferences of a calculator.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Settings {
+    pub format: NumberFormat,
+    /// Digits shown after the decimal point.
+    pub precision: Option<usize>,
+    pub angle: AngleMode,
+    /// Print each REPL input in canonical form before its result.
+    pub echo: bool,
+    /// Exact decimal arithmetic instead of floating point, if set.
+    pub decimal: Option<DecimalMode>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            format: NumberFormat::Auto,
+            precision: None,
+            angle: AngleMode::Radians,
+            echo: false,
+            decimal: None,
+        }
+    }
+}
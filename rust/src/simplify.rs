@@ -0,0 +1,227 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    ast::AST,
+    decimal::{self, Decimal},
+    environment::Environment,
+    error::CalcError,
+    forms,
+    printer::is_minus_one,
+    tokens::{Cmp, Num, Op, Token},
+    value::Value,
+};
+
+/// Folds constant subexpressions and applies identities that do not change
+/// the result, e.g. `2 * 3 + x` becomes `6 + x` and `x * 1` becomes `x`.
+///
+/// The negations the parser writes as `-1 * x` are cancelled where possible:
+/// `a - -b` becomes `a + b` and `-(-x)` becomes `x`. With `exact` set,
+/// identities that only hold for finite values, like `x - x = 0` and
+/// `x * 0 = 0`, are applied too. In `env`'s decimal mode, constants are
+/// folded in decimal arithmetic, as evaluation would. Calls to `env`'s pure
+/// functions with constant arguments are folded too, as in `sin(0) + x`.
+///
+/// Outside decimal mode a float is only folded when it has a short literal,
+/// so that `0.1 + 0.2 + x` keeps the sum it was written as rather than
+/// becoming `0.30000000000000004 + x`.
+pub fn simplify(tree: &AST, exact: bool, env: &dyn Environment) -> Box<AST> {
+    let (first, links) = tree.chain();
+    if !links.is_empty() {
+        let mut result = simplify(first, exact, env);
+        for (_, oper, right) in links {
+            let right = simplify(right, exact, env);
+            result = combine(oper.clone(), result, right, exact, env);
+        }
+        return result;
+    }
+    match (tree.value(), tree.left(), tree.right()) {
+        (Token::Operator(oper), Some(left), Some(right)) => {
+            let left = simplify(left, exact, env);
+            let right = simplify(right, exact, env);
+            combine(oper.clone(), left, right, exact, env)
+        }
+        (Token::Degree, Some(operand), _) => AST::new_degrees(simplify(operand, exact, env)),
+        (Token::Conversion(unit), Some(operand), _) => {
+            AST::new_conversion(simplify(operand, exact, env), unit.clone())
+        }
+        (Token::Currency(currency), Some(operand), _) => {
+            AST::new_money(simplify(operand, exact, env), currency)
+        }
+        (Token::Exchange(currency), Some(operand), _) => {
+            AST::new_exchange(simplify(operand, exact, env), currency)
+        }
+        (Token::Comparison(cmp), Some(left), Some(right)) => compare(
+            cmp.clone(),
+            simplify(left, exact, env),
+            simplify(right, exact, env),
+            env,
+        ),
+        (Token::Equals, Some(left), Some(right)) => {
+            AST::new_equation(simplify(left, exact, env), simplify(right, exact, env))
+        }
+        (Token::Function(name), _, _) => {
+            let args = tree
+                .arguments()
+                .into_iter()
+                .map(|arg| simplify(arg, exact, env))
+                .collect();
+            call(name, AST::new_call(name, args), env)
+        }
+        _ => Box::new(tree.clone()),
+    }
+}
+
+fn combine(
+    oper: Op,
+    left: Box<AST>,
+    right: Box<AST>,
+    exact: bool,
+    env: &dyn Environment,
+) -> Box<AST> {
+    if let (Some(mode), Token::Number(a), Token::Number(b)) =
+        (env.decimal(), left.value(), right.value())
+    {
+        if let (Ok(Value::Decimal(a)), Ok(Value::Decimal(b))) = (
+            AST::evaluate_number(a, true).map(Value::exact),
+            AST::evaluate_number(b, true).map(Value::exact),
+        ) {
+            if let Ok(Some(x)) = decimal::operate(&oper, &a, &b, mode) {
+                return decimal_literal(x);
+            }
+        }
+    } else if let (Some(_), Some(_)) = (number(&left), number(&right)) {
+        let folded = AST::new_operator(oper.clone(), left.clone(), right.clone()).eval(env);
+        if let Some(folded) = fold(folded, env) {
+            return folded;
+        }
+    }
+
+    match oper {
+        Op::Add if is(&right, 0.0) => left,
+        Op::Add if is(&left, 0.0) => right,
+        Op::Add if negation(&right).is_some() || is_negative(&right) => {
+            AST::new_operator(Op::Sub, left, negate(right))
+        }
+        Op::Add if negation(&left).is_some() => AST::new_operator(Op::Sub, right, negate(left)),
+        Op::Sub if is(&right, 0.0) => left,
+        Op::Sub if is(&left, 0.0) => negate(right),
+        Op::Sub if negation(&right).is_some() || is_negative(&right) => {
+            AST::new_operator(Op::Add, left, negate(right))
+        }
+        Op::Sub if exact && left.equivalent(&right) => literal(0.0),
+        Op::Mult if exact && (is(&left, 0.0) || is(&right, 0.0)) => literal(0.0),
+        Op::Div if exact && is(&left, 0.0) => literal(0.0),
+        Op::Pow if exact && is(&right, 0.0) => literal(1.0),
+        Op::Mult if is(&right, 1.0) => left,
+        Op::Mult if is(&left, 1.0) => right,
+        Op::Mult if is_minus_one(&left) && negation(&right).is_some() => negate(right),
+        Op::Mult | Op::Div if negation(&left).is_some() && negation(&right).is_some() => {
+            AST::new_operator(oper, negate(left), negate(right))
+        }
+        Op::Div | Op::Pow if is(&right, 1.0) => left,
+        _ => AST::new_operator(oper, left, right),
+    }
+}
+
+/// `left cmp right`, folded to 1 or 0 when both sides are numbers.
+fn compare(cmp: Cmp, left: Box<AST>, right: Box<AST>, env: &dyn Environment) -> Box<AST> {
+    let both_numbers = number(&left).is_some() && number(&right).is_some();
+    let tree = AST::new_comparison(cmp, left, right);
+    match tree.eval(env) {
+        Ok(Value::Integer(n)) if both_numbers => decimal_literal(Decimal::new(n, 0)),
+        _ => tree,
+    }
+}
+
+/// `tree`, a call of `name`, folded when `name` is a pure function and the
+/// arguments are all numbers. Calls without arguments, such as `today()`,
+/// are left alone.
+fn call(name: &str, tree: Box<AST>, env: &dyn Environment) -> Box<AST> {
+    let args = tree.arguments();
+    let constant = !args.is_empty() && args.iter().all(|arg| number(arg).is_some());
+    let pure = !forms::is_form(name) && env.function(name).is_some_and(|f| f.is_pure());
+    if constant && pure {
+        if let Some(folded) = fold(tree.eval(env), env) {
+            return folded;
+        }
+    }
+    tree
+}
+
+/// The literal a folded constant is written as, or `None` to leave it
+/// unfolded: it failed, is not a number, or is a float whose shortest
+/// literal is long, outside decimal mode.
+fn fold(value: Result<Value, CalcError>, env: &dyn Environment) -> Option<Box<AST>> {
+    match value.ok()? {
+        Value::Integer(n) => Some(decimal_literal(Decimal::new(n, 0))),
+        Value::Decimal(x) => Some(decimal_literal(*x)),
+        Value::Number(x) if x.is_finite() && (env.decimal().is_some() || is_short(x)) => {
+            Some(literal(x))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `x` is written with at most 15 significant digits, as many as
+/// any decimal a float can hold keeps.
+fn is_short(x: f64) -> bool {
+    let scientific = format!("{:e}", x);
+    let (mantissa, _) = scientific.split_once('e').unwrap_or((&scientific, ""));
+    mantissa.chars().filter(|c| c.is_ascii_digit()).count() <= 15
+}
+
+fn number(tree: &AST) -> Option<f64> {
+    match tree.value() {
+        Token::Number(Num::Integer(x)) => Some(*x as f64),
+        Token::Number(Num::Float(x)) => Some(*x),
+        Token::Number(Num::Decimal(x)) => Some(x.to_f64()),
+        _ => None,
+    }
+}
+
+fn is(tree: &AST, value: f64) -> bool {
+    number(tree) == Some(value)
+}
+
+fn is_negative(tree: &AST) -> bool {
+    number(tree).is_some_and(|x| x < 0.0)
+}
+
+/// The operand of a `-1 * x` node.
+fn negation(tree: &AST) -> Option<&AST> {
+    match (tree.value(), tree.left(), tree.right()) {
+        (Token::Operator(Op::Mult), Some(left), Some(right)) if is_minus_one(left) => Some(right),
+        _ => None,
+    }
+}
+
+fn negate(tree: Box<AST>) -> Box<AST> {
+    if let Token::Number(Num::Decimal(x)) = tree.value() {
+        return decimal_literal(-*x);
+    }
+    if let Some(x) = number(&tree) {
+        return literal(-x);
+    }
+    match negation(&tree) {
+        Some(operand) => Box::new(operand.clone()),
+        None => AST::new_operator(Op::Mult, literal(-1.0), tree),
+    }
+}
+
+/// A number node, written as an integer when the value is whole.
+fn literal(x: f64) -> Box<AST> {
+    if x == x.trunc() && x.abs() <= 2f64.powi(53) {
+        AST::new_leaf(Token::Number(Num::Integer(x as i64)))
+    } else {
+        AST::new_leaf(Token::Number(Num::Float(x)))
+    }
+}
+
+/// A decimal number node, written as an integer when the value is whole.
+fn decimal_literal(x: Decimal) -> Box<AST> {
+    match x.to_integer().and_then(|n| i64::try_from(n).ok()) {
+        Some(n) => AST::new_leaf(Token::Number(Num::Integer(n))),
+        None => AST::new_leaf(Token::Number(Num::Decimal(x))),
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{calculator::Calculator, parse_expression, printer::infix, settings::DecimalMode};
+
+use super::*;
+
+fn simplified(expression: &str, exact: bool) -> String {
+    infix(&simplify(
+        &parse_expression(expression).unwrap(),
+        exact,
+        &Calculator::new(),
+    ))
+}
+
+#[test]
+fn folds_constants() {
+    assert_eq!(simplified("2*3+x", false), "6 + x");
+    assert_eq!(simplified("x * (10 / 4 - 0.5)", false), "x * 2");
+    assert_eq!(simplified("(1 + 2) * (3 + 4)", false), "21");
+    assert_eq!(simplified("f(2 * 2, y)", false), "f(4, y)");
+    assert_eq!(simplified("2 > 1 + x", false), "2 > 1 + x");
+    assert_eq!(simplified("x * (3 >= 1 + 2)", false), "x");
+    assert_eq!(simplified("0.5 + 0.25 * x", false), "0.5 + 0.25 * x");
+    assert_eq!(simplified("1.5 * 3 + x", false), "4.5 + x");
+}
+
+#[test]
+fn keeps_failing_constants() {
+    assert_eq!(simplified("x + 1 / 0", false), "x + 1 / 0");
+}
+
+#[test]
+fn applies_identities() {
+    assert_eq!(simplified("x * 1 + 0", false), "x");
+    assert_eq!(simplified("1 * (y - 0) / 1", false), "y");
+    assert_eq!(simplified("0 + a * (2 - 1)", false), "a");
+    assert_eq!(simplified("0 - a", false), "-a");
+}
+
+#[test]
+fn cancels_negations() {
+    assert_eq!(simplified("-(-x)", false), "x");
+    assert_eq!(simplified("a - -b", false), "a + b");
+    assert_eq!(simplified("a + -b", false), "a - b");
+    assert_eq!(simplified("a + -3", false), "a - 3");
+    assert_eq!(simplified("-a + b", false), "b - a");
+    assert_eq!(simplified("-a * -b", false), "a * b");
+    assert_eq!(simplified("-(a + b)", false), "-(a + b)");
+}
+
+#[test]
+fn self_subtraction_needs_exact_mode() {
+    assert_eq!(simplified("x - x", false), "x - x");
+    assert_eq!(simplified("f(x) * 2 - f(x) * 2", true), "0");
+    assert_eq!(simplified("x - y", true), "x - y");
+}
+
+#[test]
+fn folds_decimals_exactly() {
+    let mut calculator = Calculator::new();
+    calculator.settings_mut().decimal = Some(DecimalMode::default());
+    let decimal = |expression| {
+        let tree = parse_expression(expression).unwrap();
+        infix(&simplify(&tree, true, &calculator))
+    };
+    assert_eq!(decimal("0.1 + 0.2"), "0.3");
+    assert_eq!(decimal("x * (0.5 + 0.5)"), "x");
+    assert_eq!(
+        decimal("2^100 + x"),
+        "1267650600228229401496703205376.0 + x"
+    );
+    assert_eq!(decimal("x + (0.1 - 0.4)"), "x - 0.3");
+    assert_eq!(decimal("x + 1 / 0"), "x + 1 / 0");
+}
+
+#[test]
+fn keeps_floats_without_a_short_literal() {
+    assert_eq!(simplified("0.1 + 0.2", false), "0.1 + 0.2");
+    assert_eq!(simplified("0.1 + 0.2 + x", false), "0.1 + 0.2 + x");
+    assert_eq!(simplified("x / (1 / 3)", false), "x / (1 / 3)");
+    assert_eq!(simplified("(1 / 4) * x", false), "0.25 * x");
+}
+
+#[test]
+fn folds_calls_with_constant_arguments() {
+    assert_eq!(simplified("sin(0) + x", false), "x");
+    assert_eq!(simplified("max(1, 2, 3) * y", false), "3 * y");
+    assert_eq!(simplified("sqrt(2 * 8) - x", false), "4 - x");
+    assert_eq!(simplified("gcd(12, 18) + x", false), "6 + x");
+    // Long results, variables, failures and unknown names stay calls.
+    assert_eq!(simplified("sqrt(2) * x", false), "sqrt(2) * x");
+    assert_eq!(simplified("sin(x) + cos(0)", false), "sin(x) + 1");
+    assert_eq!(simplified("ln(0 - 1) + x", false), "ln(-1) + x");
+    assert_eq!(simplified("f(2 * 2)", false), "f(4)");
+    assert_eq!(simplified("today() - 1 d", false), "today() - 1 d");
+
+    let mut calculator = Calculator::new();
+    calculator.define_fn("double", &["x"], "2 * x").unwrap();
+    let tree = parse_expression("double(3) + x").unwrap();
+    assert_eq!(infix(&simplify(&tree, false, &calculator)), "double(3) + x");
+}
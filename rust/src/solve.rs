@@ -0,0 +1,298 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    ast::AST,
+    derivative::derivative_in,
+    environment::Environment,
+    error::{CalcError, ErrorKind},
+    format_value, forms,
+    tokens::{Op, Token},
+};
+
+/// A function of the unknown, as evaluated by the solver.
+pub type Function<'a> = dyn Fn(f64) -> Result<f64, CalcError> + 'a;
+
+/// Steps smaller than this, relative to the root, end the iteration.
+const TOLERANCE: f64 = 1e-13;
+
+const MAX_ITERATIONS: usize = 200;
+
+/// How many pieces an interval is cut into when looking for sign changes.
+const SAMPLES: usize = 1000;
+
+/// How close to zero a function must get to have a root where its graph only
+/// touches the axis, as `x^2` does at 0.
+const TOUCH_TOLERANCE: f64 = 1e-10;
+
+/// Half-widths of the intervals searched around the starting point when
+/// Newton's method fails without an interval to search.
+const SEARCH_WIDTHS: [f64; 3] = [10.0, 1e3, 1e6];
+
+/// The roots of `equation` for `var`, in increasing order.
+///
+/// `equation` is either `lhs = rhs` or an expression that should equal zero.
+/// With an interval, every root in it is returned: sign changes are refined
+/// with Brent's method and roots where the graph only touches zero with
+/// Newton's method. Without one, Newton's method starts from the current
+/// value of `var` (or 1) and a single root is returned.
+pub fn solve(
+    equation: &AST,
+    var: &str,
+    interval: Option<(f64, f64)>,
+    env: &dyn Environment,
+) -> Result<Vec<f64>, CalcError> {
+    let residual = match (equation.value(), equation.left(), equation.right()) {
+        (Token::Equals, Some(left), Some(right)) => {
+            AST::new_operator(Op::Sub, Box::new(left.clone()), Box::new(right.clone()))
+        }
+        _ => Box::new(equation.clone()),
+    };
+    let f = |x: f64| forms::number_at("solve", &residual, var, x, env);
+    let derivative = derivative_in(&residual, var, env.angle_mode()).ok();
+    let slope = |x: f64| match &derivative {
+        Some(tree) => forms::number_at("solve", tree, var, x, env),
+        None => numeric_slope(&f, x),
+    };
+
+    match interval {
+        Some((a, b)) => {
+            let roots = roots_between(&f, &slope, a.min(b), a.max(b))?;
+            if roots.is_empty() {
+                return Err(CalcError::new(
+                    ErrorKind::NoConvergence,
+                    &format!(
+                        "solve found no root for {} between {} and {}",
+                        var,
+                        format_value(a.min(b)),
+                        format_value(a.max(b))
+                    ),
+                ));
+            }
+            Ok(roots)
+        }
+        None => {
+            let start = env
+                .variable(var)
+                .and_then(|value| value.as_number())
+                .filter(|x| x.is_finite())
+                .unwrap_or(1.0);
+            if let Ok(root) = newton(&f, &slope, start) {
+                return Ok(vec![root]);
+            }
+            for width in SEARCH_WIDTHS {
+                let roots = roots_between(&f, &slope, start - width, start + width)?;
+                let nearest = roots
+                    .into_iter()
+                    .min_by(|x, y| (x - start).abs().total_cmp(&(y - start).abs()));
+                if let Some(root) = nearest {
+                    return Ok(vec![root]);
+                }
+            }
+            Err(CalcError::new(
+                ErrorKind::NoConvergence,
+                &format!(
+                    "solve did not converge starting from {} = {}; try giving an interval",
+                    var,
+                    format_value(start)
+                ),
+            ))
+        }
+    }
+}
+
+/// Central difference, for functions that cannot be differentiated symbolically.
+fn numeric_slope(f: &Function, x: f64) -> Result<f64, CalcError> {
+    let h = 6e-6 * (1.0 + x.abs());
+    Ok((f(x + h)? - f(x - h)?) / (2.0 * h))
+}
+
+/// `f(x)`, or NaN where `f` is undefined, e.g. `ln(x)` for negative `x`.
+fn sample(f: &Function, x: f64) -> Result<f64, CalcError> {
+    match f(x) {
+        Err(error) if matches!(error.kind, ErrorKind::Domain | ErrorKind::DivideByZero) => {
+            Ok(f64::NAN)
+        }
+        result => result,
+    }
+}
+
+fn no_convergence(method: &str) -> CalcError {
+    CalcError::new(
+        ErrorKind::NoConvergence,
+        &format!("{} did not converge", method),
+    )
+}
+
+/// Every root of `f` between `a` and `b`, in increasing order.
+///
+/// Poles where `f` changes sign without passing through zero, as `1 / x`
+/// does, are not roots and are skipped.
+pub fn roots_between(
+    f: &Function,
+    slope: &Function,
+    a: f64,
+    b: f64,
+) -> Result<Vec<f64>, CalcError> {
+    if !(a.is_finite() && b.is_finite() && a < b) {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!(
+                "Invalid interval from {} to {}",
+                format_value(a),
+                format_value(b)
+            ),
+        ));
+    }
+    let step = (b - a) / SAMPLES as f64;
+    let xs: Vec<f64> = (0..=SAMPLES)
+        .map(|i| if i == SAMPLES { b } else { a + step * i as f64 })
+        .collect();
+    let ys = xs
+        .iter()
+        .map(|x| sample(f, *x))
+        .collect::<Result<Vec<f64>, CalcError>>()?;
+    if ys.iter().all(|y| y.is_nan()) {
+        // Undefined everywhere: report why rather than finding nothing.
+        f(a)?;
+    }
+
+    let mut roots = vec![];
+    for i in 0..SAMPLES {
+        let (x0, x1, y0, y1) = (xs[i], xs[i + 1], ys[i], ys[i + 1]);
+        if y0 == 0.0 {
+            roots.push(x0);
+        } else if y0.is_finite() && y1.is_finite() && y1 != 0.0 && (y0 < 0.0) != (y1 < 0.0) {
+            let root = brent(f, x0, x1)?;
+            if sample(f, root)?.abs() <= y0.abs().min(y1.abs()) {
+                roots.push(root);
+            }
+        }
+    }
+    if ys[SAMPLES] == 0.0 {
+        roots.push(b);
+    }
+
+    // Local minima of |f| that do not cross zero may still touch it.
+    for i in 1..SAMPLES {
+        let (y0, y1, y2) = (ys[i - 1], ys[i], ys[i + 1]);
+        let touches = y0 * y1 > 0.0 && y1 * y2 > 0.0 && y1.abs() < y0.abs() && y1.abs() <= y2.abs();
+        if !touches {
+            continue;
+        }
+        if let Ok(root) = newton(f, slope, xs[i]) {
+            let nearby = (xs[i - 1]..=xs[i + 1]).contains(&root);
+            if nearby && sample(f, root)?.abs() <= TOUCH_TOLERANCE {
+                roots.push(root);
+            }
+        }
+    }
+
+    // Brent's method stops within TOLERANCE, so a root at zero may come
+    // back as a tiny number rather than zero itself.
+    for root in roots.iter_mut() {
+        if root.abs() <= TOLERANCE {
+            *root = 0.0;
+        }
+    }
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots.dedup_by(|x, y| (*x - *y).abs() <= 1e-9 * (1.0 + y.abs()));
+    Ok(roots)
+}
+
+/// Brent's method: a root of `f` between `a` and `b`, where `f(a)` and `f(b)`
+/// have opposite signs.
+pub fn brent(f: &Function, mut a: f64, mut b: f64) -> Result<f64, CalcError> {
+    let (mut fa, mut fb) = (f(a)?, f(b)?);
+    if fa == 0.0 {
+        return Ok(a);
+    }
+    if (fa < 0.0) == (fb < 0.0) && fb != 0.0 {
+        return Err(CalcError::new(
+            ErrorKind::InvalidArgument,
+            &format!(
+                "No sign change between {} and {}",
+                format_value(a),
+                format_value(b)
+            ),
+        ));
+    }
+
+    let (mut c, mut fc) = (b, fb);
+    let (mut d, mut e) = (b - a, b - a);
+    for _ in 0..MAX_ITERATIONS {
+        if (fb < 0.0) == (fc < 0.0) {
+            c = a;
+            fc = fa;
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            (a, b, c) = (b, c, b);
+            (fa, fb, fc) = (fb, fc, fb);
+        }
+        let tolerance = 2.0 * f64::EPSILON * b.abs() + 0.5 * TOLERANCE;
+        let middle = 0.5 * (c - b);
+        if middle.abs() <= tolerance || fb == 0.0 {
+            return Ok(b);
+        }
+
+        if e.abs() >= tolerance && fa.abs() > fb.abs() {
+            // Secant step, or inverse quadratic interpolation with three points.
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * middle * s, 1.0 - s)
+            } else {
+                let (q, r) = (fa / fc, fb / fc);
+                (
+                    s * (2.0 * middle * q * (q - r) - (b - a) * (r - 1.0)),
+                    (q - 1.0) * (r - 1.0) * (s - 1.0),
+                )
+            };
+            if p > 0.0 {
+                q = -q;
+            }
+            p = p.abs();
+            if 2.0 * p < (3.0 * middle * q - (tolerance * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                d = middle;
+                e = d;
+            }
+        } else {
+            d = middle;
+            e = d;
+        }
+
+        a = b;
+        fa = fb;
+        b += if d.abs() > tolerance {
+            d
+        } else {
+            tolerance.copysign(middle)
+        };
+        fb = f(b)?;
+    }
+    Err(no_convergence("Brent's method"))
+}
+
+/// Newton's method from `x0`, with `slope` the derivative of `f`.
+pub fn newton(f: &Function, slope: &Function, x0: f64) -> Result<f64, CalcError> {
+    let mut x = x0;
+    for _ in 0..MAX_ITERATIONS {
+        let y = f(x)?;
+        if y == 0.0 {
+            return Ok(x);
+        }
+        let step = y / slope(x)?;
+        if !step.is_finite() {
+            break;
+        }
+        x -= step;
+        if step.abs() <= TOLERANCE * (1.0 + x.abs()) {
+            return Ok(x);
+        }
+    }
+    Err(no_convergence("Newton's method"))
+}
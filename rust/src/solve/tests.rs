@@ -0,0 +1,122 @@
+use std::f64::consts::PI;
+
+use crate::{calculator::Calculator, value::Value};
+
+use super::*;
+
+fn close(actual: f64, expected: f64) -> bool {
+    (actual - expected).abs() <= 1e-12 * (1.0 + expected.abs())
+}
+
+#[test]
+fn brent_finds_a_bracketed_root() {
+    let f = |x: f64| Ok(x * x - 2.0);
+    assert!(close(brent(&f, 0.0, 2.0).unwrap(), 2f64.sqrt()));
+    assert!(close(
+        brent(&|x: f64| Ok(x.cos() - x), 0.0, 1.0).unwrap(),
+        0.7390851332151607
+    ));
+    let error = brent(&f, 2.0, 3.0).expect_err("no sign change");
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn newton_reports_failure_to_converge() {
+    let f = |x: f64| Ok(x * x - 2.0);
+    let slope = |x: f64| Ok(2.0 * x);
+    assert!(close(newton(&f, &slope, 1.0).unwrap(), 2f64.sqrt()));
+
+    let f = |x: f64| Ok(x * x + 1.0);
+    let error = newton(&f, &slope, 0.5).expect_err("x^2 + 1 has no real root");
+    assert_eq!(error.kind, ErrorKind::NoConvergence);
+}
+
+#[test]
+fn every_root_in_an_interval() {
+    let calculator = Calculator::new();
+    let roots = calculator.solve("sin(x)", "x", Some((-1.0, 7.0))).unwrap();
+    assert_eq!(roots.len(), 3);
+    for (root, expected) in roots.iter().zip([0.0, PI, 2.0 * PI]) {
+        assert!(close(*root, expected), "{} != {}", root, expected);
+    }
+
+    let roots = calculator.solve("x^3 = x", "x", Some((2.0, -2.0))).unwrap();
+    assert_eq!(roots, vec![-1.0, 0.0, 1.0]);
+
+    // Touches zero without changing sign.
+    let roots = calculator
+        .solve("(x - 0.3)^2", "x", Some((0.0, 1.0)))
+        .unwrap();
+    assert_eq!(roots.len(), 1);
+    assert!((roots[0] - 0.3).abs() < 1e-6);
+}
+
+#[test]
+fn poles_and_gaps_are_not_roots() {
+    let calculator = Calculator::new();
+    let error = calculator
+        .solve("1 / x", "x", Some((-1.0, 1.0)))
+        .expect_err("1 / x only changes sign at its pole");
+    assert_eq!(error.kind, ErrorKind::NoConvergence);
+
+    let roots = calculator
+        .solve("ln(x) = 0", "x", Some((-5.0, 5.0)))
+        .unwrap();
+    assert_eq!(roots, vec![1.0]);
+
+    let error = calculator
+        .solve("ln(x)", "x", Some((-5.0, -1.0)))
+        .expect_err("ln is undefined for negative numbers");
+    assert_eq!(error.kind, ErrorKind::Domain);
+}
+
+#[test]
+fn newton_without_an_interval() {
+    let mut calculator = Calculator::new();
+    let roots = calculator.solve("x^2 - 2 = 0", "x", None).unwrap();
+    assert!(close(roots[0], 2f64.sqrt()));
+
+    // Starts from the current value of the variable.
+    calculator.set_var("x", -3.0).unwrap();
+    let roots = calculator.solve("x^2 - 2 = 0", "x", None).unwrap();
+    assert!(close(roots[0], -(2f64.sqrt())));
+
+    // The derivative vanishes at the start, so a bracket is searched instead.
+    calculator.set_var("x", 0.0).unwrap();
+    let roots = calculator.solve("x^2 = 4", "x", None).unwrap();
+    assert!(close(roots[0].abs(), 2.0));
+
+    let error = calculator
+        .solve("x^2 + 1 = 0", "x", None)
+        .expect_err("there is no real root");
+    assert_eq!(error.kind, ErrorKind::NoConvergence);
+    assert_eq!(
+        error.message,
+        "solve did not converge starting from x = 0.0; try giving an interval"
+    );
+}
+
+#[test]
+fn solve_form() {
+    let mut calculator = Calculator::new();
+    calculator.define_fn("f", &["x"], "x^3 + x").unwrap();
+    let roots = calculator.eval("solve(f(x) = 10, x, 0, 5)").unwrap();
+    assert_eq!(roots, Value::List(vec![Value::Number(2.0)]));
+    let roots = calculator.eval("solve(x^2 = 9, x, -5, 5) + 1").unwrap();
+    assert_eq!(
+        roots,
+        Value::List(vec![Value::Number(-2.0), Value::Number(4.0)])
+    );
+    let root = calculator.eval("solve(x^2 = 9, x, -5, 5)[1]").unwrap();
+    assert_eq!(root, Value::Number(3.0));
+    let roots = calculator.eval("solve(x^2 = -1, x, -5, 5)");
+    assert_eq!(roots.unwrap_err().kind, ErrorKind::NoConvergence);
+    assert!(calculator.get_var("x").is_none());
+
+    let error = calculator.eval("solve(x^2 = 2, x, 0)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = calculator.eval("solve(x^2 = 2, 3)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidArgument);
+    let error = calculator.define_fn("solve", &["x"], "x").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidName);
+}
@@ -0,0 +1,211 @@
+//! Statistics over the arguments of a call, as in `mean(2, 4, 9)`, or the
+//! elements of a list, as in `mean([2, 4, 9])`.
+//!
+//! Totals use compensated summation and the variance Welford's
+//! running update, so long runs of similar values keep their precision.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    decimal::{self, Decimal},
+    error::{CalcError, ErrorKind},
+    functions::{Arity, Callable, ValueFunction},
+    list,
+    settings::DecimalMode,
+    tokens::Op,
+    value::Value,
+};
+
+/// A running total that carries the low-order bits each addition drops, by
+/// Neumaier's variant of Kahan summation, which also keeps them when a term
+/// is larger than the total so far.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Sum {
+    total: f64,
+    compensation: f64,
+}
+
+impl Sum {
+    pub fn add(&mut self, x: f64) {
+        let t = self.total + x;
+        self.compensation += if self.total.abs() >= x.abs() {
+            (self.total - t) + x
+        } else {
+            (x - t) + self.total
+        };
+        self.total = t;
+    }
+
+    pub fn total(&self) -> f64 {
+        self.total + self.compensation
+    }
+}
+
+pub fn sum(xs: &[f64]) -> f64 {
+    let mut sum = Sum::default();
+    for x in xs {
+        sum.add(*x);
+    }
+    sum.total()
+}
+
+/// The sum of `args`, with lists standing for their elements, in decimal
+/// arithmetic. `None` when one of them is not a decimal or an integer, or the
+/// total has more digits than a decimal holds.
+pub fn exact_sum(args: &[Value], mode: DecimalMode) -> Option<Decimal> {
+    let mut total = Decimal::ZERO;
+    for arg in list::spread(args) {
+        let Value::Decimal(x) = arg.exact() else {
+            return None;
+        };
+        total = decimal::operate(&Op::Add, &total, &x, mode).ok()??;
+    }
+    Some(total)
+}
+
+fn exact_mean(args: &[Value], mode: DecimalMode) -> Option<Decimal> {
+    let count = list::spread(args).len();
+    let count = Decimal::new(i128::try_from(count).ok().filter(|n| *n > 0)?, 0);
+    decimal::operate(&Op::Div, &exact_sum(args, mode)?, &count, mode).ok()?
+}
+
+/// The mean and the sum of squared deviations from it, by Welford's method.
+fn moments(xs: &[f64]) -> (f64, f64) {
+    let (mut mean, mut squares) = (0.0, 0.0);
+    for (i, x) in xs.iter().enumerate() {
+        let delta = x - mean;
+        mean += delta / (i + 1) as f64;
+        squares += delta * (x - mean);
+    }
+    (mean, squares)
+}
+
+pub fn mean(xs: &[f64]) -> f64 {
+    moments(xs).0
+}
+
+/// The variance of a sample when `sample` is set, which divides by one less
+/// than the count, and of a whole population otherwise.
+pub fn variance(xs: &[f64], sample: bool) -> Result<f64, String> {
+    let needed = if sample { 2 } else { 1 };
+    if xs.len() < needed {
+        return Err(format!("needs at least {} values", needed));
+    }
+    let squares = moments(xs).1;
+    Ok(squares / (xs.len() - usize::from(sample)) as f64)
+}
+
+pub fn median(xs: &[f64]) -> f64 {
+    percentile(xs, 0.5).unwrap_or(f64::NAN)
+}
+
+/// The value a fraction `p` of the way through `xs` in order, interpolating
+/// between neighbours as spreadsheets' `PERCENTILE` does.
+pub fn percentile(xs: &[f64], p: f64) -> Result<f64, String> {
+    if !(0.0..=1.0).contains(&p) {
+        return Err("the percentile must be between 0 and 1".to_owned());
+    }
+    let sorted = sorted(xs);
+    let rank = p * (sorted.len() - 1) as f64;
+    let below = rank.floor() as usize;
+    let above = rank.ceil() as usize;
+    Ok(sorted[below] + (rank - below as f64) * (sorted[above] - sorted[below]))
+}
+
+/// The value that occurs most often, the first of them on a tie.
+pub fn mode(xs: &[f64]) -> Result<f64, String> {
+    let count = |x: f64| xs.iter().filter(|y| **y == x).count();
+    let mut best = (xs[0], count(xs[0]));
+    for x in &xs[1..] {
+        let n = count(*x);
+        if n > best.1 {
+            best = (*x, n);
+        }
+    }
+    match best {
+        (_, 1) => Err("no value occurs more than once".to_owned()),
+        (x, _) => Ok(x),
+    }
+}
+
+fn sorted(xs: &[f64]) -> Vec<f64> {
+    let mut sorted = xs.to_vec();
+    sorted.sort_by(|x, y| x.total_cmp(y));
+    sorted
+}
+
+/// The arguments of `name` as numbers, with lists standing for their
+/// elements.
+pub fn numbers(name: &str, args: &[Value]) -> Result<Vec<f64>, CalcError> {
+    list::spread(args)
+        .iter()
+        .map(|arg| {
+            let x = arg.as_number().ok_or_else(|| {
+                CalcError::new(
+                    ErrorKind::TypeMismatch,
+                    &format!("{} expects numbers but got {}", name, arg.described()),
+                )
+            })?;
+            if x.is_nan() {
+                return Err(CalcError::new(
+                    ErrorKind::Domain,
+                    &format!("{} expects numbers but got NaN", name),
+                ));
+            }
+            Ok(x)
+        })
+        .collect()
+}
+
+/// A statistic of all its arguments, of which there must be `at_least`
+/// once lists are spread.
+fn statistic<F>(name: &'static str, at_least: usize, f: F) -> ValueFunction
+where
+    F: Fn(&[f64]) -> Result<f64, String> + 'static,
+{
+    ValueFunction::new(name, Arity::AtLeast(1), move |args| {
+        let xs = numbers(name, args)?;
+        let result = if xs.len() < at_least {
+            Err(format!("needs at least {} values", at_least))
+        } else {
+            f(&xs)
+        };
+        result
+            .map(Value::Number)
+            .map_err(|e| CalcError::new(ErrorKind::Domain, &format!("{}: {}", name, e)))
+    })
+}
+
+/// `mean`, which in decimal mode is exact when every value is a decimal or an
+/// integer.
+fn mean_function() -> ValueFunction {
+    let inexact = statistic("mean", 1, |xs| Ok(mean(xs)));
+    ValueFunction::with_env("mean", Arity::AtLeast(1), move |args, env| {
+        match env.decimal().and_then(|mode| exact_mean(args, mode)) {
+            Some(mean) => Ok(Value::Decimal(Box::new(mean))),
+            None => inexact.call(args, env),
+        }
+    })
+}
+
+/// The statistics functions. `sum` is the special form in `forms`, which
+/// adds its arguments unless they take the shape of a series.
+pub fn functions() -> Vec<ValueFunction> {
+    vec![
+        mean_function(),
+        statistic("median", 1, |xs| Ok(median(xs))),
+        statistic("mode", 1, mode),
+        statistic("var", 2, |xs| variance(xs, true)),
+        statistic("varp", 1, |xs| variance(xs, false)),
+        statistic("stdev", 2, |xs| variance(xs, true).map(f64::sqrt)),
+        statistic("stdevp", 1, |xs| variance(xs, false).map(f64::sqrt)),
+        statistic("percentile", 2, |xs| {
+            let (p, xs) = xs.split_last().expect("at least two arguments");
+            percentile(xs, *p)
+        }),
+        ValueFunction::new("count", Arity::AtLeast(0), |args| {
+            Ok(Value::Number(list::spread(args).len() as f64))
+        }),
+    ]
+}
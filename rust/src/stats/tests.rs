@@ -0,0 +1,89 @@
+use crate::{calculator::Calculator, decimal::Rounding};
+
+use super::*;
+
+fn eval(expression: &str) -> Result<Value, CalcError> {
+    Calculator::new().eval(expression)
+}
+
+#[test]
+fn compensated_sum() {
+    let xs = vec![0.1; 10];
+    assert_eq!(sum(&xs), 1.0);
+    assert_eq!(sum(&[1e16, 1.0, -1e16]), 1.0);
+    assert_eq!(sum(&[]), 0.0);
+}
+
+#[test]
+fn center_and_spread() {
+    let xs = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+    assert_eq!(mean(&xs), 5.0);
+    assert_eq!(variance(&xs, false), Ok(4.0));
+    assert_eq!(variance(&xs, true), Ok(32.0 / 7.0));
+    assert_eq!(median(&xs), 4.5);
+    assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+    assert_eq!(mode(&xs), Ok(4.0));
+    assert_eq!(mode(&[1.0, 2.0, 2.0, 1.0]), Ok(1.0));
+    assert!(mode(&[1.0, 2.0]).is_err());
+    assert!(variance(&[1.0], true).is_err());
+
+    // A large offset would cancel every digit of a naive sum of squares.
+    let shifted: Vec<f64> = [4.0, 7.0, 13.0, 16.0].iter().map(|x| x + 1e9).collect();
+    assert_eq!(variance(&shifted, true), Ok(30.0));
+}
+
+#[test]
+fn percentiles() {
+    let xs = [15.0, 20.0, 35.0, 40.0, 50.0];
+    assert_eq!(percentile(&xs, 0.0), Ok(15.0));
+    assert_eq!(percentile(&xs, 0.4), Ok(29.0));
+    assert_eq!(percentile(&xs, 1.0), Ok(50.0));
+    assert!(percentile(&xs, 1.5).is_err());
+}
+
+#[test]
+fn variadic_calls() {
+    assert_eq!(eval("mean(1, 2, 3, 4)"), Ok(Value::Number(2.5)));
+    assert_eq!(
+        eval("stdevp(2, 4, 4, 4, 5, 5, 7, 9)"),
+        Ok(Value::Number(2.0))
+    );
+    assert_eq!(
+        eval("percentile(15, 20, 35, 40, 50, 0.4)"),
+        Ok(Value::Number(29.0))
+    );
+    assert_eq!(eval("count(1, 2, 3)"), Ok(Value::Number(3.0)));
+    assert_eq!(eval("count()"), Ok(Value::Number(0.0)));
+    assert_eq!(eval("sum(0.1, 0.2, 0.3)"), Ok(Value::Number(0.6)));
+    assert_eq!(eval("sum(7)"), Ok(Value::Number(7.0)));
+    assert_eq!(eval("sum(k^2, k, 1, 10)"), Ok(Value::Number(385.0)));
+    assert_eq!(eval("sum(1, 2, 3, 4)"), Ok(Value::Number(10.0)));
+
+    let error = eval("var(3)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+    let error = eval("mode(1, 2, 3)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Domain);
+    let error = eval("mean(1, 2026-10-19)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::TypeMismatch);
+}
+
+#[test]
+fn bound_names_are_not_a_series() {
+    let mut calculator = Calculator::new();
+    calculator.set_var("a", 5.0).unwrap();
+    calculator.set_var("b", 7.0).unwrap();
+    assert_eq!(calculator.eval("sum(a, b, 1, 3)"), Ok(Value::Number(16.0)));
+    assert_eq!(calculator.eval("sum(k, k, 1, 3)"), Ok(Value::Number(6.0)));
+}
+
+#[test]
+fn decimal_totals_are_exact() {
+    let mut calculator = Calculator::new();
+    calculator.settings_mut().decimal = Some(DecimalMode::new(Rounding::HalfEven));
+    let mut eval = |expression| calculator.eval(expression).unwrap().to_string();
+    assert_eq!(eval("sum(0.1, 0.2)"), "0.3");
+    assert_eq!(eval("sum([0.1, 0.2], 3)"), "3.3");
+    assert_eq!(eval("mean(0.1, 0.2)"), "0.15");
+    // A float in the mix makes the total a float.
+    assert_eq!(eval("sum(0.5, sqrt(2))"), "1.9142135623730951");
+}
@@ -1,8 +1,38 @@
 use std::fmt::Display;
 
+use crate::{decimal::Decimal, units::Unit};
+
 #[cfg(test)]
 mod tests;
 
+/// SI prefixes that scale a number literal written directly after it, as in
+/// `4.7k` or `3.3µ`, with their powers of ten.
+pub const SI_PREFIXES: &[(&str, i32)] = &[
+    ("E", 18),
+    ("P", 15),
+    ("T", 12),
+    ("G", 9),
+    ("M", 6),
+    ("k", 3),
+    ("m", -3),
+    ("µ", -6),
+    ("u", -6),
+    ("n", -9),
+    ("p", -12),
+    ("f", -15),
+    ("a", -18),
+];
+
+/// Binary prefixes, as in `16Gi`, with their powers of two.
+pub const BINARY_PREFIXES: &[(&str, i32)] = &[
+    ("Ki", 10),
+    ("Mi", 20),
+    ("Gi", 30),
+    ("Ti", 40),
+    ("Pi", 50),
+    ("Ei", 60),
+];
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ParenType {
     OpenParen,
@@ -13,6 +43,9 @@ pub enum ParenType {
 pub enum Num {
     Float(f64),
     Integer(i64),
+    /// A literal with a decimal point or an exponent, with its digits as
+    /// written so that decimal arithmetic can use them exactly.
+    Decimal(Decimal),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -21,6 +54,18 @@ pub enum Op {
     Sub,
     Mult,
     Div,
+    Pow,
+}
+
+/// A comparison, which is 1 when it holds and 0 otherwise, as in `x > 5`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Cmp {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -28,7 +73,42 @@ pub enum Token {
     Paren(ParenType),
     Number(Num),
     Operator(Op),
+    Comparison(Cmp),
     Variable(String),
+    Function(String),
+    Comma,
+    /// Separates the sides of an equation, which may only appear as a call
+    /// argument, e.g. `solve(x^2 = 2, x)`.
+    Equals,
+    /// The `°` after an angle in degrees, as in `30°`.
+    Degree,
+    /// The unit of a quantity, over the number it applies to, as in `5 km`.
+    Unit(Unit),
+    /// Converts its operand to a unit, as in `60 mph to m/s`.
+    Conversion(Unit),
+    /// The currency of an amount of money, over the amount, as in `120 USD`.
+    Currency(String),
+    /// Converts its operand to a currency, as in `120 USD to EUR`.
+    Exchange(String),
+    /// A date or time literal as written, e.g. `2026-10-18`, `14:30` or
+    /// `2026-10-18 14:30`.
+    Date(String),
+    /// A duration written as quantities of time in a row, as in `3d 4h`,
+    /// over their sum.
+    Duration,
+    /// A square bracket, around a list as in `[1, 2, 3]` or an index as in
+    /// `v[0]`.
+    Bracket(ParenType),
+    /// The `..` of a range, as in `1..10`, and the range over its ends.
+    Range,
+    /// The `->` between a lambda's parameters and its body, as in `x -> x^2`.
+    Arrow,
+    /// A list literal, over its elements chained like call arguments.
+    List,
+    /// An element of a list, over the list and the index, as in `v[0]`.
+    Index,
+    /// A lambda with the named parameters, over its body.
+    Lambda(Vec<String>),
     Whitespace,
     EOL,
     InvalidToken(String),
@@ -43,6 +123,35 @@ impl Token {
         }
     }
 
+    /// The number `digits` scaled by the prefix `suffix`, e.g. `4.7` and `k`
+    /// for 4700, or `None` if `suffix` is not a prefix.
+    pub fn new_scaled(digits: &str, suffix: &str) -> Option<Self> {
+        let num = Self::parse_number(digits)?;
+        if let Some((_, power)) = SI_PREFIXES.iter().find(|(prefix, _)| *prefix == suffix) {
+            // Parsed with the exponent so that the result is rounded once:
+            // `3.3µ` is exactly the number `3.3e-6` stands for.
+            let scaled: f64 = format!("{}e{}", digits, power).parse().ok()?;
+            let exact = match num {
+                Num::Integer(n) if *power > 0 => n.checked_mul(10i64.pow(*power as u32)),
+                _ => None,
+            };
+            return Some(Token::Number(
+                exact.map_or(Num::Float(scaled), Num::Integer),
+            ));
+        }
+        let (_, power) = BINARY_PREFIXES
+            .iter()
+            .find(|(prefix, _)| *prefix == suffix)?;
+        let scaled = match num {
+            Num::Integer(n) => n
+                .checked_mul(1 << power)
+                .map_or(Num::Float(n as f64 * 2f64.powi(*power)), Num::Integer),
+            Num::Float(x) => Num::Float(x * 2f64.powi(*power)),
+            Num::Decimal(x) => Num::Float(x.to_f64() * 2f64.powi(*power)),
+        };
+        Some(Token::Number(scaled))
+    }
+
     pub fn new_paren(value: &str) -> Self {
         if let Some(paren) = Self::parse_paren(value) {
             Token::Paren(paren)
@@ -51,6 +160,14 @@ impl Token {
         }
     }
 
+    pub fn new_bracket(value: &str) -> Self {
+        match value {
+            "[" => Token::Bracket(ParenType::OpenParen),
+            "]" => Token::Bracket(ParenType::CloseParen),
+            _ => Token::InvalidToken(value.to_owned()),
+        }
+    }
+
     pub fn new_op(value: &str) -> Self {
         if let Some(op) = Self::parse_operator(value) {
             Token::Operator(op)
@@ -59,10 +176,26 @@ impl Token {
         }
     }
 
+    pub fn new_comparison(value: &str) -> Self {
+        match value {
+            "<" => Token::Comparison(Cmp::Less),
+            "<=" => Token::Comparison(Cmp::LessEqual),
+            ">" => Token::Comparison(Cmp::Greater),
+            ">=" => Token::Comparison(Cmp::GreaterEqual),
+            "==" => Token::Comparison(Cmp::Equal),
+            "!=" => Token::Comparison(Cmp::NotEqual),
+            _ => Token::InvalidToken(value.to_owned()),
+        }
+    }
+
     pub fn new_variable(value: &str) -> Self {
         Token::Variable(value.to_owned())
     }
 
+    pub fn new_function(name: &str) -> Self {
+        Token::Function(name.to_owned())
+    }
+
     fn parse_paren(value: &str) -> Option<ParenType> {
         match value {
             "(" => Some(ParenType::OpenParen),
@@ -74,6 +207,8 @@ impl Token {
     fn parse_number(value: &str) -> Option<Num> {
         if let Ok(int_val) = value.parse::<i64>() {
             Some(Num::Integer(int_val))
+        } else if let Some(decimal) = Decimal::parse(value) {
+            Some(Num::Decimal(decimal))
         } else if let Ok(float_val) = value.parse::<f64>() {
             Some(Num::Float(float_val))
         } else {
@@ -87,6 +222,7 @@ impl Token {
             "-" => Some(Op::Sub),
             "*" => Some(Op::Mult),
             "/" => Some(Op::Div),
+            "^" => Some(Op::Pow),
             _ => None,
         }
     }
@@ -99,14 +235,48 @@ impl Display for Token {
             Token::Paren(ParenType::CloseParen) => write!(f, ")"),
             Token::Number(Num::Float(x)) => write!(f, "{}", x),
             Token::Number(Num::Integer(x)) => write!(f, "{}", x),
+            Token::Number(Num::Decimal(x)) => write!(f, "{}", x),
             Token::Operator(Op::Add) => write!(f, "+"),
             Token::Operator(Op::Sub) => write!(f, "-"),
             Token::Operator(Op::Mult) => write!(f, "*"),
             Token::Operator(Op::Div) => write!(f, "/"),
+            Token::Operator(Op::Pow) => write!(f, "^"),
+            Token::Comparison(cmp) => write!(f, "{}", cmp),
             Token::Variable(x) => write!(f, "{}", x),
+            Token::Function(x) => write!(f, "{}", x),
+            Token::Comma => write!(f, ","),
+            Token::Equals => write!(f, "="),
+            Token::Degree => write!(f, "°"),
+            Token::Unit(unit) => write!(f, "{}", unit),
+            Token::Conversion(unit) => write!(f, "to {}", unit),
+            Token::Currency(currency) => write!(f, "{}", currency),
+            Token::Exchange(currency) => write!(f, "to {}", currency),
+            Token::Date(text) => write!(f, "{}", text),
+            Token::Duration => write!(f, "duration"),
+            Token::Bracket(ParenType::OpenParen) => write!(f, "["),
+            Token::Bracket(ParenType::CloseParen) => write!(f, "]"),
+            Token::Range => write!(f, ".."),
+            Token::Arrow => write!(f, "->"),
+            Token::List => write!(f, "list"),
+            Token::Index => write!(f, "index"),
+            Token::Lambda(params) => write!(f, "({}) ->", params.join(", ")),
             Token::Whitespace => write!(f, "''"),
             Token::EOL => write!(f, "EoL"),
             Token::InvalidToken(_) => write!(f, "Invalid"),
         }
     }
 }
+
+impl Display for Cmp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Cmp::Less => "<",
+            Cmp::LessEqual => "<=",
+            Cmp::Greater => ">",
+            Cmp::GreaterEqual => ">=",
+            Cmp::Equal => "==",
+            Cmp::NotEqual => "!=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
@@ -1,4 +1,5 @@
 use super::*;
+use crate::decimal::Decimal;
 
 #[test]
 fn test_new_number() {
@@ -12,13 +13,22 @@ fn test_new_number() {
     assert_eq!(t, Token::Number(Num::Integer(-909082)));
 
     let t = Token::new_number("15.281");
-    assert_eq!(t, Token::Number(Num::Float(15.281)));
+    assert_eq!(
+        t,
+        Token::Number(Num::Decimal(Decimal::parse("15.281").unwrap()))
+    );
 
     let t = Token::new_number("0.237");
-    assert_eq!(t, Token::Number(Num::Float(0.237)));
+    assert_eq!(
+        t,
+        Token::Number(Num::Decimal(Decimal::parse("0.237").unwrap()))
+    );
 
     let t = Token::new_number("10.");
-    assert_eq!(t, Token::Number(Num::Float(10.0)));
+    assert_eq!(
+        t,
+        Token::Number(Num::Decimal(Decimal::parse("10.").unwrap()))
+    );
 }
 
 #[test]
@@ -36,6 +46,31 @@ fn test_new_number_invalid() {
     assert_eq!(t, Token::InvalidToken("+".to_owned()));
 }
 
+#[test]
+fn test_new_scaled() {
+    let t = Token::new_scaled("4.7", "k");
+    assert_eq!(t, Some(Token::Number(Num::Float(4700.0))));
+
+    let t = Token::new_scaled("3", "G");
+    assert_eq!(t, Some(Token::Number(Num::Integer(3_000_000_000))));
+
+    let t = Token::new_scaled("3.3", "µ");
+    assert_eq!(t, Some(Token::Number(Num::Float(3.3e-6))));
+
+    let t = Token::new_scaled("2", "Ki");
+    assert_eq!(t, Some(Token::Number(Num::Integer(2048))));
+
+    let t = Token::new_scaled("0.5", "Mi");
+    assert_eq!(t, Some(Token::Number(Num::Float(524288.0))));
+
+    // Too large for an integer.
+    let t = Token::new_scaled("100", "E");
+    assert_eq!(t, Some(Token::Number(Num::Float(1e20))));
+
+    assert_eq!(Token::new_scaled("1", "km"), None);
+    assert_eq!(Token::new_scaled("1", "h"), None);
+}
+
 #[test]
 fn test_new_op() {
     let t = Token::new_op("+");
@@ -0,0 +1,546 @@
+//! Physical units and quantities.
+//!
+//! A quantity is a number written with a unit, as in `5 km` or `9.81 m/s^2`.
+//! Every unit reduces to a scale and a dimension: the powers of the SI base
+//! units it is made of. Quantities can be added only to quantities of the
+//! same dimension, and multiplying or dividing them combines their units.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt::Display;
+
+use crate::{
+    datetime,
+    error::{CalcError, ErrorKind},
+    format_value,
+    tokens::Op,
+    value::Value,
+};
+
+/// Powers of the SI base units, in the order metre, kilogram, second,
+/// ampere, kelvin, mole, candela.
+pub type Dimension = [i8; 7];
+
+const fn dimension(m: i8, kg: i8, s: i8, a: i8, k: i8, mol: i8, cd: i8) -> Dimension {
+    [m, kg, s, a, k, mol, cd]
+}
+
+const LENGTH: Dimension = dimension(1, 0, 0, 0, 0, 0, 0);
+const MASS: Dimension = dimension(0, 1, 0, 0, 0, 0, 0);
+/// The dimension of durations, which dates can be moved by.
+pub const TIME: Dimension = dimension(0, 0, 1, 0, 0, 0, 0);
+const CURRENT: Dimension = dimension(0, 0, 0, 1, 0, 0, 0);
+const TEMPERATURE: Dimension = dimension(0, 0, 0, 0, 1, 0, 0);
+const AMOUNT: Dimension = dimension(0, 0, 0, 0, 0, 1, 0);
+const LUMINOSITY: Dimension = dimension(0, 0, 0, 0, 0, 0, 1);
+const VOLUME: Dimension = dimension(3, 0, 0, 0, 0, 0, 0);
+const SPEED: Dimension = dimension(1, 0, -1, 0, 0, 0, 0);
+const FREQUENCY: Dimension = dimension(0, 0, -1, 0, 0, 0, 0);
+const FORCE: Dimension = dimension(1, 1, -2, 0, 0, 0, 0);
+const ENERGY: Dimension = dimension(2, 1, -2, 0, 0, 0, 0);
+const POWER: Dimension = dimension(2, 1, -3, 0, 0, 0, 0);
+const PRESSURE: Dimension = dimension(-1, 1, -2, 0, 0, 0, 0);
+const CHARGE: Dimension = dimension(0, 0, 1, 1, 0, 0, 0);
+const VOLTAGE: Dimension = dimension(2, 1, -3, -1, 0, 0, 0);
+const RESISTANCE: Dimension = dimension(2, 1, -3, -2, 0, 0, 0);
+const CAPACITANCE: Dimension = dimension(-2, -1, 4, 2, 0, 0, 0);
+const INDUCTANCE: Dimension = dimension(2, 1, -2, -2, 0, 0, 0);
+const FLUX_DENSITY: Dimension = dimension(0, 1, -2, -1, 0, 0, 0);
+const FLUX: Dimension = dimension(2, 1, -2, -1, 0, 0, 0);
+const CONDUCTANCE: Dimension = dimension(-2, -1, 3, 2, 0, 0, 0);
+
+/// A unit that can be written by name.
+struct Definition {
+    symbol: &'static str,
+    /// The size of the unit in SI base units.
+    scale: f64,
+    dimension: Dimension,
+    /// Whether SI prefixes may be put in front, as in `km` or `mA`.
+    prefixes: bool,
+}
+
+const fn si(symbol: &'static str, scale: f64, dimension: Dimension) -> Definition {
+    Definition {
+        symbol,
+        scale,
+        dimension,
+        prefixes: true,
+    }
+}
+
+const fn other(symbol: &'static str, scale: f64, dimension: Dimension) -> Definition {
+    Definition {
+        symbol,
+        scale,
+        dimension,
+        prefixes: false,
+    }
+}
+
+/// The units, looked up before prefixes are considered so that `min` is a
+/// minute and `Pa` a pascal. Temperatures are in kelvin only, since scales
+/// with an offset such as Celsius do not multiply.
+const UNITS: &[Definition] = &[
+    si("m", 1.0, LENGTH),
+    si("g", 1e-3, MASS),
+    si("s", 1.0, TIME),
+    si("A", 1.0, CURRENT),
+    si("K", 1.0, TEMPERATURE),
+    si("mol", 1.0, AMOUNT),
+    si("cd", 1.0, LUMINOSITY),
+    si("Hz", 1.0, FREQUENCY),
+    si("N", 1.0, FORCE),
+    si("J", 1.0, ENERGY),
+    si("W", 1.0, POWER),
+    si("Pa", 1.0, PRESSURE),
+    si("C", 1.0, CHARGE),
+    si("V", 1.0, VOLTAGE),
+    si("ohm", 1.0, RESISTANCE),
+    si("F", 1.0, CAPACITANCE),
+    si("H", 1.0, INDUCTANCE),
+    si("T", 1.0, FLUX_DENSITY),
+    si("Wb", 1.0, FLUX),
+    si("S", 1.0, CONDUCTANCE),
+    si("L", 1e-3, VOLUME),
+    si("eV", 1.602176634e-19, ENERGY),
+    si("Wh", 3600.0, ENERGY),
+    si("Ah", 3600.0, CHARGE),
+    si("cal", 4.184, ENERGY),
+    si("bar", 1e5, PRESSURE),
+    other("min", 60.0, TIME),
+    other("h", 3600.0, TIME),
+    other("d", 86400.0, TIME),
+    other("wk", 604800.0, TIME),
+    other("yr", 31557600.0, TIME),
+    other("in", 0.0254, LENGTH),
+    other("ft", 0.3048, LENGTH),
+    other("yd", 0.9144, LENGTH),
+    other("mi", 1609.344, LENGTH),
+    other("nmi", 1852.0, LENGTH),
+    other("au", 149597870700.0, LENGTH),
+    other("ly", 9460730472580800.0, LENGTH),
+    other("t", 1000.0, MASS),
+    other("lb", 0.45359237, MASS),
+    other("oz", 0.028349523125, MASS),
+    other("mph", 0.44704, SPEED),
+    other("kn", 1852.0 / 3600.0, SPEED),
+    other("gal", 0.003785411784, VOLUME),
+    other("atm", 101325.0, PRESSURE),
+    other("psi", 6894.757293168361, PRESSURE),
+    other("mmHg", 133.322387415, PRESSURE),
+    other("hp", 745.6998715822702, POWER),
+];
+
+const PREFIXES: &[(&str, f64)] = &[
+    ("E", 1e18),
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("h", 1e2),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("µ", 1e-6),
+    ("u", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+    ("f", 1e-15),
+    ("a", 1e-18),
+];
+
+/// Units a product or quotient of metric units is given in when it matches
+/// their dimension, so that `kg*m/s^2` is shown as `N` and `V/kohm` as `A`.
+const COHERENT: &[&str] = &[
+    "m", "kg", "s", "A", "K", "mol", "N", "J", "W", "Pa", "C", "V", "ohm", "F", "H", "T", "Wb", "S",
+];
+
+/// The scale and dimension of the unit called `symbol`, possibly prefixed,
+/// and whether it is metric.
+fn definition(symbol: &str) -> Option<(f64, Dimension, bool)> {
+    if let Some(unit) = UNITS.iter().find(|unit| unit.symbol == symbol) {
+        return Some((unit.scale, unit.dimension, unit.prefixes));
+    }
+    PREFIXES.iter().find_map(|(prefix, factor)| {
+        let base = symbol.strip_prefix(prefix)?;
+        UNITS
+            .iter()
+            .find(|unit| unit.prefixes && unit.symbol == base)
+            .map(|unit| (factor * unit.scale, unit.dimension, true))
+    })
+}
+
+/// Whether `symbol` names a unit, e.g. `km` or `mph`.
+pub fn is_unit(symbol: &str) -> bool {
+    definition(symbol).is_some()
+}
+
+/// A product of named units raised to whole powers, e.g. `m/s^2`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Unit {
+    factors: Vec<(String, i32)>,
+}
+
+impl Unit {
+    /// The unit built from `(symbol, power)` factors, with repeated symbols
+    /// merged. Fails on the first symbol that is not a unit.
+    pub fn new(factors: Vec<(String, i32)>) -> Result<Self, CalcError> {
+        let mut unit = Unit::default();
+        for (symbol, power) in factors {
+            if !is_unit(&symbol) {
+                return Err(unknown_unit(&symbol));
+            }
+            match unit.factors.iter_mut().find(|(known, _)| *known == symbol) {
+                Some((_, total)) => *total += power,
+                None => unit.factors.push((symbol, power)),
+            }
+        }
+        unit.factors.retain(|(_, power)| *power != 0);
+        Ok(unit)
+    }
+
+    /// Parses a unit as it is displayed, e.g. `m^3/(kg*s^2)` or `1/mol`.
+    pub fn parse(text: &str) -> Result<Self, CalcError> {
+        let mut factors = vec![];
+        for (i, part) in text.split('/').enumerate() {
+            let sign = if i == 0 { 1 } else { -1 };
+            let part = part.trim();
+            let part = match part.strip_prefix('(').and_then(|p| p.strip_suffix(')')) {
+                Some(inner) => inner,
+                None => part,
+            };
+            if i == 0 && part == "1" {
+                continue;
+            }
+            for factor in part.split('*') {
+                let (symbol, power) = match factor.split_once('^') {
+                    Some((symbol, power)) => {
+                        let power = power
+                            .trim()
+                            .parse::<i32>()
+                            .map_err(|_| unknown_unit(text))?;
+                        (symbol.trim(), power)
+                    }
+                    None => (factor.trim(), 1),
+                };
+                factors.push((symbol.to_owned(), sign * power));
+            }
+        }
+        Self::new(factors)
+    }
+
+    /// This unit with `prefix` in front, as `kV` is `V` with `k`, if it is a
+    /// single prefixable unit that has no prefix yet.
+    pub fn prefixed(&self, prefix: &str) -> Option<Unit> {
+        let [(symbol, 1)] = self.factors.as_slice() else {
+            return None;
+        };
+        let prefixable = UNITS
+            .iter()
+            .any(|unit| unit.prefixes && unit.symbol == symbol);
+        let symbol = format!("{}{}", prefix, symbol);
+        // A table entry such as `min` would be found before the prefix.
+        let shadowed = UNITS.iter().any(|unit| unit.symbol == symbol);
+        let known = prefix.is_empty() || PREFIXES.iter().any(|(known, _)| *known == prefix);
+        (prefixable && known && !shadowed).then(|| Unit {
+            factors: vec![(symbol, 1)],
+        })
+    }
+
+    pub fn factors(&self) -> &[(String, i32)] {
+        &self.factors
+    }
+
+    /// The size of the unit in SI base units.
+    pub fn scale(&self) -> f64 {
+        let (above, below) = self.scales();
+        above / below
+    }
+
+    /// The scales of the factors with positive and with negative powers,
+    /// kept apart so that conversions round once: `km/h` is `1000 / 3600`.
+    fn scales(&self) -> (f64, f64) {
+        let part = |sign: i32| -> f64 {
+            self.factors
+                .iter()
+                .filter(|(_, power)| power.signum() == sign)
+                .map(|(symbol, power)| scale_of(symbol).powi(power.abs()))
+                .product()
+        };
+        (part(1), part(-1))
+    }
+
+    /// The factor that turns a number of this unit into a number of `other`.
+    fn ratio(&self, other: &Unit) -> f64 {
+        let (above, below) = self.scales();
+        let (other_above, other_below) = other.scales();
+        (above * other_below) / (below * other_above)
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        let mut total = [0; 7];
+        for (symbol, power) in self.factors.iter() {
+            for (sum, base) in total.iter_mut().zip(dimension_of(symbol)) {
+                *sum += base * *power as i8;
+            }
+        }
+        total
+    }
+
+    /// Whether the unit is a single unit of time such as `h`, the kind that
+    /// makes up a duration like `3d 4h`.
+    pub fn is_time(&self) -> bool {
+        matches!(self.factors.as_slice(), [(_, 1)]) && self.dimension() == TIME
+    }
+
+    /// Whether the unit has no dimension, as `km/m` does.
+    pub fn is_dimensionless(&self) -> bool {
+        self.dimension() == [0; 7]
+    }
+
+    /// The unit times `other` raised to `sign`, and the factor the product of
+    /// the values must be multiplied by. A unit of `other` with the same
+    /// dimension as one already present is converted to it, so `km * m` is
+    /// `km^2` and `km/h * min` is `km`.
+    pub(crate) fn combine(&self, other: &Unit, sign: i32) -> (Unit, f64) {
+        let mut factors = self.factors.clone();
+        let mut factor = 1.0;
+        for (symbol, power) in other.factors.iter() {
+            let power = sign * power;
+            let same = factors
+                .iter()
+                .position(|(known, _)| known == symbol)
+                .or_else(|| {
+                    factors
+                        .iter()
+                        .position(|(known, _)| dimension_of(known) == dimension_of(symbol))
+                });
+            match same {
+                Some(i) => {
+                    factor *= (scale_of(symbol) / scale_of(&factors[i].0)).powi(power);
+                    factors[i].1 += power;
+                }
+                None => factors.push((symbol.clone(), power)),
+            }
+        }
+        factors.retain(|(_, power)| *power != 0);
+        (Unit { factors }, factor)
+    }
+
+    /// The unit raised to `power`, if every factor keeps a whole power.
+    fn powf(&self, power: f64) -> Option<Unit> {
+        let factors = self
+            .factors
+            .iter()
+            .map(|(symbol, own)| {
+                let total = *own as f64 * power;
+                (total == total.round()).then(|| (symbol.clone(), total as i32))
+            })
+            .collect::<Option<Vec<(String, i32)>>>()?;
+        Some(Unit { factors })
+    }
+}
+
+fn scale_of(symbol: &str) -> f64 {
+    definition(symbol).map_or(1.0, |(scale, _, _)| scale)
+}
+
+fn dimension_of(symbol: &str) -> Dimension {
+    definition(symbol).map_or([0; 7], |(_, dimension, _)| dimension)
+}
+
+fn is_metric(symbol: &str) -> bool {
+    definition(symbol).is_some_and(|(_, _, metric)| metric)
+}
+
+fn unknown_unit(symbol: &str) -> CalcError {
+    CalcError::new(ErrorKind::UnknownUnit, &format!("Unknown unit: {}", symbol))
+}
+
+/// Writes the factors with positive powers, then those with negative powers
+/// after a `/`: `kg*m/s^2`, `m^3/(kg*s^2)`, `1/mol`.
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let part = |factors: Vec<(&String, i32)>| -> String {
+            let factors: Vec<String> = factors
+                .into_iter()
+                .map(|(symbol, power)| match power {
+                    1 => symbol.clone(),
+                    _ => format!("{}^{}", symbol, power),
+                })
+                .collect();
+            factors.join("*")
+        };
+        let above: Vec<(&String, i32)> = self
+            .factors
+            .iter()
+            .filter(|(_, power)| *power > 0)
+            .map(|(symbol, power)| (symbol, *power))
+            .collect();
+        let below: Vec<(&String, i32)> = self
+            .factors
+            .iter()
+            .filter(|(_, power)| *power < 0)
+            .map(|(symbol, power)| (symbol, -power))
+            .collect();
+        let numerator = if above.is_empty() {
+            "1".to_owned()
+        } else {
+            part(above)
+        };
+        match below.len() {
+            0 => write!(f, "{}", numerator),
+            1 => write!(f, "{}/{}", numerator, part(below)),
+            _ => write!(f, "{}/({})", numerator, part(below)),
+        }
+    }
+}
+
+/// A number of some unit, e.g. `5.3 km`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    pub fn new(value: f64, unit: Unit) -> Self {
+        Quantity { value, unit }
+    }
+
+    /// The same quantity in `unit`, which must have the same dimension.
+    pub fn convert(&self, unit: &Unit) -> Result<Quantity, CalcError> {
+        if self.unit.dimension() != unit.dimension() {
+            return Err(CalcError::new(
+                ErrorKind::DimensionMismatch,
+                &format!("Cannot convert {} to {}", describe(&self.unit), unit),
+            ));
+        }
+        Ok(Quantity::new(
+            self.value * self.unit.ratio(unit),
+            unit.clone(),
+        ))
+    }
+
+    /// A plain number when the unit has no dimension, and the quantity in a
+    /// named SI unit when it is a product of metric units matching one.
+    pub fn simplified(self) -> Value {
+        if self.unit.is_dimensionless() {
+            return Value::Number(self.value * self.unit.scale());
+        }
+        let metric = self.unit.factors.len() > 1
+            && self
+                .unit
+                .factors
+                .iter()
+                .all(|(symbol, _)| is_metric(symbol));
+        if metric {
+            let dimension = self.unit.dimension();
+            if let Some(symbol) = COHERENT
+                .iter()
+                .find(|symbol| dimension_of(symbol) == dimension)
+            {
+                let value = self.value * self.unit.scale();
+                let unit = Unit {
+                    factors: vec![(symbol.to_string(), 1)],
+                };
+                return Value::Quantity(Quantity::new(value, unit));
+            }
+        }
+        Value::Quantity(self)
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", format_value(self.value), self.unit)
+    }
+}
+
+impl TryFrom<Value> for Quantity {
+    type Error = CalcError;
+
+    /// A number is a quantity without a unit.
+    fn try_from(value: Value) -> Result<Self, CalcError> {
+        match value {
+            Value::Number(x) => Ok(Quantity::new(x, Unit::default())),
+            Value::Integer(n) => Ok(Quantity::new(n as f64, Unit::default())),
+            Value::Decimal(x) => Ok(Quantity::new(x.to_f64(), Unit::default())),
+            Value::Quantity(quantity) => Ok(quantity),
+            Value::Duration(duration) => Ok(datetime::as_quantity(&duration)),
+            value => Err(CalcError::new(
+                ErrorKind::TypeMismatch,
+                &format!("Expected a quantity but got {}", value.type_name()),
+            )),
+        }
+    }
+}
+
+/// How a unit is named in errors, where a plain number has none.
+fn describe(unit: &Unit) -> String {
+    if unit.factors.is_empty() {
+        "a number".to_owned()
+    } else {
+        unit.to_string()
+    }
+}
+
+/// Applies `oper` to two values of which at least one is a quantity.
+pub fn operate(oper: &Op, left: Value, right: Value) -> Result<Value, CalcError> {
+    let (left, right) = (Quantity::try_from(left)?, Quantity::try_from(right)?);
+    let result = match oper {
+        Op::Add | Op::Sub => {
+            if left.unit.dimension() != right.unit.dimension() {
+                let verb = if *oper == Op::Add { "add" } else { "subtract" };
+                return Err(CalcError::new(
+                    ErrorKind::DimensionMismatch,
+                    &format!(
+                        "Cannot {} {} and {}",
+                        verb,
+                        describe(&left.unit),
+                        describe(&right.unit)
+                    ),
+                ));
+            }
+            let right = right.value * right.unit.ratio(&left.unit);
+            let value = match oper {
+                Op::Add => left.value + right,
+                _ => left.value - right,
+            };
+            Quantity::new(value, left.unit)
+        }
+        Op::Mult => {
+            let (unit, factor) = left.unit.combine(&right.unit, 1);
+            Quantity::new(left.value * right.value * factor, unit)
+        }
+        Op::Div => {
+            if right.value == 0.0 {
+                Err(CalcError::new(ErrorKind::DivideByZero, "Divide by Zero"))?
+            }
+            let (unit, factor) = left.unit.combine(&right.unit, -1);
+            Quantity::new(left.value / right.value * factor, unit)
+        }
+        Op::Pow => {
+            if !right.unit.is_dimensionless() {
+                return Err(CalcError::new(
+                    ErrorKind::DimensionMismatch,
+                    &format!("An exponent cannot have a unit, but got {}", right.unit),
+                ));
+            }
+            let power = right.value * right.unit.scale();
+            let unit = left.unit.powf(power).ok_or_else(|| {
+                CalcError::new(
+                    ErrorKind::DimensionMismatch,
+                    &format!(
+                        "Cannot raise {} to the power {}",
+                        left.unit,
+                        format_value(power)
+                    ),
+                )
+            })?;
+            Quantity::new(left.value.powf(power), unit)
+        }
+    };
+    Ok(result.simplified())
+}
@@ -0,0 +1,132 @@
+use crate::{calculator::Calculator, constants::CONSTANTS};
+
+use super::*;
+
+fn eval(expression: &str) -> Result<String, CalcError> {
+    Calculator::new()
+        .eval(expression)
+        .map(|value| value.to_string())
+}
+
+#[test]
+fn names_and_prefixes() {
+    for symbol in [
+        "m", "km", "mA", "kWh", "MeV", "hPa", "mohm", "ug", "min", "mph",
+    ] {
+        assert!(is_unit(symbol), "{} is a unit", symbol);
+    }
+    for symbol in ["x", "kmi", "Mmph", "mm2", "pi", ""] {
+        assert!(!is_unit(symbol), "{} is not a unit", symbol);
+    }
+    // Whole names win over prefixes: `min` is a minute, not a milli-inch,
+    // and `Pa` a pascal rather than a peta-year.
+    assert_eq!(Unit::parse("min").unwrap().scale(), 60.0);
+    assert_eq!(Unit::parse("Pa").unwrap().dimension(), PRESSURE);
+    assert_eq!(Unit::parse("ms").unwrap().scale(), 1e-3);
+    assert_eq!(Unit::parse("kg").unwrap().scale(), 1.0);
+}
+
+#[test]
+fn parse_and_display() {
+    for text in [
+        "m/s^2",
+        "kg*m^2/s^2",
+        "m^3/(kg*s^2)",
+        "1/mol",
+        "N/A^2",
+        "J*s",
+    ] {
+        assert_eq!(Unit::parse(text).unwrap().to_string(), text);
+    }
+    assert_eq!(Unit::parse("J/mol/K").unwrap().to_string(), "J/(mol*K)");
+    assert_eq!(Unit::parse("m*m").unwrap().to_string(), "m^2");
+    let error = Unit::parse("m/furlong").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnknownUnit);
+    assert_eq!(error.message, "Unknown unit: furlong");
+
+    for constant in CONSTANTS
+        .iter()
+        .filter(|constant| !constant.unit.is_empty())
+    {
+        assert!(
+            matches!(constant.to_value(), Value::Quantity(_)),
+            "{} has a unit",
+            constant.name
+        );
+    }
+}
+
+#[test]
+fn quantities() {
+    assert_eq!(eval("5 km + 300 m").unwrap(), "5.3 km");
+    assert_eq!(eval("60 mph to m/s").unwrap(), "26.8224 m/s");
+    assert_eq!(eval("9.81 m/s^2 * 3 kg").unwrap(), "29.43 N");
+    assert_eq!(eval("1 m/s in km/h").unwrap(), "3.6 km/h");
+    assert_eq!(eval("12 in to cm").unwrap(), "30.48 cm");
+    assert_eq!(eval("60 km/h * 30 min").unwrap(), "30.0 km");
+    assert_eq!(eval("10 V / 2 kohm").unwrap(), "0.005 A");
+    assert_eq!(eval("(3 m)^2 - 5 m^2").unwrap(), "4.0 m^2");
+    assert_eq!(eval("(9 m^2)^0.5").unwrap(), "3.0 m");
+    assert_eq!(eval("-2 m/s * 3").unwrap(), "-6.0 m/s");
+    assert_eq!(eval("8 J/(mol*K) * 300 K").unwrap(), "2400.0 J/mol");
+    // Units cancel down to plain numbers.
+    assert_eq!(eval("6 km / 300 m").unwrap(), "20.0");
+    assert_eq!(eval("c * 2 s to km").unwrap(), "599584.916 km");
+}
+
+#[test]
+fn dimensions_must_match() {
+    let error = eval("3 m + 2 s").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DimensionMismatch);
+    assert_eq!(error.message, "Cannot add m and s");
+    assert_eq!(error.span, Some((0, 9)));
+
+    let error = eval("1 - 2 kg").unwrap_err();
+    assert_eq!(error.message, "Cannot subtract a number and kg");
+    let error = eval("60 mph to kg").unwrap_err();
+    assert_eq!(error.message, "Cannot convert mph to kg");
+    let error = eval("2 ^ 3 s").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DimensionMismatch);
+    let error = eval("(2 m)^0.5").unwrap_err();
+    assert_eq!(error.message, "Cannot raise m to the power 0.5");
+    let error = eval("sqrt(4 m^2)").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::TypeMismatch);
+}
+
+#[test]
+fn units_follow_numbers() {
+    let mut calculator = Calculator::new();
+    calculator.set_var("s", 4.0).unwrap();
+    calculator.set_var("x", 2.0).unwrap();
+    // After a number `s` is a second, elsewhere it is the variable.
+    assert_eq!(calculator.eval("3 s").unwrap().to_string(), "3.0 s");
+    assert_eq!(calculator.eval("3 * s"), Ok(Value::Number(12.0)));
+    // A unit only continues through `*` and `/` into another unit name.
+    assert_eq!(calculator.eval("3 m * x").unwrap().to_string(), "6.0 m");
+    assert_eq!(calculator.eval("3 m / 2 s").unwrap().to_string(), "1.5 m/s");
+
+    let error = calculator.eval("3 m to furlong").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnknownUnit);
+    assert_eq!(error.span, Some((7, 14)));
+    let error = calculator.eval("3 x").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InvalidExpression);
+}
+
+#[test]
+fn prefixes_and_units_after_numbers() {
+    // A prefix written against the number scales it, unless the letters
+    // also name a unit: `5m` is five metres, like `5 m`, `5mm` and `5km`.
+    assert_eq!(eval("5m"), Ok("5.0 m".to_owned()));
+    assert_eq!(eval("5 m"), Ok("5.0 m".to_owned()));
+    assert_eq!(eval("5km + 300m"), Ok("5.3 km".to_owned()));
+    assert_eq!(eval("4.7k * 2"), Ok("9400.0".to_owned()));
+    assert_eq!(eval("5n * 2"), Ok("0.00000001".to_owned()));
+    // Letters that are not a whole prefix are read as a unit instead.
+    assert_eq!(eval("5mm"), Ok("5.0 mm".to_owned()));
+    assert_eq!(eval("5min"), Ok("5.0 min".to_owned()));
+    assert_eq!(eval("4.7kohm"), Ok("4.7 kohm".to_owned()));
+    assert_eq!(eval("3.3 µF"), Ok("3.3 µF".to_owned()));
+    // `Pi` is the binary prefix, and `pi` needs a space or `*`.
+    assert_eq!(eval("1Pi"), Ok("1125899906842624.0".to_owned()));
+    assert!(eval("2pi").is_err());
+}
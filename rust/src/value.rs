@@ -0,0 +1,136 @@
+use std::fmt::Display;
+
+use crate::{
+    datetime::{DateTime, Duration},
+    decimal::Decimal,
+    format_value,
+    money::Money,
+    number_theory,
+    units::Quantity,
+};
+
+/// The result of evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    /// An exact integer from the number theory functions, e.g. `fib(100)`,
+    /// which keeps every digit past 2^53 where floats lose them.
+    Integer(i128),
+    /// An exact decimal, from decimal arithmetic. Boxed like money.
+    Decimal(Box<Decimal>),
+    /// A number with a unit, e.g. `5 km`.
+    Quantity(Quantity),
+    /// An amount in a currency, e.g. `120 USD`. Boxed to keep values, which
+    /// every level of evaluation holds, small.
+    Money(Box<Money>),
+    /// A date, a moment or a time of day, e.g. `2026-10-18`.
+    Date(DateTime),
+    /// A length of time, e.g. `3d 4h`.
+    Duration(Duration),
+    /// A list of values, e.g. `[1, 2, 3]` or `1..3`.
+    List(Vec<Value>),
+    /// A prime factorization as primes and their powers, e.g. `2^3 * 5`,
+    /// which stands for the number it multiplies out to.
+    Factors(Vec<(u64, u32)>),
+}
+
+impl Value {
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(x) => Some(*x),
+            Value::Integer(n) => Some(*n as f64),
+            Value::Decimal(x) => Some(x.to_f64()),
+            Value::Factors(factors) => Some(number_theory::product(factors) as f64),
+            _ => None,
+        }
+    }
+
+    /// Name of the value's type as reported in JSON output.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(x) if x.is_finite() && *x == x.trunc() && x.abs() <= 2f64.powi(53) => {
+                "integer"
+            }
+            Value::Number(_) => "float",
+            Value::Integer(_) => "integer",
+            Value::Decimal(_) => "decimal",
+            Value::Quantity(_) => "quantity",
+            Value::Money(_) => "money",
+            Value::Date(_) => "date",
+            Value::Duration(_) => "duration",
+            Value::List(_) => "list",
+            Value::Factors(_) => "factors",
+        }
+    }
+
+    /// The type name with its article, as in "an integer", for error messages.
+    pub fn described(&self) -> String {
+        let name = self.type_name();
+        let article = if name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+            "an"
+        } else {
+            "a"
+        };
+        format!("{} {}", article, name)
+    }
+}
+
+impl Value {
+    /// The value with an exact decimal turned into a float, for operations
+    /// that only have a floating-point form.
+    pub fn inexact(self) -> Value {
+        match self {
+            Value::Integer(n) => Value::Number(n as f64),
+            Value::Decimal(x) => Value::Number(x.to_f64()),
+            Value::Factors(factors) => Value::Number(number_theory::product(&factors) as f64),
+            value => value,
+        }
+    }
+
+    /// The value with an exact integer turned into a decimal, for arithmetic
+    /// that keeps it exact.
+    pub fn exact(self) -> Value {
+        match self {
+            Value::Integer(n) => Value::Decimal(Box::new(Decimal::new(n, 0))),
+            value => value,
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(x) => write!(f, "{}", format_value(*x)),
+            Value::Integer(n) => match number_theory::exact_float(*n) {
+                Some(x) => write!(f, "{}", format_value(x)),
+                None => write!(f, "{}", n),
+            },
+            Value::Decimal(x) => write!(f, "{}", x),
+            Value::Quantity(quantity) => write!(f, "{}", quantity),
+            Value::Money(money) => write!(f, "{}", money),
+            Value::Date(date) => write!(f, "{}", date),
+            Value::Duration(duration) => write!(f, "{}", duration),
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Value::Factors(factors) if factors.is_empty() => write!(f, "1"),
+            Value::Factors(factors) => {
+                let factors: Vec<String> = factors
+                    .iter()
+                    .map(|(p, count)| match count {
+                        1 => p.to_string(),
+                        count => format!("{}^{}", p, count),
+                    })
+                    .collect();
+                write!(f, "{}", factors.join(" * "))
+            }
+        }
+    }
+}
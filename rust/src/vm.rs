@@ -0,0 +1,290 @@
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    ast::AST,
+    decimal::Decimal,
+    environment::{Environment, Scope},
+    error::{CalcError, ErrorKind, Span},
+    forms,
+    printer::infix,
+    tokens::{Cmp, Op, Token},
+    value::Value,
+};
+
+/// One step of a [`Program`]. Operands index into the program's tables.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Instruction {
+    /// Push `constants[index]`.
+    Const(usize),
+    /// Push the argument bound to a parameter slot.
+    Load(usize),
+    /// Push the variable `names[index]` from the environment.
+    Global(usize),
+    /// Pop the right then the left operand and push the result.
+    Binary(Op),
+    /// Pop the right then the left operand and push 1 if they compare as
+    /// `cmp` says and 0 if not.
+    Compare(Cmp),
+    /// Pop `argc` arguments and push the result of calling `names[index]`.
+    Call(usize, usize),
+    /// Push the result of walking `trees[index]`, for special forms that
+    /// take their arguments unevaluated.
+    Tree(usize),
+}
+
+/// An expression lowered to a flat instruction sequence for a stack machine.
+#[derive(Debug)]
+pub struct Program {
+    code: Vec<Instruction>,
+    spans: Vec<Option<Span>>,
+    constants: Vec<Value>,
+    /// The literals among `constants` as written, used in decimal mode.
+    exact: Vec<Option<Decimal>>,
+    names: Vec<String>,
+    trees: Vec<AST>,
+    params: Vec<String>,
+    max_stack: usize,
+}
+
+impl Program {
+    /// Lowers `tree`, resolving the names in `params` to argument slots.
+    pub fn compile(tree: &AST, params: &[String]) -> Result<Self, CalcError> {
+        let mut program = Program {
+            code: vec![],
+            spans: vec![],
+            constants: vec![],
+            exact: vec![],
+            names: vec![],
+            trees: vec![],
+            params: params.to_vec(),
+            max_stack: 0,
+        };
+        program.emit_tree(tree, params, 0)?;
+        Ok(program)
+    }
+
+    pub fn code(&self) -> &[Instruction] {
+        &self.code
+    }
+
+    /// Emits the instructions for `tree`, given `depth` values are already on
+    /// the stack, and tracks the deepest the stack gets.
+    fn emit_tree(&mut self, tree: &AST, params: &[String], depth: usize) -> Result<(), CalcError> {
+        self.max_stack = self.max_stack.max(depth + 1);
+        let slot = |name: &str| params.iter().position(|param| param == name);
+        match tree.value() {
+            Token::Number(num) => {
+                let value = AST::evaluate_number(num, false)?;
+                let exact = match AST::evaluate_number(num, true)? {
+                    Value::Decimal(x) => Some(*x),
+                    _ => None,
+                };
+                let index = self.constant_exactly(value, exact);
+                self.emit(Instruction::Const(index), tree.span());
+            }
+            Token::Variable(name) => match slot(name) {
+                Some(index) => self.emit(Instruction::Load(index), tree.span()),
+                None => {
+                    let index = self.name(name);
+                    self.emit(Instruction::Global(index), tree.span());
+                }
+            },
+            // Long sums and products are emitted a term at a time, keeping the
+            // running result on the stack.
+            Token::Operator(_) if tree.chained() => {
+                let (first, links) = tree.chain();
+                self.emit_tree(first, params, depth)?;
+                for (link, oper, right) in links {
+                    self.emit_tree(right, params, depth + 1)?;
+                    self.emit(Instruction::Binary(oper.clone()), link.span());
+                }
+            }
+            Token::Operator(oper) => match (tree.left(), tree.right()) {
+                (Some(left), Some(right)) => {
+                    self.emit_tree(left, params, depth)?;
+                    self.emit_tree(right, params, depth + 1)?;
+                    self.emit(Instruction::Binary(oper.clone()), tree.span());
+                }
+                _ => return Err(CalcError::invalid_expression()),
+            },
+            Token::Comparison(cmp) => match (tree.left(), tree.right()) {
+                (Some(left), Some(right)) => {
+                    self.emit_tree(left, params, depth)?;
+                    self.emit_tree(right, params, depth + 1)?;
+                    self.emit(Instruction::Compare(cmp.clone()), tree.span());
+                }
+                _ => return Err(CalcError::invalid_expression()),
+            },
+            // A number with a unit is a constant quantity, and so are dates
+            // and durations.
+            Token::Unit(_) | Token::Date(_) | Token::Duration => {
+                let value = tree.eval(&HashMap::<String, f64>::new())?;
+                let index = self.constant(value);
+                self.emit(Instruction::Const(index), tree.span());
+            }
+            // Angles in degrees depend on the angle mode at run time, money
+            // on the exchange rates, and conversions and lists are rare
+            // enough to walk.
+            Token::Degree
+            | Token::Conversion(_)
+            | Token::Currency(_)
+            | Token::Exchange(_)
+            | Token::List
+            | Token::Index
+            | Token::Range
+            | Token::Lambda(_) => {
+                self.trees.push(tree.clone());
+                self.emit(Instruction::Tree(self.trees.len() - 1), tree.span());
+            }
+            Token::Function(name) => {
+                let args = tree.arguments();
+                if forms::is_form(name) {
+                    self.trees.push(tree.clone());
+                    self.emit(Instruction::Tree(self.trees.len() - 1), tree.span());
+                    return Ok(());
+                }
+                match (slot(name), args.as_slice()) {
+                    // A parameter followed by parentheses multiplies, as in `x(2)`.
+                    (Some(index), [arg]) => {
+                        self.emit(Instruction::Load(index), tree.span());
+                        self.emit_tree(arg, params, depth + 1)?;
+                        self.emit(Instruction::Binary(Op::Mult), tree.span());
+                    }
+                    (Some(_), _) => return Err(CalcError::invalid_expression()),
+                    (None, _) => {
+                        for (i, arg) in args.iter().enumerate() {
+                            self.emit_tree(arg, params, depth + i)?;
+                        }
+                        let index = self.name(name);
+                        self.emit(Instruction::Call(index, args.len()), tree.span());
+                    }
+                }
+            }
+            x => {
+                return Err(CalcError::new(
+                    ErrorKind::InvalidExpression,
+                    &format!("Cannot evaluate {}", x),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, instruction: Instruction, span: Option<Span>) {
+        self.code.push(instruction);
+        self.spans.push(span);
+    }
+
+    fn constant(&mut self, value: Value) -> usize {
+        self.constant_exactly(value, None)
+    }
+
+    /// A constant that is `exact` in decimal mode, as literals are.
+    fn constant_exactly(&mut self, value: Value, exact: Option<Decimal>) -> usize {
+        match self
+            .constants
+            .iter()
+            .zip(self.exact.iter())
+            .position(|(constant, decimal)| *constant == value && *decimal == exact)
+        {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.exact.push(exact);
+                self.constants.len() - 1
+            }
+        }
+    }
+
+    fn name(&mut self, name: &str) -> usize {
+        match self.names.iter().position(|known| known == name) {
+            Some(index) => index,
+            None => {
+                self.names.push(name.to_owned());
+                self.names.len() - 1
+            }
+        }
+    }
+
+    /// Executes the program with `args` bound to the parameter slots.
+    pub fn run(&self, env: &dyn Environment, args: &[Value]) -> Result<Value, CalcError> {
+        let mut stack: Vec<Value> = Vec::with_capacity(self.max_stack);
+        for (pc, instruction) in self.code.iter().enumerate() {
+            let locate = |error: CalcError| match (error.span, self.spans[pc]) {
+                (None, Some(span)) => error.with_span(span),
+                _ => error,
+            };
+            let value = match instruction {
+                Instruction::Const(index) => match (self.exact[*index], env.decimal()) {
+                    (Some(exact), Some(_)) => Value::Decimal(Box::new(exact)),
+                    _ => self.constants[*index].clone(),
+                },
+                Instruction::Load(slot) => args[*slot].clone(),
+                Instruction::Global(index) => {
+                    AST::evaluate_variable(&self.names[*index], env).map_err(locate)?
+                }
+                Instruction::Binary(oper) => {
+                    let right = stack.pop().ok_or_else(CalcError::invalid_expression)?;
+                    let left = stack.pop().ok_or_else(CalcError::invalid_expression)?;
+                    AST::evaluate_operator(oper, left, right, env).map_err(locate)?
+                }
+                Instruction::Compare(cmp) => {
+                    let right = stack.pop().ok_or_else(CalcError::invalid_expression)?;
+                    let left = stack.pop().ok_or_else(CalcError::invalid_expression)?;
+                    AST::evaluate_comparison(cmp, left, right, env).map_err(locate)?
+                }
+                Instruction::Call(index, argc) => {
+                    let values = stack.split_off(stack.len() - argc);
+                    Self::call(&self.names[*index], values, env).map_err(locate)?
+                }
+                Instruction::Tree(index) => {
+                    let bindings = self.params.iter().cloned().zip(args.iter().cloned());
+                    let scope = Scope::new(env, bindings.collect());
+                    self.trees[*index].eval(&scope)?
+                }
+            };
+            stack.push(value);
+        }
+        stack.pop().ok_or_else(CalcError::invalid_expression)
+    }
+
+    /// Calls a function, or multiplies when a variable is followed by a
+    /// parenthesized expression, matching `AST::eval`.
+    fn call(name: &str, args: Vec<Value>, env: &dyn Environment) -> Result<Value, CalcError> {
+        if let Some(function) = env.function(name) {
+            function.arity().check(args.len())?;
+            return function.call(&args, env);
+        }
+        let value = AST::evaluate_variable(name, env)?;
+        match <[Value; 1]>::try_from(args) {
+            Ok([arg]) => AST::evaluate_operator(&Op::Mult, value, arg, env),
+            Err(_) => Err(CalcError::invalid_expression()),
+        }
+    }
+}
+
+/// Lists one instruction per line with its operands resolved, e.g. `const 2.0`.
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (pc, instruction) in self.code.iter().enumerate() {
+            write!(f, "{:04} ", pc)?;
+            match instruction {
+                Instruction::Const(index) => writeln!(f, "const {}", self.constants[*index])?,
+                Instruction::Load(slot) => writeln!(f, "load {}", slot)?,
+                Instruction::Global(index) => writeln!(f, "global {}", self.names[*index])?,
+                Instruction::Binary(oper) => {
+                    writeln!(f, "binary {}", Token::Operator(oper.clone()))?
+                }
+                Instruction::Compare(cmp) => writeln!(f, "compare {}", cmp)?,
+                Instruction::Call(index, argc) => {
+                    writeln!(f, "call {} {}", self.names[*index], argc)?
+                }
+                Instruction::Tree(index) => writeln!(f, "tree {}", infix(&self.trees[*index]))?,
+            }
+        }
+        Ok(())
+    }
+}
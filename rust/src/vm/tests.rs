@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use crate::tokenize;
+
+use super::*;
+
+fn compile(expression: &str, params: &[&str]) -> Program {
+    let tree = AST::build_tree_spanned(&tokenize(expression).unwrap()).unwrap();
+    let params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+    Program::compile(&tree, &params).expect("should compile")
+}
+
+#[test]
+fn lowers_to_postfix() {
+    let program = compile("2 * x + rate", &["x"]);
+    assert_eq!(
+        program.code(),
+        &[
+            Instruction::Const(0),
+            Instruction::Load(0),
+            Instruction::Binary(Op::Mult),
+            Instruction::Global(0),
+            Instruction::Binary(Op::Add),
+        ]
+    );
+    assert_eq!(
+        program.to_string(),
+        "0000 const 2.0\n0001 load 0\n0002 binary *\n0003 global rate\n0004 binary +\n"
+    );
+}
+
+#[test]
+fn constants_and_names_are_shared() {
+    let program = compile("y * 2 + y * 2", &[]);
+    assert_eq!(program.constants, vec![Value::Integer(2)]);
+    assert_eq!(program.names, vec!["y".to_owned()]);
+}
+
+#[test]
+fn stack_depth_is_bounded() {
+    assert_eq!(compile("1", &[]).max_stack, 1);
+    assert_eq!(compile("1 + 2 * 3", &[]).max_stack, 3);
+    assert_eq!(compile("(1 + 2) * 3", &[]).max_stack, 2);
+    assert_eq!(compile("max(1, 2, 3 - 4)", &[]).max_stack, 4);
+}
+
+#[test]
+fn matches_the_tree_walker() {
+    let mut env = HashMap::new();
+    env.insert("a".to_owned(), 3.0);
+    env.insert("b".to_owned(), -0.5);
+    for expression in [
+        "1 + 2 * 3 - 4 / 8",
+        "a(2 + b) - (a - b) * 2",
+        "-(a + 1) / -b",
+        "10 - 2 - 3",
+        "a / (b + 0.5)",
+        "c + 1",
+    ] {
+        let tree = AST::build_tree_spanned(&tokenize(expression).unwrap()).unwrap();
+        let program = Program::compile(&tree, &[]).unwrap();
+        assert_eq!(program.run(&env, &[]), tree.eval(&env), "{}", expression);
+    }
+}
+
+#[test]
+fn errors_point_at_the_failing_instruction() {
+    let env = HashMap::<String, f64>::new();
+    let error = compile("1 + f(2)", &[]).run(&env, &[]).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnknownVariable);
+    assert_eq!(error.span, Some((4, 8)));
+
+    let error = compile("x / 0", &["x"])
+        .run(&env, &[Value::Number(1.0)])
+        .unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DivideByZero);
+    assert_eq!(error.span, Some((0, 5)));
+}
+
+#[test]
+fn long_sums_keep_a_running_total() {
+    let mut tokens = vec![Token::new_number("1")];
+    for _ in 1..5000 {
+        tokens.extend([Token::new_op("+"), Token::new_variable("x")]);
+    }
+    let tree = AST::build_tree(&tokens).unwrap();
+    let program = Program::compile(&tree, &["x".to_owned()]).unwrap();
+    assert_eq!(program.max_stack, 2);
+    let env = HashMap::<String, f64>::new();
+    assert_eq!(
+        program.run(&env, &[Value::Number(2.0)]),
+        Ok(Value::Number(9999.0))
+    );
+}
+
+#[test]
+fn comparisons_push_one_or_zero() {
+    let program = compile("x >= 2", &["x"]);
+    assert_eq!(
+        program.code(),
+        &[
+            Instruction::Load(0),
+            Instruction::Const(0),
+            Instruction::Compare(Cmp::GreaterEqual),
+        ]
+    );
+    let env = HashMap::<String, f64>::new();
+    assert_eq!(
+        program.run(&env, &[Value::Integer(2)]),
+        Ok(Value::Integer(1))
+    );
+    assert_eq!(
+        program.run(&env, &[Value::Number(1.5)]),
+        Ok(Value::Integer(0))
+    );
+}